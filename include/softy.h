@@ -0,0 +1,83 @@
+/*
+ * C interface to softy, for calling it as a golden model from testbenches (see include/softy_dpi.svh for
+ * SystemVerilog DPI-C). Link against the cdylib (libsofty.so) or staticlib (libsofty.a) built by cargo.
+ *
+ * Formats are given as their exponent and significand widths and special encoding. Modes are packed into one word:
+ * rounding mode, flush to zero, denormals are zero, tininess, overflow and NaN modes, and (for stochastic rounding) the
+ * number of random bits, which are taken from random_bits. Each function returns the raw result bits and, if flags
+ * isn't null, writes the flags raised (in SoftFloat's bit positions). Invalid arguments return 0 with flags set to
+ * SOFTY_FLAG_ERROR.
+ *
+ * Hand-maintained: keep in sync with src/ffi.rs.
+ */
+
+#ifndef SOFTY_H
+#define SOFTY_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Rounding mode, in bits 0-2 of modes */
+#define SOFTY_MODE_ROUNDING_MASK 0x7u
+#define SOFTY_ROUND_NEAREST_EVEN 0x0u
+#define SOFTY_ROUND_NEAREST_AWAY 0x1u
+#define SOFTY_ROUND_TOWARD_ZERO 0x2u
+#define SOFTY_ROUND_TOWARD_POSITIVE 0x3u
+#define SOFTY_ROUND_TOWARD_NEGATIVE 0x4u
+#define SOFTY_ROUND_STOCHASTIC 0x5u
+
+#define SOFTY_MODE_FLUSH_TO_ZERO 0x8u
+#define SOFTY_MODE_DENORMALS_ARE_ZERO 0x10u
+#define SOFTY_MODE_TININESS_AFTER_ROUNDING 0x20u
+#define SOFTY_MODE_SATURATE 0x40u
+
+/* NaN mode, in bits 7-8 of modes */
+#define SOFTY_MODE_NAN_SHIFT 0x7u
+#define SOFTY_NAN_CANONICAL 0x0u
+#define SOFTY_NAN_PROPAGATE_FIRST 0x1u
+#define SOFTY_NAN_X86 0x2u
+#define SOFTY_NAN_ARM 0x3u
+
+/* Rounding from the exact result, as IEEE 754 specifies (rather than as the hardware does) */
+#define SOFTY_MODE_IEEE_ROUNDING 0x200u
+
+/* Number of random bits for stochastic rounding (1-32), in bits 16-21 of modes */
+#define SOFTY_MODE_NUM_RANDOM_BITS_SHIFT 0x10u
+
+/* Special encodings */
+#define SOFTY_SPECIAL_ENCODING_IEEE 0x0u
+#define SOFTY_SPECIAL_ENCODING_NAN_ONLY 0x1u
+#define SOFTY_SPECIAL_ENCODING_FINITE_ONLY 0x2u
+
+/* Flags */
+#define SOFTY_FLAG_INEXACT 0x1u
+#define SOFTY_FLAG_UNDERFLOW 0x2u
+#define SOFTY_FLAG_OVERFLOW 0x4u
+#define SOFTY_FLAG_DIVIDE_BY_ZERO 0x8u
+#define SOFTY_FLAG_INVALID 0x10u
+#define SOFTY_FLAG_ERROR 0x80000000u
+
+uint32_t softy_addition(uint32_t num_exp_bits, uint32_t num_sig_bits, uint32_t special_encoding, uint32_t modes,
+                        uint32_t random_bits, uint32_t source1, uint32_t source2, uint32_t *flags);
+
+uint32_t softy_multiplication(uint32_t num_exp_bits, uint32_t num_sig_bits, uint32_t special_encoding, uint32_t modes,
+                              uint32_t random_bits, uint32_t source1, uint32_t source2, uint32_t *flags);
+
+uint32_t softy_fused_multiply_add(uint32_t num_exp_bits, uint32_t num_sig_bits, uint32_t special_encoding,
+                                  uint32_t modes, uint32_t random_bits, uint32_t source1, uint32_t source2,
+                                  uint32_t source3, uint32_t *flags);
+
+/* From the source format to the target format */
+uint32_t softy_conversion(uint32_t num_exp_bits, uint32_t num_sig_bits, uint32_t special_encoding,
+                          uint32_t target_num_exp_bits, uint32_t target_num_sig_bits,
+                          uint32_t target_special_encoding, uint32_t modes, uint32_t random_bits, uint32_t source,
+                          uint32_t *flags);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SOFTY_H */
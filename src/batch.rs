@@ -0,0 +1,343 @@
+// Ops over slices of raw encodings, for pushing many values through one format. Per-format constants are derived once
+// per batch rather than once per op, finite sources skip building Values, and work can be split across threads. Every
+// result (and flag) matches the scalar op's exactly; special sources (NaN, infinity) take the scalar op's path.
+
+use std::thread;
+
+use crate::context::*;
+use crate::flags::*;
+use crate::format::*;
+use crate::ops::addition::*;
+use crate::ops::multiplication::*;
+use crate::rounding::*;
+use crate::value::*;
+
+// Constants of a format, derived once for a whole batch
+#[derive(Clone, Debug)]
+struct FormatConstants {
+    format: Format,
+    sign_shift: u32,
+    exp_max: u32,
+    sig_mask: u32,
+    hidden_bit: u32,
+    min_exp: i32,
+}
+
+impl FormatConstants {
+    fn new(format: &Format) -> FormatConstants {
+        FormatConstants {
+            format: format.clone(),
+            sign_shift: format.num_exp_bits + format.num_sig_bits,
+            exp_max: format.exp_max(),
+            sig_mask: format.sig_mask(),
+            hidden_bit: 1 << format.num_sig_bits,
+            min_exp: format.min_normal_exp() - format.num_sig_bits as i32,
+        }
+    }
+
+    // Sign, full sig and exp (as in Value::decode_finite) of a finite encoding, with denormals read as zero if the
+    // context asks; None for NaNs and infinities
+    fn decode_finite(&self, bits: u32, context: &Context) -> Option<(bool, u32, i32)> {
+        let sign = (bits >> self.sign_shift) & 1 != 0;
+        let exp = (bits >> self.format.num_sig_bits) & self.exp_max;
+        let sig = bits & self.sig_mask;
+
+        let is_special = exp == self.exp_max
+            && match self.format.special_encoding {
+                SpecialEncoding::Ieee => true,
+                SpecialEncoding::NanOnly => sig == self.sig_mask,
+                SpecialEncoding::FiniteOnly => false,
+            };
+        if is_special {
+            return None;
+        }
+
+        if exp == 0 {
+            Some((sign, if context.denormals_are_zero { 0 } else { sig }, self.min_exp))
+        } else {
+            Some((sign, self.hidden_bit | sig, self.min_exp + exp as i32 - 1))
+        }
+    }
+
+    fn addition(&self, source1: u32, source2: u32, context: &mut Context) -> u32 {
+        let (lhs, rhs) = match (self.decode_finite(source1, context), self.decode_finite(source2, context)) {
+            (Some(lhs), Some(rhs)) => (lhs, rhs),
+            _ => {
+                let (source1, source2) = (Value::from_bits(source1, self.format.clone()), Value::from_bits(source2, self.format.clone()));
+                return addition_with_context(source1, source2, context).to_bits();
+            }
+        };
+
+        // Swap as in addition's swap stage, then run the remaining stages
+        let (lhs, rhs) = if lhs.2 < rhs.2 || (lhs.2 == rhs.2 && lhs.1 < rhs.1) { (rhs, lhs) } else { (lhs, rhs) };
+        let swapped = Swapped {
+            lhs_sign: lhs.0,
+            lhs_sig: lhs.1,
+            lhs_exp: lhs.2,
+            rhs_sign: rhs.0,
+            rhs_sig: rhs.1,
+            rhs_exp: rhs.2,
+            format: self.format.clone(),
+        };
+        let sum = add(&negate(&align(&swapped, context)), context);
+        round(sum.sign, sum.sig, sum.exp, &sum.format, context).to_bits()
+    }
+
+    fn multiplication(&self, source1: u32, source2: u32, context: &mut Context) -> u32 {
+        let (lhs, rhs) = match (self.decode_finite(source1, context), self.decode_finite(source2, context)) {
+            (Some(lhs), Some(rhs)) => (lhs, rhs),
+            _ => {
+                let (source1, source2) = (Value::from_bits(source1, self.format.clone()), Value::from_bits(source2, self.format.clone()));
+                return multiplication_with_context(source1, source2, context).to_bits();
+            }
+        };
+
+        // Zeros are special to multiplication's decode stage
+        let sign = lhs.0 ^ rhs.0;
+        if lhs.1 == 0 || rhs.1 == 0 {
+            return (sign as u32) << self.sign_shift;
+        }
+        let decoded = Decoded { sign, lhs_sig: lhs.1, lhs_exp: lhs.2, rhs_sig: rhs.1, rhs_exp: rhs.2, format: self.format.clone() };
+        let product = multiply(&decoded);
+        round(product.sign, product.sig, product.exp, &product.format, context).to_bits()
+    }
+}
+
+// A batch evaluator for one format
+#[derive(Clone, Debug)]
+pub struct Batch {
+    constants: FormatConstants,
+    num_threads: usize,
+}
+
+// Below this many results per thread, spawning threads costs more than it saves
+const MIN_RESULTS_PER_THREAD: usize = 4096;
+
+impl Batch {
+    pub fn new(format: &Format) -> Batch {
+        Batch {
+            constants: FormatConstants::new(format),
+            num_threads: 1,
+        }
+    }
+
+    // Splits batches across up to num_threads threads (stochastic rounding always runs on the calling thread, drawing
+    // random bits in order)
+    pub fn with_num_threads(self, num_threads: usize) -> Batch {
+        if num_threads == 0 {
+            panic!("Requested batch with 0 threads, but it must use at least 1 thread.");
+        }
+
+        Batch {
+            num_threads,
+            ..self
+        }
+    }
+
+    pub fn format(&self) -> &Format {
+        &self.constants.format
+    }
+
+    pub fn num_threads(&self) -> usize {
+        self.num_threads
+    }
+
+    // results[i] = sources1[i] + sources2[i], with flags[i] the flags raised by that op (if flags are wanted); every
+    // flag raised is also raised on context
+    pub fn addition(&self, sources1: &[u32], sources2: &[u32], results: &mut [u32], flags: Option<&mut [Flags]>, context: &mut Context) {
+        self.evaluate("addition", FormatConstants::addition, sources1, sources2, results, flags, context);
+    }
+
+    // results[i] = sources1[i] * sources2[i], as with addition
+    pub fn multiplication(&self, sources1: &[u32], sources2: &[u32], results: &mut [u32], flags: Option<&mut [Flags]>, context: &mut Context) {
+        self.evaluate("multiplication", FormatConstants::multiplication, sources1, sources2, results, flags, context);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn evaluate(
+        &self,
+        name: &str,
+        op: fn(&FormatConstants, u32, u32, &mut Context) -> u32,
+        sources1: &[u32],
+        sources2: &[u32],
+        results: &mut [u32],
+        flags: Option<&mut [Flags]>,
+        context: &mut Context,
+    ) {
+        let num_results = results.len();
+        if sources1.len() != num_results || sources2.len() != num_results || flags.as_ref().is_some_and(|flags| flags.len() != num_results) {
+            panic!(
+                "Requested batch {} of {} and {} sources into {} results, but every slice must have the same length.",
+                name,
+                sources1.len(),
+                sources2.len(),
+                num_results
+            );
+        }
+
+        let num_threads = match context.rounding_mode {
+            RoundingMode::Stochastic { .. } => 1,
+            _ => self.num_threads.min(num_results / MIN_RESULTS_PER_THREAD).max(1),
+        };
+        if num_threads == 1 {
+            let raised = evaluate_chunk(&self.constants, op, sources1, sources2, results, flags, context);
+            context.raise(raised);
+            return;
+        }
+
+        let chunk_len = num_results.div_ceil(num_threads);
+        let modes = Modes::new(context);
+        let mut flag_chunks = match flags {
+            Some(flags) => flags.chunks_mut(chunk_len).map(Some).collect::<Vec<_>>(),
+            None => (0..num_threads).map(|_| None).collect(),
+        };
+        let raised = thread::scope(|scope| {
+            let handles = results
+                .chunks_mut(chunk_len)
+                .zip(sources1.chunks(chunk_len).zip(sources2.chunks(chunk_len)))
+                .zip(flag_chunks.iter_mut())
+                .map(|((results, (sources1, sources2)), flags)| {
+                    let constants = &self.constants;
+                    let flags = flags.take();
+                    scope.spawn(move || evaluate_chunk(constants, op, sources1, sources2, results, flags, &mut modes.context()))
+                })
+                .collect::<Vec<_>>();
+            handles.into_iter().fold(Flags::empty(), |raised, handle| raised | handle.join().unwrap())
+        });
+        context.raise(raised);
+    }
+}
+
+// Evaluates a chunk on context (whose own flags are left untouched), returning the union of the flags raised
+fn evaluate_chunk(
+    constants: &FormatConstants,
+    op: fn(&FormatConstants, u32, u32, &mut Context) -> u32,
+    sources1: &[u32],
+    sources2: &[u32],
+    results: &mut [u32],
+    mut flags: Option<&mut [Flags]>,
+    context: &mut Context,
+) -> Flags {
+    let previous_flags = context.flags();
+    let mut raised = Flags::empty();
+    for i in 0..results.len() {
+        context.clear_flags();
+        results[i] = op(constants, sources1[i], sources2[i], context);
+        raised |= context.flags();
+        if let Some(flags) = flags.as_mut() {
+            flags[i] = context.flags();
+        }
+    }
+    context.clear_flags();
+    context.raise(previous_flags);
+    raised
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ops::*;
+
+    // Every pair of encodings of an 8-bit format
+    fn all_pairs() -> (Vec<u32>, Vec<u32>) {
+        (0..0x10000).map(|pair| (pair >> 8, pair & 0xff)).unzip()
+    }
+
+    fn contexts() -> Vec<Context> {
+        vec![
+            Context::new(),
+            Context::new().with_rounding_mode(RoundingMode::NearestEven).with_flush_to_zero(false).with_denormals_are_zero(false),
+            Context::new()
+                .with_rounding_mode(RoundingMode::TowardNegative)
+                .with_flush_to_zero(false)
+                .with_tininess_mode(TininessMode::BeforeRounding)
+                .with_nan_mode(NanMode::PropagateFirst),
+            Context::new().with_rounding_mode(RoundingMode::NearestAway).with_overflow_mode(OverflowMode::Saturate).with_nan_mode(NanMode::X86),
+        ]
+    }
+
+    // Compares a batch op against the scalar op over every pair of encodings, with and without threads
+    fn check(format: &Format, op: Op) {
+        let (sources1, sources2) = all_pairs();
+        for context in contexts() {
+            let mut expected_context = Modes::new(&context).context();
+            let expected = sources1
+                .iter()
+                .zip(&sources2)
+                .map(|(&source1, &source2)| {
+                    expected_context.clear_flags();
+                    let result = op.evaluate(&[source1, source2], format, &mut expected_context);
+                    (result.to_bits(), expected_context.flags())
+                })
+                .collect::<Vec<_>>();
+            let expected_raised = expected.iter().fold(Flags::empty(), |raised, &(_, flags)| raised | flags);
+
+            for num_threads in [1, 4] {
+                let batch = Batch::new(format).with_num_threads(num_threads);
+                let mut actual_context = Modes::new(&context).context();
+                let mut results = vec![0; sources1.len()];
+                let mut flags = vec![Flags::empty(); sources1.len()];
+
+                match op {
+                    Op::Addition => batch.addition(&sources1, &sources2, &mut results, Some(&mut flags), &mut actual_context),
+                    Op::Multiplication => batch.multiplication(&sources1, &sources2, &mut results, Some(&mut flags), &mut actual_context),
+                    _ => unreachable!(),
+                }
+
+                for (i, &(expected_result, expected_flags)) in expected.iter().enumerate() {
+                    assert_eq!((results[i], flags[i]), (expected_result, expected_flags), "{} {:02x} {:02x}", format, sources1[i], sources2[i]);
+                }
+                assert_eq!(actual_context.flags(), expected_raised);
+            }
+        }
+    }
+
+    #[test]
+    fn addition_matches_scalar() {
+        for format in ["e4m3fn", "e5m2", "e3m4+finite"] {
+            check(&format.parse().unwrap(), Op::Addition);
+        }
+    }
+
+    #[test]
+    fn multiplication_matches_scalar() {
+        for format in ["e4m3fn", "e5m2", "e3m4+finite"] {
+            check(&format.parse().unwrap(), Op::Multiplication);
+        }
+    }
+
+    #[test]
+    fn stochastic_rounding_matches_scalar() {
+        let f: Format = "bf16".parse().unwrap();
+        let sources1 = (0..1000).map(|i| 0x3f80 + i).collect::<Vec<_>>();
+        let sources2 = (0..1000).map(|i| 0x3b00 + 7 * i).collect::<Vec<_>>();
+        let random_source = || {
+            let mut state = 1u32;
+            move |num_bits: u32| {
+                state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+                state >> (32 - num_bits)
+            }
+        };
+        let rounding_mode = RoundingMode::Stochastic { num_random_bits: 8 };
+
+        let mut context = Context::new().with_rounding_mode(rounding_mode).with_random_source(random_source());
+        let expected = sources1
+            .iter()
+            .zip(&sources2)
+            .map(|(&source1, &source2)| addition_with_context(Value::from_bits(source1, f.clone()), Value::from_bits(source2, f.clone()), &mut context).to_bits())
+            .collect::<Vec<_>>();
+
+        let mut context = Context::new().with_rounding_mode(rounding_mode).with_random_source(random_source());
+        let mut results = vec![0; sources1.len()];
+        Batch::new(&f).with_num_threads(4).addition(&sources1, &sources2, &mut results, None, &mut context);
+
+        assert_eq!(results, expected);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested batch addition of 2 and 1 sources into 2 results, but every slice must have the same length.")]
+    fn mismatched_lengths() {
+        Batch::new(&Format::ieee754_single()).addition(&[0, 0], &[0], &mut [0, 0], None, &mut Context::new());
+    }
+}
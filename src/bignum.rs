@@ -0,0 +1,310 @@
+use std::cmp::Ordering;
+
+// Arbitrary-precision unsigned integer, just enough for exact decimal conversion (little-endian 32-bit limbs)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    pub(crate) fn from_u64(x: u64) -> BigUint {
+        let mut res = BigUint { limbs: vec![x as u32, (x >> 32) as u32] };
+        res.normalize();
+        res
+    }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    pub(crate) fn mul_small(&mut self, x: u32) {
+        let mut carry = 0u64;
+        for limb in self.limbs.iter_mut() {
+            let product = (*limb as u64) * (x as u64) + carry;
+            *limb = product as u32;
+            carry = product >> 32;
+        }
+        if carry != 0 {
+            self.limbs.push(carry as u32);
+        }
+        self.normalize();
+    }
+
+    pub(crate) fn mul_pow2(&mut self, n: u32) {
+        if self.is_zero() {
+            return;
+        }
+
+        let limb_shift = (n / 32) as usize;
+        let bit_shift = n % 32;
+        if bit_shift != 0 {
+            let mut carry = 0;
+            for limb in self.limbs.iter_mut() {
+                let shifted = ((*limb as u64) << bit_shift) | carry;
+                *limb = shifted as u32;
+                carry = shifted >> 32;
+            }
+            if carry != 0 {
+                self.limbs.push(carry as u32);
+            }
+        }
+        self.limbs.splice(0..0, (0..limb_shift).map(|_| 0));
+    }
+
+    pub(crate) fn mul_pow5(&mut self, mut n: u32) {
+        // 5^13 is the largest power of 5 that fits in a limb
+        while n >= 13 {
+            self.mul_small(1220703125);
+            n -= 13;
+        }
+        self.mul_small(5u32.pow(n));
+    }
+
+    pub(crate) fn mul_pow10(&mut self, n: u32) {
+        self.mul_pow5(n);
+        self.mul_pow2(n);
+    }
+
+    pub(crate) fn mul(&self, other: &BigUint) -> BigUint {
+        let mut limbs = vec![0u32; self.limbs.len() + other.limbs.len()];
+        for (i, &x) in self.limbs.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, &y) in other.limbs.iter().enumerate() {
+                let product = limbs[i + j] as u64 + (x as u64) * (y as u64) + carry;
+                limbs[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            limbs[i + other.limbs.len()] = carry as u32;
+        }
+        let mut res = BigUint { limbs };
+        res.normalize();
+        res
+    }
+
+    pub(crate) fn shr(&self, n: u32) -> BigUint {
+        let limb_shift = (n / 32) as usize;
+        let bit_shift = n % 32;
+        if limb_shift >= self.limbs.len() {
+            return BigUint { limbs: Vec::new() };
+        }
+
+        let mut limbs = self.limbs[limb_shift..].to_vec();
+        if bit_shift != 0 {
+            for i in 0..limbs.len() {
+                let hi = limbs.get(i + 1).map_or(0, |&hi| hi << (32 - bit_shift));
+                limbs[i] = (limbs[i] >> bit_shift) | hi;
+            }
+        }
+        let mut res = BigUint { limbs };
+        res.normalize();
+        res
+    }
+
+    // self mod 2^n
+    pub(crate) fn low_bits(&self, n: u32) -> BigUint {
+        let num_limbs = (n as usize).div_ceil(32).min(self.limbs.len());
+        let mut limbs = self.limbs[..num_limbs].to_vec();
+        if num_limbs == (n as usize).div_ceil(32) && n % 32 != 0 {
+            limbs[num_limbs - 1] &= (1 << (n % 32)) - 1;
+        }
+        let mut res = BigUint { limbs };
+        res.normalize();
+        res
+    }
+
+    // Low 64 bits
+    pub(crate) fn to_u64(&self) -> u64 {
+        let lo = *self.limbs.first().unwrap_or(&0) as u64;
+        let hi = *self.limbs.get(1).unwrap_or(&0) as u64;
+        (hi << 32) | lo
+    }
+
+    pub(crate) fn add(&self, other: &BigUint) -> BigUint {
+        let len = self.limbs.len().max(other.limbs.len());
+        let mut limbs = Vec::with_capacity(len + 1);
+        let mut carry = 0u64;
+        for i in 0..len {
+            let sum = *self.limbs.get(i).unwrap_or(&0) as u64 + *other.limbs.get(i).unwrap_or(&0) as u64 + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        limbs.push(carry as u32);
+        let mut res = BigUint { limbs };
+        res.normalize();
+        res
+    }
+
+    // Requires self >= other
+    pub(crate) fn sub_assign(&mut self, other: &BigUint) {
+        let mut borrow = 0i64;
+        for i in 0..self.limbs.len() {
+            let diff = self.limbs[i] as i64 - *other.limbs.get(i).unwrap_or(&0) as i64 - borrow;
+            self.limbs[i] = diff as u32;
+            borrow = if diff < 0 { 1 } else { 0 };
+        }
+        self.normalize();
+    }
+
+    // Divides by other, assuming the quotient is a single decimal digit; self becomes the remainder
+    pub(crate) fn div_rem_digit(&mut self, other: &BigUint) -> u32 {
+        let mut digit = 0;
+        while *self >= *other {
+            self.sub_assign(other);
+            digit += 1;
+        }
+        digit
+    }
+
+    pub(crate) fn bit_len(&self) -> u32 {
+        match self.limbs.last() {
+            Some(top) => 32 * self.limbs.len() as u32 - top.leading_zeros(),
+            None => 0,
+        }
+    }
+
+    // Divides by other, assuming the quotient fits in 128 bits; self becomes the remainder
+    pub(crate) fn div_rem(&mut self, other: &BigUint) -> u128 {
+        if *self < *other {
+            return 0;
+        }
+
+        let mut quotient = 0;
+        for i in (0..=(self.bit_len() - other.bit_len())).rev() {
+            let mut shifted = other.clone();
+            shifted.mul_pow2(i);
+            if *self >= shifted {
+                self.sub_assign(&shifted);
+                quotient |= 1 << i;
+            }
+        }
+        quotient
+    }
+
+    fn div_rem_small(&mut self, x: u32) -> u32 {
+        let mut rem = 0u64;
+        for limb in self.limbs.iter_mut().rev() {
+            let dividend = (rem << 32) | (*limb as u64);
+            *limb = (dividend / x as u64) as u32;
+            rem = dividend % x as u64;
+        }
+        self.normalize();
+        rem as u32
+    }
+
+    pub(crate) fn to_decimal_string(&self) -> String {
+        if self.is_zero() {
+            return "0".to_string();
+        }
+
+        // Peel off 9 digits at a time, least significant chunk first
+        let mut x = self.clone();
+        let mut chunks = Vec::new();
+        while !x.is_zero() {
+            chunks.push(x.div_rem_small(1_000_000_000));
+        }
+
+        let mut res = chunks.pop().unwrap().to_string();
+        for chunk in chunks.iter().rev() {
+            res.push_str(&format!("{:09}", chunk));
+        }
+        res
+    }
+
+    fn normalize(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &BigUint) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &BigUint) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_decimal_string() {
+        assert_eq!(BigUint::from_u64(0).to_decimal_string(), "0");
+        assert_eq!(BigUint::from_u64(1337).to_decimal_string(), "1337");
+        assert_eq!(BigUint::from_u64(u64::MAX).to_decimal_string(), "18446744073709551615");
+
+        let mut x = BigUint::from_u64(1);
+        x.mul_pow2(100);
+
+        assert_eq!(x.to_decimal_string(), "1267650600228229401496703205376");
+
+        let mut x = BigUint::from_u64(3);
+        x.mul_pow10(20);
+
+        assert_eq!(x.to_decimal_string(), "300000000000000000000");
+    }
+
+    #[test]
+    fn arithmetic() {
+        let mut a = BigUint::from_u64(u64::MAX);
+        let b = BigUint::from_u64(1);
+
+        let sum = a.add(&b);
+
+        assert_eq!(sum.to_decimal_string(), "18446744073709551616");
+
+        a.mul_small(10);
+        a.sub_assign(&BigUint::from_u64(5));
+
+        assert_eq!(a.to_decimal_string(), "184467440737095516145");
+
+        let mut r = BigUint::from_u64(97);
+
+        assert_eq!(r.div_rem_digit(&BigUint::from_u64(10)), 9);
+        assert_eq!(r, BigUint::from_u64(7));
+        assert!(BigUint::from_u64(7) < sum);
+    }
+
+    #[test]
+    fn mul_shr_low_bits() {
+        let mut x = BigUint::from_u64(0xdeadbeefcafe);
+        x.mul_pow2(70);
+        x = x.add(&BigUint::from_u64(0x1234));
+
+        let y = x.mul(&BigUint::from_u64(1000));
+
+        assert_eq!(y.to_decimal_string(), "289053471754840256672882810326880820000");
+        assert_eq!(x.shr(70).to_u64(), 0xdeadbeefcafe);
+        assert_eq!(x.shr(74).to_u64(), 0xdeadbeefcaf);
+        assert!(x.shr(118).is_zero());
+        assert_eq!(x.low_bits(70).to_u64(), 0x1234);
+        assert_eq!(x.low_bits(8).to_u64(), 0x34);
+        assert_eq!(x.low_bits(1000), x);
+        assert!(BigUint::from_u64(0).mul(&x).is_zero());
+    }
+
+    #[test]
+    fn div_rem() {
+        let mut x = BigUint::from_u64(1);
+        x.mul_pow10(30);
+        x = x.add(&BigUint::from_u64(7));
+
+        let mut y = BigUint::from_u64(1);
+        y.mul_pow10(10);
+
+        assert_eq!(x.bit_len(), 100);
+        assert_eq!(x.div_rem(&y), 100000000000000000000);
+        assert_eq!(x, BigUint::from_u64(7));
+        assert_eq!(x.div_rem(&y), 0);
+        assert_eq!(x, BigUint::from_u64(7));
+    }
+}
@@ -0,0 +1,52 @@
+// Golden-model server (see softy::server for the protocol): serves stdin/stdout, or on Unix with --socket <path>, each
+// connection to a Unix socket at path
+
+use std::env;
+use std::io;
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+use std::process;
+#[cfg(unix)]
+use std::thread;
+
+use softy::server::*;
+
+#[cfg(unix)]
+const USAGE: &str = "Usage: softy-server [--socket <path>]";
+#[cfg(not(unix))]
+const USAGE: &str = "Usage: softy-server";
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let result = match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        [] => serve(io::stdin().lock(), io::stdout().lock()),
+        #[cfg(unix)]
+        ["--socket", path] => serve_socket(path),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(error) = result {
+        eprintln!("softy-server: {}", error);
+        process::exit(1);
+    }
+}
+
+#[cfg(unix)]
+fn serve_socket(path: &str) -> io::Result<()> {
+    let listener = UnixListener::bind(path)?;
+    for stream in listener.incoming() {
+        let stream = stream?;
+        thread::spawn(move || {
+            let reader = match stream.try_clone() {
+                Ok(reader) => reader,
+                Err(error) => return eprintln!("softy-server: {}", error),
+            };
+            if let Err(error) = serve(reader, stream) {
+                eprintln!("softy-server: {}", error);
+            }
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,307 @@
+// The softy command-line calculator, for evaluating single ops and inspecting encodings without writing code, eg.
+//
+//   softy add --format bf16 0x3f80 1.5
+//   softy decode --format e4m3fn 0x7e
+//   softy encode --format fp16 0.1
+//
+// Operands are encodings when written as hex integers ("0x3f80"), and otherwise literals (as accepted by Value::parse,
+// eg. "1.5", "-0x1.8p+1", "inf" or "nan"), rounded into the format. Unlike Context::new, the modes default to IEEE
+// behavior: IEEE rounding to nearest even, without flushing denormals.
+
+use std::io::Write;
+
+use crate::context::*;
+use crate::flags::*;
+use crate::format::*;
+use crate::ops::*;
+use crate::rounding::*;
+use crate::value::*;
+
+pub const USAGE: &str = "\
+Usage: softy <command> [options] <operand>...
+
+Commands:
+  add <a> <b>          a + b
+  mul <a> <b>          a * b
+  fma <a> <b> <c>      a * b + c, rounded once
+  convert <a>          a, converted to the --to format
+  decode <bits>...     fields and decimal values of encodings
+  encode <value>...    encodings of values, rounded into the format
+
+Options:
+  --format <spec>      format of operands (eg. fp32, fp16, bf16, e4m3fn, e5m10+bias14); default fp32
+  --to <spec>          target format for convert
+  --rounding <mode>    nearest_even (default), nearest_away, toward_zero, toward_positive, toward_negative or
+                       stochastic<n> (with n random bits)
+  --random-bits <hex>  random bits for stochastic rounding; default 0
+  --ftz                flush tiny results to zero
+  --daz                read denormal inputs as zero
+  --tininess <mode>    before_rounding (default) or after_rounding
+  --overflow <mode>    ieee (default) or saturate
+  --nan-mode <mode>    canonical (default), propagate_first, x86 or arm
+";
+
+struct Options {
+    format: Format,
+    target_format: Option<Format>,
+    rounding_mode: RoundingMode,
+    random_bits: u32,
+    flush_to_zero: bool,
+    denormals_are_zero: bool,
+    tininess_mode: TininessMode,
+    overflow_mode: OverflowMode,
+    nan_mode: NanMode,
+}
+
+impl Options {
+    fn context(&self) -> Context {
+        let random_bits = self.random_bits;
+        Context::new()
+            .with_rounding_mode(self.rounding_mode)
+            .with_random_source(move |_| random_bits)
+            .with_flush_to_zero(self.flush_to_zero)
+            .with_denormals_are_zero(self.denormals_are_zero)
+            .with_tininess_mode(self.tininess_mode)
+            .with_overflow_mode(self.overflow_mode)
+            .with_nan_mode(self.nan_mode)
+            .with_ieee_rounding(true)
+    }
+}
+
+// Splits arguments (after the command) into options and operands
+fn parse_args(args: &[String]) -> Result<(Options, Vec<String>), String> {
+    let mut options = Options {
+        format: Format::ieee754_single(),
+        target_format: None,
+        rounding_mode: RoundingMode::NearestEven,
+        random_bits: 0,
+        flush_to_zero: false,
+        denormals_are_zero: false,
+        tininess_mode: TininessMode::BeforeRounding,
+        overflow_mode: OverflowMode::Ieee,
+        nan_mode: NanMode::Canonical,
+    };
+    let mut operands = Vec::new();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            operands.push(arg.clone());
+            continue;
+        }
+
+        let mut value = || args.next().ok_or_else(|| format!("Missing value for {}.", arg));
+        let invalid = |value: &str| format!("Invalid value \"{}\" for {}.", value, arg);
+        match arg.as_str() {
+            "--format" => options.format = value()?.parse().map_err(|error: FormatError| error.to_string())?,
+            "--to" => options.target_format = Some(value()?.parse().map_err(|error: FormatError| error.to_string())?),
+            "--rounding" => {
+                let value = value()?;
+                options.rounding_mode = value.parse().map_err(|_| invalid(value))?;
+            }
+            "--random-bits" => {
+                let value = value()?;
+                let digits = value.strip_prefix("0x").unwrap_or(value);
+                options.random_bits = u32::from_str_radix(digits, 16).map_err(|_| invalid(value))?;
+            }
+            "--ftz" => options.flush_to_zero = true,
+            "--daz" => options.denormals_are_zero = true,
+            "--tininess" => {
+                let value = value()?;
+                options.tininess_mode = value.parse().map_err(|_| invalid(value))?;
+            }
+            "--overflow" => {
+                let value = value()?;
+                options.overflow_mode = value.parse().map_err(|_| invalid(value))?;
+            }
+            "--nan-mode" => {
+                let value = value()?;
+                options.nan_mode = value.parse().map_err(|_| invalid(value))?;
+            }
+            _ => return Err(format!("Unknown option {}.", arg)),
+        }
+    }
+    Ok((options, operands))
+}
+
+// An operand's encoding: hex integers are taken as bits, anything else is parsed as a literal
+fn parse_operand(operand: &str, format: &Format, context: &mut Context) -> Result<u32, String> {
+    let digits = operand.strip_prefix("0x").or_else(|| operand.strip_prefix("0X"));
+    if let Some(digits) = digits.filter(|digits| !digits.is_empty() && digits.bytes().all(|c| c.is_ascii_hexdigit())) {
+        return match u32::from_str_radix(digits, 16) {
+            Ok(bits) if (bits as u64) >> format.num_storage_bits() == 0 => Ok(bits),
+            _ => Err(format!("Encoding {} doesn't fit in {} ({} bits).", operand, format, format.num_storage_bits())),
+        };
+    }
+
+    Value::parse(operand, format.clone(), context)
+        .map(|value| value.to_bits())
+        .map_err(|error| format!("Invalid operand \"{}\": {}", operand, error))
+}
+
+// Eg. "0x3fc0 (sign 0, exp 0x7f, sig 0x40) = 1.5 [PositiveNormal]"
+fn describe(bits: u32, format: &Format) -> String {
+    let value = Value::from_bits(bits, format.clone());
+    format!("{} = {} [{:?}]", value.to_fields_string(), value, value.classify())
+}
+
+fn flag_names(flags: Flags) -> String {
+    let names = Flags::NAMES.iter().filter(|(flag, _)| flags.contains(*flag)).map(|(_, name)| *name).collect::<Vec<_>>();
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(" | ")
+    }
+}
+
+// Runs the command in args (not including the program name), writing its output to out
+pub fn run<W: Write>(args: &[String], out: &mut W) -> Result<(), String> {
+    let write_error = |error: std::io::Error| error.to_string();
+
+    let command = args.first().ok_or_else(|| USAGE.to_string())?;
+    // Help anywhere wins over the rest of the arguments, eg. "softy add --help"
+    if command == "help" || args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        return out.write_all(USAGE.as_bytes()).map_err(write_error);
+    }
+    let (options, operands) = parse_args(&args[1..])?;
+    let format = &options.format;
+
+    let op = match command.as_str() {
+        "add" => Op::Addition,
+        "mul" => Op::Multiplication,
+        "fma" => Op::FusedMultiplyAdd,
+        "convert" => Op::Conversion(options.target_format.clone().ok_or("convert requires --to <spec>.")?),
+        "decode" | "encode" => {
+            if operands.is_empty() {
+                return Err(format!("{} takes at least 1 operand.", command));
+            }
+
+            for operand in &operands {
+                let mut context = options.context();
+                let bits = if command == "decode" {
+                    parse_operand(operand, format, &mut context)?
+                } else {
+                    let value = Value::parse(operand, format.clone(), &mut context);
+                    value.map_err(|error| format!("Invalid operand \"{}\": {}", operand, error))?.to_bits()
+                };
+                writeln!(out, "{}: {}", format, describe(bits, format)).map_err(write_error)?;
+                writeln!(out, "  exact: {}", Value::from_bits(bits, format.clone()).to_exact_string()).map_err(write_error)?;
+                if command == "encode" {
+                    writeln!(out, "  flags: {}", flag_names(context.flags())).map_err(write_error)?;
+                }
+            }
+            return Ok(());
+        }
+        _ => return Err(format!("Unknown command \"{}\".\n\n{}", command, USAGE)),
+    };
+
+    if operands.len() != op.num_sources() {
+        return Err(format!("{} takes {} operands, but got {}.", command, op.num_sources(), operands.len()));
+    }
+    let sources = operands
+        .iter()
+        .map(|operand| parse_operand(operand, format, &mut options.context()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (i, &source) in sources.iter().enumerate() {
+        writeln!(out, "source{}: {}", i + 1, describe(source, format)).map_err(write_error)?;
+    }
+    let mut context = options.context();
+    let result = op.evaluate(&sources, format, &mut context);
+    let result_format = op.result_format(format);
+    writeln!(out, "result:  {}", describe(result.to_bits(), &result_format)).map_err(write_error)?;
+    writeln!(out, "flags:   {}", flag_names(context.flags())).map_err(write_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_args(args: &str) -> Result<String, String> {
+        let args = args.split_whitespace().map(str::to_string).collect::<Vec<_>>();
+        let mut out = Vec::new();
+        run(&args, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn ops() {
+        assert_eq!(
+            run_args("add --format bf16 0x3f80 1.5"),
+            Ok(concat!(
+                "source1: 0x3f80 (sign 0, exp 0x7f, sig 0x00) = 1 [PositiveNormal]\n",
+                "source2: 0x3fc0 (sign 0, exp 0x7f, sig 0x40) = 1.5 [PositiveNormal]\n",
+                "result:  0x4020 (sign 0, exp 0x80, sig 0x20) = 2.5 [PositiveNormal]\n",
+                "flags:   none\n",
+            )
+            .to_string())
+        );
+        assert_eq!(
+            run_args("mul --format fp16 --rounding toward_zero 0x7bff 2"),
+            Ok(concat!(
+                "source1: 0x7bff (sign 0, exp 0x1e, sig 0x3ff) = 65500 [PositiveNormal]\n",
+                "source2: 0x4000 (sign 0, exp 0x10, sig 0x000) = 2 [PositiveNormal]\n",
+                "result:  0x7bff (sign 0, exp 0x1e, sig 0x3ff) = 65500 [PositiveNormal]\n",
+                "flags:   INEXACT | OVERFLOW\n",
+            )
+            .to_string())
+        );
+        assert!(run_args("fma 2 3 -1").unwrap().contains("result:  0x40a00000 (sign 0, exp 0x81, sig 0x200000) = 5 [PositiveNormal]\n"));
+        assert!(run_args("convert --to e4m3fn 1000").unwrap().contains("result:  0x7f (sign 0, exp 0xf, sig 0x7) = nan [QuietNan]\n"));
+        assert!(run_args("convert --to e4m3fn --overflow saturate 1000").unwrap().contains("result:  0x7e (sign 0, exp 0xf, sig 0x6) = 450 [PositiveNormal]\n"));
+
+        // Denormal handling is off by default
+        assert!(run_args("add --format fp16 0x0001 0x0001").unwrap().contains("result:  0x0002 "));
+        assert!(run_args("add --format fp16 --daz 0x0001 0x0001").unwrap().contains("result:  0x0000 "));
+    }
+
+    #[test]
+    fn decode_encode() {
+        // Values print as the shortest decimal that reads back the same encoding, with the exact value below
+        assert_eq!(
+            run_args("decode --format e4m3fn 0x7e 0x01"),
+            Ok(concat!(
+                "e4m3fn: 0x7e (sign 0, exp 0xf, sig 0x6) = 450 [PositiveNormal]\n",
+                "  exact: 448\n",
+                "e4m3fn: 0x01 (sign 0, exp 0x0, sig 0x1) = 0.002 [PositiveSubnormal]\n",
+                "  exact: 0.001953125\n",
+            )
+            .to_string())
+        );
+        assert_eq!(
+            run_args("encode --format fp16 0.1"),
+            Ok(concat!(
+                "e5m10: 0x2e66 (sign 0, exp 0x0b, sig 0x266) = 0.1 [PositiveNormal]\n",
+                "  exact: 0.0999755859375\n",
+                "  flags: INEXACT\n",
+            )
+            .to_string())
+        );
+        // Hex integers are encodings only for decode; encode reads them as literals
+        assert!(run_args("encode --format fp16 0x10").unwrap().starts_with("e5m10: 0x4c00 "));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(run_args("add 1"), Err("add takes 2 operands, but got 1.".to_string()));
+        assert_eq!(run_args("add --format fp16 0x10000 1"), Err("Encoding 0x10000 doesn't fit in e5m10 (16 bits).".to_string()));
+        assert!(run_args("add 1 x").unwrap_err().starts_with("Invalid operand \"x\": Requested value is not a valid literal;"));
+        assert_eq!(
+            run_args("add --format e2m1+finite nan 1"),
+            Err("Invalid operand \"nan\": Requested format has no NaN encoding.".to_string())
+        );
+        assert_eq!(run_args("add --rounding up 1 1"), Err("Invalid value \"up\" for --rounding.".to_string()));
+        assert_eq!(run_args("add 1 1 --format"), Err("Missing value for --format.".to_string()));
+        assert_eq!(run_args("add --bogus 1 1"), Err("Unknown option --bogus.".to_string()));
+        assert_eq!(run_args("convert 1"), Err("convert requires --to <spec>.".to_string()));
+        assert!(run_args("frobnicate").unwrap_err().starts_with("Unknown command \"frobnicate\"."));
+        assert_eq!(run_args(""), Err(USAGE.to_string()));
+    }
+
+    #[test]
+    fn help() {
+        for args in &["help", "--help", "-h", "add --help", "convert -h --to e4m3fn", "add --bogus 1 --help"] {
+            assert_eq!(run_args(args), Ok(USAGE.to_string()), "{}", args);
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::flags::*;
+use crate::rounding::*;
+
+// Operating modes shared by all ops, along with the exception flags they've raised. The default models the
+// hardware: truncation, with denormal inputs treated as zero, denormal results flushed to zero and no IEEE rounding.
+pub struct Context {
+    pub(crate) rounding_mode: RoundingMode,
+    pub(crate) random_source: Option<Box<dyn RandomSource>>,
+    pub(crate) flush_to_zero: bool,
+    pub(crate) denormals_are_zero: bool,
+    pub(crate) tininess_mode: TininessMode,
+    pub(crate) overflow_mode: OverflowMode,
+    pub(crate) nan_mode: NanMode,
+    pub(crate) ieee_rounding: bool,
+    pub(crate) flags: Flags,
+    // The bits most recently drawn for stochastic rounding, so traces can record them
+    pub(crate) last_random_bits: Option<u32>,
+}
+
+// How NaN results are chosen. Every mode quiets propagated NaNs and raises invalid for signaling NaN sources.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NanMode {
+    // Always the canonical (positive, quiet) NaN, as in RISC-V or ARM's default NaN mode
+    Canonical,
+    // The first NaN source, in operand order; generated NaNs are canonical
+    PropagateFirst,
+    // As PropagateFirst, but generated NaNs are x86's negative "QNaN floating-point indefinite"
+    X86,
+    // The first signaling NaN source, then the first quiet NaN source; FMA checks its addend first
+    Arm,
+}
+
+impl fmt::Display for NanMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NanMode::Canonical => write!(f, "canonical"),
+            NanMode::PropagateFirst => write!(f, "propagate_first"),
+            NanMode::X86 => write!(f, "x86"),
+            NanMode::Arm => write!(f, "arm"),
+        }
+    }
+}
+
+impl FromStr for NanMode {
+    type Err = ParseModeError;
+
+    fn from_str(s: &str) -> Result<NanMode, ParseModeError> {
+        match s {
+            "canonical" => Ok(NanMode::Canonical),
+            "propagate_first" => Ok(NanMode::PropagateFirst),
+            "x86" => Ok(NanMode::X86),
+            "arm" => Ok(NanMode::Arm),
+            _ => Err(ParseModeError::InvalidNanMode(s.to_string())),
+        }
+    }
+}
+
+impl Context {
+    pub fn new() -> Context {
+        Context {
+            rounding_mode: RoundingMode::TowardZero,
+            random_source: None,
+            flush_to_zero: true,
+            denormals_are_zero: true,
+            tininess_mode: TininessMode::BeforeRounding,
+            overflow_mode: OverflowMode::Ieee,
+            nan_mode: NanMode::Canonical,
+            ieee_rounding: false,
+            flags: Flags::empty(),
+            last_random_bits: None,
+        }
+    }
+
+    pub fn with_rounding_mode(self, rounding_mode: RoundingMode) -> Context {
+        if let RoundingMode::Stochastic { num_random_bits } = rounding_mode {
+            if num_random_bits == 0 || num_random_bits > MAX_NUM_RANDOM_BITS {
+                panic!("Requested stochastic rounding mode uses {} random bits, but must use between 1 and {} random bits.", num_random_bits, MAX_NUM_RANDOM_BITS);
+            }
+        }
+
+        Context {
+            rounding_mode,
+            ..self
+        }
+    }
+
+    pub fn with_random_source<R: RandomSource + 'static>(self, random_source: R) -> Context {
+        Context {
+            random_source: Some(Box::new(random_source)),
+            ..self
+        }
+    }
+
+    // Whether tiny results are replaced by zero (raising underflow and inexact)
+    pub fn with_flush_to_zero(self, flush_to_zero: bool) -> Context {
+        Context {
+            flush_to_zero,
+            ..self
+        }
+    }
+
+    // Whether denormal inputs are read as zero
+    pub fn with_denormals_are_zero(self, denormals_are_zero: bool) -> Context {
+        Context {
+            denormals_are_zero,
+            ..self
+        }
+    }
+
+    pub fn with_tininess_mode(self, tininess_mode: TininessMode) -> Context {
+        Context {
+            tininess_mode,
+            ..self
+        }
+    }
+
+    pub fn with_overflow_mode(self, overflow_mode: OverflowMode) -> Context {
+        Context {
+            overflow_mode,
+            ..self
+        }
+    }
+
+    pub fn with_nan_mode(self, nan_mode: NanMode) -> Context {
+        Context {
+            nan_mode,
+            ..self
+        }
+    }
+
+    // Whether results are rounded from the exact value, as IEEE 754 specifies. Without it, ops round as the hardware
+    // does: addition truncates the addend bits it shifts out while aligning, overflow goes to infinity in every
+    // rounding mode and zero sums (exact or flushed) are +0.
+    pub fn with_ieee_rounding(self, ieee_rounding: bool) -> Context {
+        Context {
+            ieee_rounding,
+            ..self
+        }
+    }
+
+    pub fn rounding_mode(&self) -> RoundingMode {
+        self.rounding_mode
+    }
+
+    pub fn flush_to_zero(&self) -> bool {
+        self.flush_to_zero
+    }
+
+    pub fn denormals_are_zero(&self) -> bool {
+        self.denormals_are_zero
+    }
+
+    pub fn tininess_mode(&self) -> TininessMode {
+        self.tininess_mode
+    }
+
+    pub fn overflow_mode(&self) -> OverflowMode {
+        self.overflow_mode
+    }
+
+    pub fn nan_mode(&self) -> NanMode {
+        self.nan_mode
+    }
+
+    pub fn ieee_rounding(&self) -> bool {
+        self.ieee_rounding
+    }
+
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+
+    pub fn clear_flags(&mut self) {
+        self.flags = Flags::empty();
+    }
+
+    pub(crate) fn raise(&mut self, flags: Flags) {
+        self.flags |= flags;
+    }
+
+    // Draws the random bits for a single stochastically-rounded result
+    pub(crate) fn next_random_bits(&mut self, num_random_bits: u32) -> u32 {
+        let random_source = match self.random_source.as_mut() {
+            Some(random_source) => random_source,
+            None => panic!("Stochastic rounding requires a random source."),
+        };
+        let mask = ((1u64 << num_random_bits) - 1) as u32;
+        let random_bits = random_source.random_bits(num_random_bits) & mask;
+        self.last_random_bits = Some(random_bits);
+        random_bits
+    }
+}
+
+// A context's modes, without its random source or flags: unlike a context, they can be compared and handed to another
+// thread
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Modes {
+    rounding_mode: RoundingMode,
+    flush_to_zero: bool,
+    denormals_are_zero: bool,
+    tininess_mode: TininessMode,
+    overflow_mode: OverflowMode,
+    nan_mode: NanMode,
+    ieee_rounding: bool,
+}
+
+impl Modes {
+    pub(crate) fn new(context: &Context) -> Modes {
+        Modes {
+            rounding_mode: context.rounding_mode,
+            flush_to_zero: context.flush_to_zero,
+            denormals_are_zero: context.denormals_are_zero,
+            tininess_mode: context.tininess_mode,
+            overflow_mode: context.overflow_mode,
+            nan_mode: context.nan_mode,
+            ieee_rounding: context.ieee_rounding,
+        }
+    }
+
+    // A context with these modes, but no random source and no flags
+    pub(crate) fn context(self) -> Context {
+        Context {
+            rounding_mode: self.rounding_mode,
+            random_source: None,
+            flush_to_zero: self.flush_to_zero,
+            denormals_are_zero: self.denormals_are_zero,
+            tininess_mode: self.tininess_mode,
+            overflow_mode: self.overflow_mode,
+            nan_mode: self.nan_mode,
+            ieee_rounding: self.ieee_rounding,
+            flags: Flags::empty(),
+            last_random_bits: None,
+        }
+    }
+}
+
+impl Default for Context {
+    fn default() -> Context {
+        Context::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nan_mode_names() {
+        for &mode in &[NanMode::Canonical, NanMode::PropagateFirst, NanMode::X86, NanMode::Arm] {
+            assert_eq!(mode.to_string().parse::<NanMode>(), Ok(mode));
+        }
+
+        assert_eq!(NanMode::PropagateFirst.to_string(), "propagate_first");
+        assert_eq!("riscv".parse::<NanMode>(), Err(ParseModeError::InvalidNanMode("riscv".to_string())));
+    }
+
+    #[test]
+    fn new_default() {
+        let context = Context::new();
+
+        assert_eq!(context.rounding_mode(), RoundingMode::TowardZero);
+        assert!(context.random_source.is_none());
+        assert!(context.flush_to_zero());
+        assert!(context.denormals_are_zero());
+        assert_eq!(context.tininess_mode(), TininessMode::BeforeRounding);
+        assert_eq!(context.overflow_mode(), OverflowMode::Ieee);
+        assert_eq!(context.nan_mode(), NanMode::Canonical);
+        assert!(!context.ieee_rounding());
+        assert!(context.flags().is_empty());
+    }
+
+    #[test]
+    fn flags() {
+        let mut context = Context::new();
+
+        context.raise(Flags::OVERFLOW);
+        context.raise(Flags::OVERFLOW);
+
+        assert_eq!(context.flags(), Flags::OVERFLOW);
+
+        context.clear_flags();
+
+        assert!(context.flags().is_empty());
+    }
+
+    #[test]
+    fn next_random_bits() {
+        let mut next = 0xfffffff0u32;
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 4 })
+            .with_random_source(move |_| {
+                next = next.wrapping_add(1);
+                next
+            });
+
+        assert_eq!(context.next_random_bits(4), 0x1);
+        assert_eq!(context.next_random_bits(4), 0x2);
+        assert_eq!(context.next_random_bits(32), 0xfffffff3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested stochastic rounding mode uses 0 random bits, but must use between 1 and 32 random bits.")]
+    fn with_rounding_mode_no_random_bits() {
+        // Panic
+        let _ = Context::new().with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 0 });
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested stochastic rounding mode uses 33 random bits, but must use between 1 and 32 random bits.")]
+    fn with_rounding_mode_too_many_random_bits() {
+        // Panic
+        let _ = Context::new().with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 33 });
+    }
+
+    #[test]
+    #[should_panic(expected = "Stochastic rounding requires a random source.")]
+    fn next_random_bits_without_random_source() {
+        // Panic
+        let mut context = Context::new().with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 8 });
+        let _ = context.next_random_bits(8);
+    }
+}
@@ -0,0 +1,546 @@
+// Targeted stimulus in the style of IBM's FPgen: operands constructed to force specific events inside an op (a given
+// alignment shift, massive cancellation, an exactly halfway result, ...), which uniformly random operands almost never
+// hit. Each target is defined on the exact result, so whether a case hits it doesn't depend on how the op is built.
+
+use std::cmp::Ordering;
+
+use crate::bignum::*;
+use crate::format::*;
+use crate::ops::*;
+use crate::oracle::*;
+use crate::value::*;
+use crate::verify::*;
+
+// An event that stimulus should force; the rounding events are judged at format's precision alone (so rounding up
+// past the max finite value still counts), rounding to nearest even
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Target {
+    // Addition: the smaller operand's lsb sits this many places below the larger's
+    AlignmentShift(u32),
+    // Addition of opposite signs: the result's msb sits this many places below the larger operand's
+    Cancellation(u32),
+    // Addition of like signs: the exact sum carries into a place above the larger operand's msb
+    SumCarryOut,
+    // The discarded bits are exactly half an lsb
+    Halfway,
+    // The discarded bits are nonzero, but below the round bit (ie. less than half an lsb)
+    StickyOnly,
+    // Rounding up carries out of the significand, into the next binade
+    RoundingCarryOut,
+    // The exact result's magnitude lies between the max finite value and one lsb past it (inclusive), where the
+    // rounding mode decides whether it overflows
+    OverflowBoundary,
+}
+
+impl Target {
+    pub fn applies_to(self, op: &Op) -> bool {
+        match op {
+            Op::Addition => true,
+            Op::Multiplication => !matches!(self, Target::AlignmentShift(_) | Target::Cancellation(_) | Target::SumCarryOut),
+            _ => false,
+        }
+    }
+
+    // Whether op on sources (encodings in format) hits the target; only finite, nonzero sources ever do
+    pub fn is_hit(self, op: &Op, sources: &[u32], format: &Format) -> bool {
+        if !self.applies_to(op) {
+            return false;
+        }
+        let sources = sources.iter().map(|&bits| Value::from_bits(bits, format.clone())).collect::<Vec<_>>();
+        if sources.iter().any(|source| !source.is_finite() || source.is_zero()) {
+            return false;
+        }
+
+        let exact = match op {
+            Op::Addition => Exact::from_value(&sources[0]).add(&Exact::from_value(&sources[1])),
+            _ => Exact::from_value(&sources[0]).mul(&Exact::from_value(&sources[1])),
+        };
+        if exact.mag.is_zero() {
+            return false;
+        }
+
+        match self {
+            Target::AlignmentShift(shift) => {
+                let (larger, smaller) = by_magnitude(&sources[0], &sources[1]);
+                larger.decode_finite().1 - smaller.decode_finite().1 == shift as i32
+            }
+            Target::Cancellation(num_bits) => {
+                let (larger, _) = by_magnitude(&sources[0], &sources[1]);
+                sources[0].sign != sources[1].sign && msb_exp(&Exact::from_value(larger)) - msb_exp(&exact) == num_bits as i64
+            }
+            Target::SumCarryOut => {
+                let (larger, _) = by_magnitude(&sources[0], &sources[1]);
+                sources[0].sign == sources[1].sign && msb_exp(&exact) > msb_exp(&Exact::from_value(larger))
+            }
+            Target::Halfway => discarded(&exact, format).0 == Discarded::Half,
+            Target::StickyOnly => discarded(&exact, format).0 == Discarded::BelowHalf,
+            Target::RoundingCarryOut => {
+                let (discarded, kept) = discarded(&exact, format);
+                let is_round_up = discarded == Discarded::AboveHalf || (discarded == Discarded::Half && kept % 2 == 1);
+                is_round_up && kept != 0 && (kept + 1).is_power_of_two()
+            }
+            Target::OverflowBoundary => {
+                let (max_exp, max_sig) = format.max_finite_comps();
+                let max_full_sig = ((1 << format.num_sig_bits) | max_sig) as u64;
+                let max_lsb_exp = (max_exp as i32 - format.exp_bias - format.num_sig_bits as i32) as i64;
+                cmp_magnitude(&exact, max_full_sig, max_lsb_exp) != Ordering::Less
+                    && cmp_magnitude(&exact, max_full_sig + 1, max_lsb_exp) != Ordering::Greater
+            }
+        }
+    }
+}
+
+// Ordered as addition swaps its sources
+fn by_magnitude<'a>(lhs: &'a Value, rhs: &'a Value) -> (&'a Value, &'a Value) {
+    if lhs.exp < rhs.exp || (lhs.exp == rhs.exp && lhs.sig < rhs.sig) {
+        (rhs, lhs)
+    } else {
+        (lhs, rhs)
+    }
+}
+
+fn msb_exp(x: &Exact) -> i64 {
+    x.exp + x.mag.bit_len() as i64 - 1
+}
+
+// The bits below format's lsb, relative to half an lsb
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Discarded {
+    Zero,
+    BelowHalf,
+    Half,
+    AboveHalf,
+}
+
+// Splits x's magnitude at format's lsb (denormals share the smallest normal's), returning the discarded part and the
+// kept part
+fn discarded(x: &Exact, format: &Format) -> (Discarded, u64) {
+    let lsb_exp = msb_exp(x).max(format.min_normal_exp() as i64) - format.num_sig_bits as i64;
+    if lsb_exp <= x.exp {
+        return (Discarded::Zero, 0);
+    }
+
+    let num_discarded_bits = (lsb_exp - x.exp) as u32;
+    if num_discarded_bits > x.mag.bit_len() {
+        return (Discarded::BelowHalf, 0);
+    }
+
+    let kept = x.mag.shr(num_discarded_bits).to_u64();
+    let discarded = x.mag.low_bits(num_discarded_bits);
+    let mut half = BigUint::from_u64(1);
+    half.mul_pow2(num_discarded_bits - 1);
+    let discarded = match discarded.cmp(&half) {
+        _ if discarded.is_zero() => Discarded::Zero,
+        Ordering::Less => Discarded::BelowHalf,
+        Ordering::Equal => Discarded::Half,
+        Ordering::Greater => Discarded::AboveHalf,
+    };
+    (discarded, kept)
+}
+
+// Compares |x| with sig * 2^exp
+fn cmp_magnitude(x: &Exact, sig: u64, exp: i64) -> Ordering {
+    let min_exp = x.exp.min(exp);
+    let mut lhs = x.mag.clone();
+    lhs.mul_pow2((x.exp - min_exp) as u32);
+    let mut rhs = BigUint::from_u64(sig);
+    rhs.mul_pow2((exp - min_exp) as u32);
+    lhs.cmp(&rhs)
+}
+
+// Builds normal encodings from full (hidden bit included) sigs and biased exps, in a format's finite range
+struct Builder<'a> {
+    format: &'a Format,
+    random: Random,
+}
+
+impl<'a> Builder<'a> {
+    fn precision(&self) -> u32 {
+        self.format.num_sig_bits + 1
+    }
+
+    fn max_exp(&self) -> i64 {
+        self.format.max_finite_comps().0 as i64
+    }
+
+    // Uniform in [lo, hi], which must be nonempty
+    fn range(&mut self, lo: i64, hi: i64) -> i64 {
+        let num_values = (hi - lo + 1) as u64;
+        let random = ((self.random.next_u32() as u64) << 32) | self.random.next_u32() as u64;
+        lo + (random % num_values) as i64
+    }
+
+    fn sign(&mut self) -> bool {
+        self.random.next_u32() & 1 != 0
+    }
+
+    // Random full sig, leading one included
+    fn sig(&mut self) -> u64 {
+        let precision = self.precision();
+        self.range(1 << (precision - 1), (1 << precision) - 1) as u64
+    }
+
+    // Whether candidates for target can be built in format at all: every target needs at least 2 bits of precision, and
+    // products (but for the overflow boundary) need room for an exp strictly inside the normal range
+    fn is_feasible(&self, op: &Op, target: Target) -> bool {
+        if self.precision() < 2 {
+            return false;
+        }
+
+        match op {
+            Op::Multiplication => target == Target::OverflowBoundary || self.max_exp() >= 3,
+            _ => true,
+        }
+    }
+
+    fn encode(&self, sign: bool, exp: i64, full_sig: u64) -> Option<u32> {
+        let precision = self.precision();
+        if exp < 1 || exp > self.max_exp() || full_sig >> (precision - 1) != 1 {
+            return None;
+        }
+
+        let value = Value::from_comps(sign, exp as u32, full_sig as u32 & self.format.sig_mask(), self.format.clone());
+        if !value.is_finite() {
+            return None;
+        }
+        Some(value.to_bits())
+    }
+
+    // A candidate for target, which may miss (or not even be encodable); returned in either operand order
+    fn candidate(&mut self, op: &Op, target: Target) -> Option<Vec<u32>> {
+        let sources = match op {
+            Op::Addition => self.addition_candidate(target)?,
+            _ => self.multiplication_candidate(target)?,
+        };
+        Some(if self.sign() { sources } else { vec![sources[1], sources[0]] })
+    }
+
+    fn addition_candidate(&mut self, target: Target) -> Option<Vec<u32>> {
+        let precision = self.precision() as i64;
+        let max_exp = self.max_exp();
+
+        // The larger operand, and the smaller one's exp and sig
+        let (sign, exp, sig, rhs_sign, rhs_exp, rhs_sig) = match target {
+            Target::AlignmentShift(shift) => {
+                let exp = self.range(1 + shift as i64, max_exp.max(1 + shift as i64));
+                let rhs_sign = self.sign();
+                let rhs_sig = self.sig();
+                (self.sign(), exp, self.sig(), rhs_sign, exp - shift as i64, rhs_sig)
+            }
+            Target::Cancellation(num_bits) => {
+                let sign = self.sign();
+                let num_bits = num_bits as i64;
+                if num_bits == 0 || num_bits > precision {
+                    // Little or no cancellation takes sources further apart
+                    let exp = self.range(1, max_exp);
+                    let rhs_exp = exp - self.range(1, 3);
+                    let (sig, rhs_sig) = (self.sig(), self.sig());
+                    (sign, exp, sig, !sign, rhs_exp, rhs_sig)
+                } else if num_bits == precision {
+                    // 1.0 - 0.11...1 leaves only the last bit
+                    let exp = self.range(2, max_exp);
+                    (sign, exp, 1 << (precision - 1), !sign, exp - 1, (1 << precision) - 1)
+                } else {
+                    // Same exps, with sigs that differ by a value num_bits places below the leading one
+                    let exp = self.range(1, max_exp);
+                    let difference = self.range(1 << (precision - 1 - num_bits), (1 << (precision - num_bits)) - 1);
+                    let sig = self.range((1 << (precision - 1)) + difference, (1 << precision) - 1);
+                    (sign, exp, sig as u64, !sign, exp, (sig - difference) as u64)
+                }
+            }
+            Target::SumCarryOut => {
+                let sign = self.sign();
+                let exp = self.range(1, max_exp);
+                let rhs_exp = exp - self.range(0, 2);
+                let sig = self.range(3 << (precision - 2), (1 << precision) - 1) as u64;
+                let rhs_sig = self.sig();
+                (sign, exp, sig, sign, rhs_exp, rhs_sig)
+            }
+            Target::Halfway | Target::StickyOnly => {
+                // Shape the smaller operand's bits shifted out past the larger's lsb
+                let shift = if target == Target::Halfway {
+                    self.range(1, precision)
+                } else if self.sign() {
+                    self.range(2, precision)
+                } else {
+                    self.range(precision + 2, precision + 4)
+                };
+                let exp = self.range(1 + shift, max_exp.max(1 + shift));
+                let mut rhs_sig = self.sig();
+                if shift <= precision {
+                    let mask = (1 << shift) - 1;
+                    let shifted_out = if target == Target::Halfway {
+                        1 << (shift - 1)
+                    } else {
+                        self.range(1, (1 << (shift - 1)) - 1) as u64
+                    };
+                    rhs_sig = (rhs_sig & !mask) | shifted_out;
+                }
+                let (sign, rhs_sign) = (self.sign(), self.sign());
+                (sign, exp, self.sig(), rhs_sign, exp - shift, rhs_sig | (1 << (precision - 1)))
+            }
+            Target::RoundingCarryOut => {
+                // All ones, plus at least half an lsb
+                let sign = self.sign();
+                let exp = self.range(1 + precision, max_exp.max(1 + precision));
+                let rhs_sig = self.sig();
+                (sign, exp, (1 << precision) - 1, sign, exp - precision, rhs_sig)
+            }
+            Target::OverflowBoundary => {
+                // Just below the max finite value, plus a few lsbs' worth
+                let sign = self.sign();
+                let (_, max_sig) = self.format.max_finite_comps();
+                let max_full_sig = ((1 << (precision - 1)) | max_sig) as i64;
+                let sig = self.range(max_full_sig - 3, max_full_sig) as u64;
+                let rhs_exp = max_exp - self.range(precision - 3, precision + 1);
+                let rhs_sig = self.sig();
+                (sign, max_exp, sig, sign, rhs_exp, rhs_sig)
+            }
+        };
+
+        Some(vec![self.encode(sign, exp, sig)?, self.encode(rhs_sign, rhs_exp, rhs_sig)?])
+    }
+
+    fn multiplication_candidate(&mut self, target: Target) -> Option<Vec<u32>> {
+        let precision = self.precision();
+        let exp_bias = self.format.exp_bias as i64;
+        let max_exp = self.max_exp();
+
+        let sig = self.range((1 << (precision - 1)) + 1, (1 << precision) - 1) as u64;
+        let rhs_sig = match target {
+            Target::Halfway => {
+                // Discarding num_discarded_bits bits of the product leaves exactly half iff
+                // sig * rhs_sig = 2^(num_discarded_bits - 1) (mod 2^num_discarded_bits)
+                let sig = sig | 1;
+                let num_discarded_bits = precision - 1 + (self.sign() as u32);
+                let modulus = 1u64 << num_discarded_bits;
+                let rhs_sig = ((1 << (num_discarded_bits - 1)) * inverse_mod_pow2(sig, num_discarded_bits)) % modulus;
+                let rhs_sig = if rhs_sig >> (precision - 1) == 0 { rhs_sig + modulus } else { rhs_sig };
+                return self.multiplication_sources(sig, rhs_sig);
+            }
+            Target::StickyOnly => self.sig(),
+            Target::RoundingCarryOut => {
+                // The smallest product that rounds up to 2^(2 * precision - 1)
+                let threshold: u64 = (((1 << precision) - 1) << (precision - 1)) + (1 << precision >> 2);
+                threshold.div_ceil(sig)
+            }
+            _ => {
+                // The smallest product at or above the max finite value
+                let (_, max_sig) = self.format.max_finite_comps();
+                let max_full_sig = (1 << (precision - 1)) | max_sig as u64;
+                let rhs_sig = (max_full_sig << (precision - 1)).div_ceil(sig);
+                let exp = self.range(1, max_exp);
+                let rhs_exp = max_exp - exp + exp_bias;
+                let (sign, rhs_sign) = (self.sign(), self.sign());
+                return Some(vec![self.encode(sign, exp, sig)?, self.encode(rhs_sign, rhs_exp, rhs_sig)?]);
+            }
+        };
+        self.multiplication_sources(sig, rhs_sig)
+    }
+
+    // Exps that keep the product well inside the normal range
+    fn multiplication_sources(&mut self, sig: u64, rhs_sig: u64) -> Option<Vec<u32>> {
+        let exp_bias = self.format.exp_bias as i64;
+        let max_exp = self.max_exp();
+        let exp = self.range(1, max_exp);
+        // Unbiased product exp in [min normal + 1, max normal - 1]
+        let product_exp = self.range(2 - exp_bias, max_exp - exp_bias - 1);
+        let rhs_exp = product_exp - (exp - exp_bias) + exp_bias;
+        let (sign, rhs_sign) = (self.sign(), self.sign());
+        Some(vec![self.encode(sign, exp, sig)?, self.encode(rhs_sign, rhs_exp, rhs_sig)?])
+    }
+}
+
+// x^-1 mod 2^num_bits, for odd x (Newton's iteration doubles the number of correct bits each step)
+fn inverse_mod_pow2(x: u64, num_bits: u32) -> u64 {
+    let mut inverse = 1u64;
+    for _ in 0..6 {
+        inverse = inverse.wrapping_mul(2u64.wrapping_sub(x.wrapping_mul(inverse)));
+    }
+    inverse & ((1 << num_bits) - 1)
+}
+
+// Up to num_cases source tuples (encodings in format) that make op hit target, reproducible from seed. Fewer are
+// returned when format can't (or can only rarely) hit the target, eg. alignment shifts beyond its exponent range, and
+// none when format is too narrow to build candidates (see Builder::is_feasible).
+pub fn targeted_sources(op: &Op, format: &Format, target: Target, num_cases: u64, seed: u64) -> Vec<Vec<u32>> {
+    if !target.applies_to(op) {
+        panic!("Requested target {:?} for {}, which can't hit it.", target, op.name());
+    }
+
+    let mut builder = Builder { format, random: Random::new(seed) };
+    if !builder.is_feasible(op, target) {
+        return Vec::new();
+    }
+
+    let max_num_attempts = num_cases.saturating_mul(64).max(1024);
+    let mut sources = Vec::new();
+    for _ in 0..max_num_attempts {
+        if sources.len() as u64 == num_cases {
+            break;
+        }
+
+        if let Some(candidate) = builder.candidate(op, target) {
+            if target.is_hit(op, &candidate, format) {
+                sources.push(candidate);
+            }
+        }
+    }
+    sources
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::*;
+    use crate::flags::*;
+    use crate::rounding::*;
+
+    fn context(rounding_mode: RoundingMode) -> Context {
+        Context::new()
+            .with_rounding_mode(rounding_mode)
+            .with_flush_to_zero(false)
+            .with_denormals_are_zero(false)
+            .with_ieee_rounding(true)
+    }
+
+    fn evaluate(op: &Op, sources: &[u32], format: &Format, rounding_mode: RoundingMode) -> (u32, Flags) {
+        let mut context = context(rounding_mode);
+        let result = op.evaluate(sources, format, &mut context).to_bits();
+        (result, context.flags())
+    }
+
+    fn targets(format: &Format) -> Vec<Target> {
+        let precision = format.num_sig_bits + 1;
+        let mut targets = vec![
+            Target::SumCarryOut,
+            Target::Halfway,
+            Target::StickyOnly,
+            Target::RoundingCarryOut,
+            Target::OverflowBoundary,
+        ];
+        targets.extend([0, 1, 2, precision - 1, precision, precision + 1, precision + 3].iter().map(|&shift| Target::AlignmentShift(shift)));
+        targets.extend((0..=precision).map(Target::Cancellation));
+        targets
+    }
+
+    #[test]
+    fn every_target_is_hit() {
+        for format in &[Format::new(5, 10), Format::new(8, 7), Format::ocp_fp8_e4m3(), Format::ocp_fp8_e5m2()] {
+            for op in &[Op::Addition, Op::Multiplication] {
+                for target in targets(format).into_iter().filter(|target| target.applies_to(op)) {
+                    let sources = targeted_sources(op, format, target, 50, 42);
+
+                    assert_eq!(sources.len(), 50, "{} of {:?} in {:?}", op.name(), target, format);
+                    assert!(sources.iter().all(|sources| target.is_hit(op, sources, format)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn infeasible_targets_return_fewer_cases() {
+        // Normals of one format can't cancel more than their precision
+        let f = Format::new(5, 10);
+
+        assert!(targeted_sources(&Op::Addition, &f, Target::Cancellation(12), 10, 42).is_empty());
+
+        // Formats too narrow to build candidates in
+        for format in &[Format::new(2, 1), "e3m0+finite".parse().unwrap()] {
+            for op in &[Op::Addition, Op::Multiplication] {
+                for target in targets(format).into_iter().filter(|target| target.applies_to(op)) {
+                    let sources = targeted_sources(op, format, target, 10, 42);
+
+                    assert!(sources.len() <= 10);
+                    assert!(sources.iter().all(|sources| target.is_hit(op, sources, format)));
+                }
+            }
+        }
+        assert!(targeted_sources(&Op::Multiplication, &Format::new(2, 1), Target::Halfway, 10, 42).is_empty());
+        assert!(targeted_sources(&Op::Addition, &"e3m0+finite".parse().unwrap(), Target::SumCarryOut, 10, 42).is_empty());
+    }
+
+    #[test]
+    fn is_hit() {
+        let f = Format::ieee754_single();
+
+        assert!(Target::AlignmentShift(1).is_hit(&Op::Addition, &[0x3f800000, 0x3f000000], &f)); // 1 + 0.5
+        assert!(Target::AlignmentShift(1).is_hit(&Op::Addition, &[0x3f000000, 0x3f800000], &f));
+        assert!(!Target::AlignmentShift(0).is_hit(&Op::Addition, &[0x3f800000, 0x3f000000], &f));
+        assert!(Target::SumCarryOut.is_hit(&Op::Addition, &[0x3f800000, 0x3f800000], &f)); // 1 + 1
+        assert!(!Target::SumCarryOut.is_hit(&Op::Addition, &[0x3f800000, 0xbf000000], &f));
+        assert!(Target::Cancellation(1).is_hit(&Op::Addition, &[0x3f800000, 0xbf000000], &f)); // 1 - 0.5
+        assert!(Target::Cancellation(24).is_hit(&Op::Addition, &[0x3f800000, 0xbf7fffff], &f)); // 1 - (1 - 2^-24)
+        assert!(Target::Halfway.is_hit(&Op::Addition, &[0x3f800000, 0x33800000], &f)); // 1 + 2^-24
+        assert!(Target::StickyOnly.is_hit(&Op::Addition, &[0x3f800000, 0x33000000], &f)); // 1 + 2^-25
+        assert!(Target::RoundingCarryOut.is_hit(&Op::Addition, &[0x3fffffff, 0x33800000], &f)); // (2 - 2^-23) + 2^-24
+        assert!(!Target::RoundingCarryOut.is_hit(&Op::Addition, &[0x3fffffff, 0x33000000], &f));
+        assert!(!Target::RoundingCarryOut.is_hit(&Op::Addition, &[0x3fffffff, 0x34000000], &f)); // Exact
+        assert!(Target::OverflowBoundary.is_hit(&Op::Addition, &[0x7f7fffff, 0x73800000], &f)); // max + ulp/2
+        assert!(!Target::OverflowBoundary.is_hit(&Op::Addition, &[0x7f7fffff, 0xf3800000], &f));
+        assert!(!Target::Halfway.is_hit(&Op::Addition, &[0x3f800000, 0x7f800000], &f)); // 1 + inf
+        assert!(!Target::Halfway.is_hit(&Op::FusedMultiplyAdd, &[0x3f800000, 0x3f800000, 0x33800000], &f));
+    }
+
+    #[test]
+    fn halfway_depends_on_rounding_direction() {
+        for op in &[Op::Addition, Op::Multiplication] {
+            for f in &[Format::new(5, 10), Format::ocp_fp8_e5m2()] {
+                for sources in targeted_sources(op, f, Target::Halfway, 100, 1) {
+                    let (nearest_away, _) = evaluate(op, &sources, f, RoundingMode::NearestAway);
+                    let (toward_zero, flags) = evaluate(op, &sources, f, RoundingMode::TowardZero);
+
+                    assert_ne!(nearest_away, toward_zero);
+                    assert!(flags.contains(Flags::INEXACT));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sticky_only_rounds_like_truncation() {
+        for op in &[Op::Addition, Op::Multiplication] {
+            for f in &[Format::new(5, 10), Format::ocp_fp8_e5m2()] {
+                for sources in targeted_sources(op, f, Target::StickyOnly, 100, 2) {
+                    let (nearest_even, flags) = evaluate(op, &sources, f, RoundingMode::NearestEven);
+                    let (toward_zero, _) = evaluate(op, &sources, f, RoundingMode::TowardZero);
+
+                    assert_eq!(nearest_even, toward_zero);
+                    assert!(flags.contains(Flags::INEXACT));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rounding_carry_out_clears_sig() {
+        for op in &[Op::Addition, Op::Multiplication] {
+            let f = Format::new(5, 10);
+            for sources in targeted_sources(op, &f, Target::RoundingCarryOut, 100, 3) {
+                let (nearest_even, _) = evaluate(op, &sources, &f, RoundingMode::NearestEven);
+
+                assert_eq!(nearest_even & f.sig_mask(), 0);
+            }
+        }
+    }
+
+    #[test]
+    fn overflow_boundary_depends_on_rounding_direction() {
+        for op in &[Op::Addition, Op::Multiplication] {
+            let f = Format::new(5, 10);
+            for sources in targeted_sources(op, &f, Target::OverflowBoundary, 100, 4) {
+                let (toward_zero, _) = evaluate(op, &sources, &f, RoundingMode::TowardZero);
+                let (away, _) = evaluate(op, &sources, &f, RoundingMode::NearestAway);
+
+                assert_eq!(toward_zero & 0x7fff, 0x7bff);
+                assert!(away & 0x7fff == 0x7bff || away & 0x7fff == 0x7c00);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested target SumCarryOut for multiplication, which can't hit it.")]
+    fn sum_carry_out_for_multiplication() {
+        let _ = targeted_sources(&Op::Multiplication, &Format::new(5, 10), Target::SumCarryOut, 1, 42);
+    }
+}
@@ -0,0 +1,711 @@
+use std::error;
+use std::fmt;
+
+use crate::bignum::*;
+use crate::context::*;
+use crate::format::*;
+use crate::hex::*;
+use crate::rounding::*;
+use crate::value::*;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ParseValueError {
+    Empty,
+    InvalidSyntax,
+    // The payload doesn't fit, or a signaling NaN was requested with a zero payload or in a format without one
+    InvalidNanPayload,
+    NoNanEncoding,
+}
+
+impl fmt::Display for ParseValueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ParseValueError::Empty => "Requested value is empty.",
+            ParseValueError::InvalidSyntax => {
+                "Requested value is not a valid literal; expected eg. \"-1.5e3\", \"0x1.8p+1\", \"inf\" or \"nan(0x2a)\"."
+            }
+            ParseValueError::InvalidNanPayload => "Requested NaN payload doesn't fit in the format, or is invalid for a signaling NaN.",
+            ParseValueError::NoNanEncoding => "Requested format has no NaN encoding.",
+        })
+    }
+}
+
+impl error::Error for ParseValueError {}
+
+impl Value {
+    // Parses a decimal literal (eg. "-1.5e3"), a hex float literal (eg. "0x1.8p+1"), "inf"/"infinity" or "nan"/"snan"
+    // with an optional payload (eg. "nan(0x2a)"), correctly rounded into format; inexact, underflow and overflow are
+    // raised on context
+    pub fn parse(s: &str, format: Format, context: &mut Context) -> Result<Value, ParseValueError> {
+        if s.is_empty() {
+            return Err(ParseValueError::Empty);
+        }
+
+        let (sign, rest) = match s.as_bytes()[0] {
+            b'-' => (true, &s[1..]),
+            b'+' => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let lower = rest.to_ascii_lowercase();
+
+        if lower == "inf" || lower == "infinity" {
+            // As in conversion, formats without infinities get their stand-in, or the max value when saturating
+            if context.overflow_mode == OverflowMode::Saturate && format.special_encoding != SpecialEncoding::Ieee {
+                return Ok(Value::max_finite(sign, format));
+            }
+            return Ok(Value::infinity(sign, format));
+        }
+
+        if lower.starts_with("nan") || lower.starts_with("snan") {
+            return parse_nan(sign, &lower, format);
+        }
+
+        if lower.starts_with("0x") {
+            return parse_hex(sign, &rest[2..], &format, context);
+        }
+
+        let (digits, exp) = parse_decimal(rest)?;
+        Ok(round_decimal(sign, &digits, exp, &format, context))
+    }
+
+    // Exact decimal expansion (every binary fraction has a finite one), eg. "0.100000001490116119384765625"
+    pub fn to_exact_string(&self) -> String {
+        if let Some(special) = special_string(self) {
+            return special;
+        }
+
+        let (digits, exp) = exact_digits(self);
+        decimal_string(self.sign, &digits, exp)
+    }
+}
+
+
+fn parse_nan(sign: bool, s: &str, format: Format) -> Result<Value, ParseValueError> {
+    let is_signaling = s.starts_with('s');
+    let rest = &s[if is_signaling { 4 } else { 3 }..];
+
+    let payload = if rest.is_empty() {
+        None
+    } else if rest.starts_with('(') && rest.ends_with(')') {
+        let payload = &rest[1..rest.len() - 1];
+        let payload = match payload.strip_prefix("0x") {
+            Some(hex) => u32::from_str_radix(hex, 16),
+            None => payload.parse::<u32>(),
+        };
+        Some(payload.map_err(|_| ParseValueError::InvalidNanPayload)?)
+    } else {
+        return Err(ParseValueError::InvalidSyntax);
+    };
+
+    match format.special_encoding {
+        SpecialEncoding::Ieee => {
+            if format.num_sig_bits == 0 {
+                return Err(ParseValueError::NoNanEncoding);
+            }
+
+            // The payload shares the sig with the quiet bit
+            let payload_mask = format.sig_quiet_bit() - 1;
+            let payload = payload.unwrap_or(if is_signaling { 1 } else { 0 });
+            if payload & !payload_mask != 0 || (is_signaling && payload == 0) {
+                return Err(ParseValueError::InvalidNanPayload);
+            }
+
+            let sig = if is_signaling { payload } else { format.sig_quiet_bit() | payload };
+            Ok(Value::from_comps(sign, format.exp_max(), sig, format))
+        }
+        SpecialEncoding::NanOnly => {
+            if is_signaling || payload.unwrap_or(0) != 0 {
+                return Err(ParseValueError::InvalidNanPayload);
+            }
+
+            Ok(Value::from_comps(sign, format.exp_max(), format.sig_mask(), format))
+        }
+        SpecialEncoding::FiniteOnly => Err(ParseValueError::NoNanEncoding),
+    }
+}
+
+// Splits a decimal literal into its digits (leading zeros removed) and the exponent of their last digit
+fn parse_decimal(s: &str) -> Result<(Vec<u8>, i64), ParseValueError> {
+    let (mantissa, exp) = match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], &s[i + 1..]),
+        None => (s, "0"),
+    };
+
+    let (integer, fraction) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, ""),
+    };
+    let is_digits = |s: &str| s.bytes().all(|c| c.is_ascii_digit());
+    if (integer.is_empty() && fraction.is_empty()) || !is_digits(integer) || !is_digits(fraction) {
+        return Err(ParseValueError::InvalidSyntax);
+    }
+
+    let (exp_sign, exp_digits) = match exp.as_bytes().first() {
+        Some(b'-') => (-1, &exp[1..]),
+        Some(b'+') => (1, &exp[1..]),
+        _ => (1, exp),
+    };
+    if exp_digits.is_empty() || !is_digits(exp_digits) {
+        return Err(ParseValueError::InvalidSyntax);
+    }
+    // Absurd exponents only need to saturate; they'll over/underflow any format either way
+    let exp = exp_digits
+        .bytes()
+        .fold(0i64, |exp, c| (exp * 10 + (c - b'0') as i64).min(1 << 40));
+
+    let digits = integer
+        .bytes()
+        .chain(fraction.bytes())
+        .map(|c| c - b'0')
+        .skip_while(|&digit| digit == 0)
+        .collect::<Vec<_>>();
+    Ok((digits, exp_sign * exp - fraction.len() as i64))
+}
+
+// Correctly rounds digits * 10^exp into format
+fn round_decimal(sign: bool, digits: &[u8], exp: i64, format: &Format, context: &mut Context) -> Value {
+    if digits.is_empty() {
+        return Value::zero(sign, format.clone());
+    }
+
+    // Values far outside of format's range are replaced by ones that round the same way, but are cheap to compute.
+    // Tiny values sit below the top MAX_NUM_RANDOM_BITS bits under the smallest subnormal, so even stochastic rounding
+    // can't round them up. The value is in [10^(num_digits + exp - 1), 10^(num_digits + exp)).
+    let min_log2 = (exp + digits.len() as i64 - 1) as f64 / 2f64.log10();
+    let max_log2 = (exp + digits.len() as i64) as f64 / 2f64.log10();
+    if min_log2 > (format.max_normal_exp() + 2) as f64 {
+        return round(sign, 1, format.max_normal_exp() + 2, format, context);
+    }
+    if max_log2 < (format.min_subnormal_exp() - MAX_NUM_RANDOM_BITS as i32 - 2) as f64 {
+        return round(sign, 1, format.min_subnormal_exp() - 64, format, context);
+    }
+
+    // digits * 10^exp == numerator / denominator
+    let mut numerator = BigUint::from_u64(0);
+    for &digit in digits {
+        numerator.mul_small(10);
+        numerator = numerator.add(&BigUint::from_u64(digit as u64));
+    }
+    let mut denominator = BigUint::from_u64(1);
+    if exp >= 0 {
+        numerator.mul_pow10(exp as u32);
+    } else {
+        denominator.mul_pow10(-exp as u32);
+    }
+
+    // Scale so the quotient has 101-102 bits, which is plenty for any format's sig and rounding bits, and fold
+    // the remainder into a sticky bit
+    let shift = 101 + denominator.bit_len() as i32 - numerator.bit_len() as i32;
+    if shift >= 0 {
+        numerator.mul_pow2(shift as u32);
+    } else {
+        denominator.mul_pow2(-shift as u32);
+    }
+    let quotient = numerator.div_rem(&denominator);
+    let sticky = if numerator.is_zero() { 0 } else { 1 };
+
+    round(sign, (quotient << 1) | sticky, -shift - 1, format, context)
+}
+
+// Shortest round-trip decimal, eg. "0.1"
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(special) = special_string(self) {
+            return f.write_str(&special);
+        }
+
+        let (digits, exp) = shortest_digits(self);
+        f.write_str(&decimal_string(self.sign, &digits, exp))
+    }
+}
+
+impl fmt::Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Value")
+            .field("sign", &self.sign)
+            .field("exp", &format_args!("{:#x}", self.exp))
+            .field("sig", &format_args!("{:#x}", self.sig))
+            .field("class", &self.classify())
+            .field("value", &format_args!("{}", self))
+            .field("format", &self.format)
+            .finish()
+    }
+}
+
+fn special_string(value: &Value) -> Option<String> {
+    let sign = if value.sign { "-" } else { "" };
+    if value.is_nan() {
+        Some(value.nan_string())
+    } else if value.is_inf() {
+        Some(format!("{}inf", sign))
+    } else if value.is_zero() {
+        Some(format!("{}0", sign))
+    } else {
+        None
+    }
+}
+
+// Digits d1 d2 ... dn and exp such that the finite nonzero value is exactly 0.d1d2...dn * 10^exp
+fn exact_digits(value: &Value) -> (Vec<u8>, i32) {
+    let (sig, exp) = value.decode_finite();
+
+    // sig * 2^exp == sig * 5^-exp / 10^-exp
+    let mut x = BigUint::from_u64(sig as u64);
+    let decimal_point = if exp >= 0 {
+        x.mul_pow2(exp as u32);
+        0
+    } else {
+        x.mul_pow5(-exp as u32);
+        exp
+    };
+
+    let mut digits = x.to_decimal_string().bytes().map(|digit| digit - b'0').collect::<Vec<_>>();
+    let exp = digits.len() as i32 + decimal_point;
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    (digits, exp)
+}
+
+// Shortest digits d1 d2 ... dn and exp such that 0.d1d2...dn * 10^exp rounds (to nearest even) back to the finite
+// nonzero value, following Steele & White's free-format algorithm with Burger & Dybvig's refinements
+fn shortest_digits(value: &Value) -> (Vec<u8>, i32) {
+    let (sig, exp) = value.decode_finite();
+
+    // The value's neighbors are an ulp away, except below powers of two, where the lower one is half an ulp away
+    let is_lower_gap_narrow = value.exp > 1 && value.sig == 0;
+    let gap_shift = if is_lower_gap_narrow { 1 } else { 0 };
+
+    // Scale value = r / s and the half-gaps to its neighbors m_plus / s, m_minus / s to integers
+    let mut r = BigUint::from_u64(sig as u64);
+    r.mul_pow2(1 + gap_shift);
+    let mut s = BigUint::from_u64(1);
+    s.mul_pow2(1 + gap_shift);
+    let mut m_plus = BigUint::from_u64(1);
+    m_plus.mul_pow2(gap_shift);
+    let mut m_minus = BigUint::from_u64(1);
+    if exp >= 0 {
+        r.mul_pow2(exp as u32);
+        m_plus.mul_pow2(exp as u32);
+        m_minus.mul_pow2(exp as u32);
+    } else {
+        s.mul_pow2(-exp as u32);
+    }
+
+    // Ties round to even, so the interval's endpoints round back to the value iff its sig is even
+    let is_inclusive = sig % 2 == 0;
+    let reaches = |high: &BigUint, s: &BigUint| if is_inclusive { *high >= *s } else { *high > *s };
+
+    // Estimate the decimal exponent, then fix it up so that it's the smallest k with r + m_plus < s * 10^k
+    let mut k = (((sig as f64).log2() + exp as f64) * 2f64.log10()).ceil() as i32;
+    if k >= 0 {
+        s.mul_pow10(k as u32);
+    } else {
+        r.mul_pow10(-k as u32);
+        m_plus.mul_pow10(-k as u32);
+        m_minus.mul_pow10(-k as u32);
+    }
+    while reaches(&r.add(&m_plus), &s) {
+        s.mul_small(10);
+        k += 1;
+    }
+    loop {
+        let mut high = r.add(&m_plus);
+        high.mul_small(10);
+        if reaches(&high, &s) {
+            break;
+        }
+        r.mul_small(10);
+        m_plus.mul_small(10);
+        m_minus.mul_small(10);
+        k -= 1;
+    }
+
+    // Generate digits until the remainder is within the rounding interval
+    let mut digits = Vec::new();
+    loop {
+        r.mul_small(10);
+        m_plus.mul_small(10);
+        m_minus.mul_small(10);
+        let digit = r.div_rem_digit(&s) as u8;
+
+        let is_low_done = if is_inclusive { r <= m_minus } else { r < m_minus };
+        let is_high_done = reaches(&r.add(&m_plus), &s);
+        let digit = match (is_low_done, is_high_done) {
+            (false, false) => {
+                digits.push(digit);
+                continue;
+            }
+            (true, false) => digit,
+            (false, true) => digit + 1,
+            (true, true) => if r.add(&r) < s { digit } else { digit + 1 },
+        };
+        digits.push(digit);
+        break;
+    }
+    (digits, k)
+}
+
+// Lays out 0.d1d2...dn * 10^exp positionally for moderate magnitudes, or in scientific notation otherwise
+fn decimal_string(sign: bool, digits: &[u8], exp: i32) -> String {
+    let digits = digits.iter().map(|digit| (b'0' + digit) as char).collect::<String>();
+    let num_digits = digits.len() as i32;
+
+    let mut res = if sign { "-".to_string() } else { String::new() };
+    if exp > 0 && exp <= 21 {
+        if num_digits <= exp {
+            res.push_str(&digits);
+            res.push_str(&"0".repeat((exp - num_digits) as usize));
+        } else {
+            res.push_str(&digits[..exp as usize]);
+            res.push('.');
+            res.push_str(&digits[exp as usize..]);
+        }
+    } else if exp <= 0 && exp > -6 {
+        res.push_str("0.");
+        res.push_str(&"0".repeat(-exp as usize));
+        res.push_str(&digits);
+    } else {
+        res.push_str(&digits[..1]);
+        if num_digits > 1 {
+            res.push('.');
+            res.push_str(&digits[1..]);
+        }
+        res.push_str(&format!("e{}", exp - 1));
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::flags::*;
+
+    fn nearest_even() -> Context {
+        let mut context = Context::new().with_rounding_mode(RoundingMode::NearestEven);
+        context.flush_to_zero = false;
+        context
+    }
+
+    // Same layout as Rust's `{:e}`, which also prints the shortest round-trip digits
+    fn scientific(value: &Value) -> String {
+        let (digits, exp) = shortest_digits(value);
+        let digits = digits.iter().map(|digit| (b'0' + digit) as char).collect::<String>();
+        let sign = if value.sign { "-" } else { "" };
+        if digits.len() == 1 {
+            format!("{}{}e{}", sign, digits, exp - 1)
+        } else {
+            format!("{}{}.{}e{}", sign, &digits[..1], &digits[1..], exp - 1)
+        }
+    }
+
+    #[test]
+    fn display() {
+        let f = Format::ieee754_single();
+
+        for &(bits, expected) in &[
+            (0x3f800000, "1"),
+            (0xbfc00000, "-1.5"),
+            (0x3dcccccd, "0.1"),
+            (0x3eaaaaab, "0.33333334"),
+            (0x42f6e979, "123.456"),
+            (0x4b800000, "16777216"),
+            (0x7f7fffff, "3.4028235e38"),
+            (0x00800000, "1.1754944e-38"),
+            (0x00000001, "1e-45"),
+            (0x80000000, "-0"),
+            (0x7f800000, "inf"),
+            (0xff800000, "-inf"),
+            (0x7fc00000, "nan"),
+            (0xffc0002a, "-nan(0x2a)"),
+            (0x7f800001, "snan"),
+            (0xff800002, "-snan(0x2)"),
+        ] {
+            assert_eq!(Value::from_bits(bits, f.clone()).to_string(), expected);
+        }
+
+        let half = Format::new(5, 10);
+
+        assert_eq!(Value::from_bits(0x7bff, half.clone()).to_string(), "65500");
+        assert_eq!(Value::from_bits(0x3555, half.clone()).to_string(), "0.3333");
+        assert_eq!(Value::from_bits(0x0001, half.clone()).to_string(), "6e-8");
+
+        assert_eq!(Value::from_bits(0x7e, Format::ocp_fp8_e4m3()).to_string(), "450"); // 448
+        assert_eq!(Value::from_bits(0x01, Format::ocp_fp8_e4m3()).to_string(), "0.002");
+        assert_eq!(Value::from_bits(0xb, Format::ocp_fp4_e2m1()).to_string(), "-1.5");
+    }
+
+    #[test]
+    fn shortest_matches_f32() {
+        // Walk the f32 encodings with a stride that's coprime to everything of interest
+        let mut bits = 1u32;
+        while bits < 0x7f800000 {
+            let x = f32::from_bits(bits);
+
+            assert_eq!(scientific(&Value::from_f32(x)), format!("{:e}", x));
+
+            bits += 0x3f3f1;
+        }
+
+        // Powers of two have a narrower gap below them
+        for exp in 1..255 {
+            let x = f32::from_bits(exp << 23);
+
+            assert_eq!(scientific(&Value::from_f32(x)), format!("{:e}", x));
+        }
+    }
+
+    #[test]
+    fn to_exact_string() {
+        let f = Format::ieee754_single();
+
+        assert_eq!(Value::from_bits(0x3dcccccd, f.clone()).to_exact_string(), "0.100000001490116119384765625");
+        assert_eq!(Value::from_bits(0xc2f6e979, f.clone()).to_exact_string(), "-123.45600128173828125");
+        assert_eq!(Value::from_bits(0x4b800000, f.clone()).to_exact_string(), "16777216");
+        assert_eq!(Value::from_bits(0x7f7fffff, f.clone()).to_exact_string(), "3.4028234663852885981170418348451692544e38");
+        assert_eq!(
+            Value::from_bits(0x00000001, f.clone()).to_exact_string(),
+            "1.40129846432481707092372958328991613128026194187651577175706828388979108268586060148663818836212158203125e-45"
+        );
+
+        assert_eq!(Value::from_bits(0x0001, Format::new(5, 10)).to_exact_string(), "5.9604644775390625e-8");
+    }
+
+    #[test]
+    fn debug() {
+        let x = Value::from_bits(0xbfc00000, Format::ieee754_single());
+
+        assert_eq!(
+            format!("{:?}", x),
+            "Value { sign: true, exp: 0x7f, sig: 0x400000, class: NegativeNormal, value: -1.5, \
+             format: Format { num_exp_bits: 8, num_sig_bits: 23, special_encoding: Ieee, exp_bias: 127 } }"
+        );
+    }
+
+    #[test]
+    fn parse() {
+        let f = Format::ieee754_single();
+
+        for &(s, expected) in &[
+            ("1", 0x3f800000),
+            ("-1.5", 0xbfc00000),
+            ("+0.1", 0x3dcccccd),
+            ("3.14159", 0x40490fd0),
+            ("1e10", 0x501502f9),
+            ("1E-10", 0x2edbe6ff),
+            ("123456789012345678901234567890", 0x6fc77488),
+            ("0.000000000000000000000000000000000000000000001", 0x00000001),
+            (".5", 0x3f000000),
+            ("5.", 0x40a00000),
+            ("-0", 0x80000000),
+            ("0e999", 0x00000000),
+            ("3.4028235e38", 0x7f7fffff),
+        ] {
+            let mut context = nearest_even();
+
+            assert_eq!(Value::parse(s, f.clone(), &mut context).unwrap().to_bits(), expected, "{}", s);
+        }
+
+        // Default context truncates and flushes
+        let mut context = Context::new();
+
+        assert_eq!(Value::parse("0.1", f.clone(), &mut context).unwrap().to_bits(), 0x3dcccccc);
+        assert_eq!(Value::parse("1e-40", f.clone(), &mut context).unwrap().to_bits(), 0x00000000);
+
+        let half = Format::new(5, 10);
+        let mut context = nearest_even();
+
+        assert_eq!(Value::parse("0.333", half.clone(), &mut context).unwrap().to_bits(), 0x3554);
+        assert_eq!(Value::parse("65519.99", half.clone(), &mut context).unwrap().to_bits(), 0x7bff);
+        assert_eq!(Value::parse("-1e-7", half.clone(), &mut context).unwrap().to_bits(), 0x8002);
+
+        let mut context = nearest_even();
+
+        assert_eq!(Value::parse("448", Format::ocp_fp8_e4m3(), &mut context).unwrap().to_bits(), 0x7e);
+        assert_eq!(Value::parse("-2.5", Format::ocp_fp4_e2m1(), &mut context).unwrap().to_bits(), 0xc); // -2.0
+        assert_eq!(Value::parse("1", Format::new(4, 3).with_exp_bias(11), &mut context).unwrap().to_bits(), 0x58);
+    }
+
+    #[test]
+    fn parse_halfway() {
+        let f = Format::ieee754_single();
+        let mut context = nearest_even();
+
+        // 2^24 + 1 is exactly halfway, and ties to even
+        assert_eq!(Value::parse("16777217", f.clone(), &mut context).unwrap().to_bits(), 0x4b800000);
+        assert_eq!(Value::parse("16777219", f.clone(), &mut context).unwrap().to_bits(), 0x4b800002);
+
+        // Digits far past the halfway point still count
+        assert_eq!(Value::parse("16777217.00000000000000000000000000000000001", f.clone(), &mut context).unwrap().to_bits(), 0x4b800001);
+        assert_eq!(Value::parse("16777216.99999999999999999999999999999999999", f.clone(), &mut context).unwrap().to_bits(), 0x4b800000);
+
+        // Halfway between the two smallest subnormals (2^-149 * 1.5), exactly and just above
+        let halfway = "2.101947696487225606385594374934874196920392912814773657635602425834686624028790902229957282543182373046875e-45";
+        let above = "2.1019476964872256063855943749348741969203929128147736576356024258346866240287909022299572825431823730468751e-45";
+
+        assert_eq!(Value::parse(halfway, f.clone(), &mut context).unwrap().to_bits(), 0x00000002);
+        assert_eq!(Value::parse(above, f.clone(), &mut context).unwrap().to_bits(), 0x00000002);
+        assert_eq!(Value::parse("1.401298464324817e-45", f.clone(), &mut context).unwrap().to_bits(), 0x00000001);
+        assert_eq!(Value::parse("7.006492321624085e-46", f.clone(), &mut context).unwrap().to_bits(), 0x00000000);
+        assert_eq!(Value::parse("7.0064923216240854e-46", f.clone(), &mut context).unwrap().to_bits(), 0x00000001);
+    }
+
+    #[test]
+    fn parse_matches_f32() {
+        let f = Format::ieee754_single();
+
+        // Shortest strings round-trip
+        let mut bits = 1u32;
+        while bits < 0x7f800000 {
+            let x = Value::from_bits(bits, f.clone());
+
+            assert_eq!(Value::parse(&x.to_string(), f.clone(), &mut nearest_even()).unwrap().to_bits(), bits);
+            assert_eq!(Value::parse(&x.to_exact_string(), f.clone(), &mut nearest_even()).unwrap().to_bits(), bits);
+
+            bits += 0x3f3f1;
+        }
+
+        // Arbitrary literals agree with the standard library
+        let mut state = 0x1234567u32;
+        for _ in 0..2000 {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let s = format!("{}.{}e{}", state % 100000, state / 7 % 1000000000, (state / 3 % 90) as i32 - 55);
+
+            let expected = s.parse::<f32>().unwrap().to_bits();
+
+            assert_eq!(Value::parse(&s, f.clone(), &mut nearest_even()).unwrap().to_bits(), expected, "{}", s);
+        }
+    }
+
+    #[test]
+    fn parse_flags() {
+        let f = Format::ieee754_single();
+
+        let mut context = nearest_even();
+        let _ = Value::parse("0.5", f.clone(), &mut context);
+        assert!(context.flags().is_empty());
+        let _ = Value::parse("0.1", f.clone(), &mut context);
+        assert_eq!(context.flags(), Flags::INEXACT);
+
+        let mut context = nearest_even();
+        let _ = Value::parse("1e-40", f.clone(), &mut context);
+        assert_eq!(context.flags(), Flags::UNDERFLOW | Flags::INEXACT);
+
+        let mut context = nearest_even();
+        let res = Value::parse("-1e39", f.clone(), &mut context).unwrap();
+        assert_eq!(res.to_bits(), 0xff800000);
+        assert_eq!(context.flags(), Flags::OVERFLOW | Flags::INEXACT);
+
+        let mut context = nearest_even();
+        let res = Value::parse("65520", Format::new(5, 10), &mut context).unwrap(); // halfway to 2^16, ties up
+        assert_eq!(res.to_bits(), 0x7c00);
+        assert_eq!(context.flags(), Flags::OVERFLOW | Flags::INEXACT);
+
+        // Absurd exponents
+        let mut context = nearest_even();
+        let res = Value::parse("1e99999999999999999999", f.clone(), &mut context).unwrap();
+        assert_eq!(res.to_bits(), 0x7f800000);
+        assert_eq!(context.flags(), Flags::OVERFLOW | Flags::INEXACT);
+
+        let mut context = nearest_even();
+        let res = Value::parse("-1e-99999999999999999999", f.clone(), &mut context).unwrap();
+        assert_eq!(res.to_bits(), 0x80000000);
+        assert_eq!(context.flags(), Flags::UNDERFLOW | Flags::INEXACT);
+    }
+
+    #[test]
+    fn parse_stochastic() {
+        let bfloat16 = Format::new(8, 7);
+
+        // 1.0 + 0.5 ulp
+        for &(random_bits, expected) in &[(0x00, 0x3f80), (0x7f, 0x3f80), (0x80, 0x3f81), (0xff, 0x3f81)] {
+            let mut context = Context::new()
+                .with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 8 })
+                .with_random_source(move |_| random_bits);
+
+            assert_eq!(Value::parse("1.00390625", bfloat16.clone(), &mut context).unwrap().to_bits(), expected);
+        }
+
+        // Values far below the smallest subnormal never round up
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 32 })
+            .with_random_source(|_| 0xffffffff);
+        context.flush_to_zero = false;
+
+        assert_eq!(Value::parse("1e-1000", bfloat16.clone(), &mut context).unwrap().to_bits(), 0x0000);
+
+        // But values within the top 32 bits below it can
+        let f = Format::ieee754_single();
+        assert_eq!(Value::parse("1e-47", f.clone(), &mut context).unwrap().to_bits(), 0x00000001); // 2^-7.1 ulp
+        assert_eq!(Value::parse("2e-47", f.clone(), &mut context).unwrap().to_bits(), 0x00000001);
+        assert_eq!(Value::parse("1e-54", f.clone(), &mut context).unwrap().to_bits(), 0x00000001); // 2^-30.4 ulp
+        assert_eq!(Value::parse("1e-56", f.clone(), &mut context).unwrap().to_bits(), 0x00000000); // 2^-37.1 ulp
+    }
+
+    #[test]
+    fn parse_special() {
+        let f = Format::ieee754_single();
+        let mut context = nearest_even();
+
+        for &(s, expected) in &[
+            ("inf", 0x7f800000),
+            ("-Infinity", 0xff800000),
+            ("+INF", 0x7f800000),
+            ("nan", 0x7fc00000),
+            ("-NaN", 0xffc00000),
+            ("nan(0x2a)", 0x7fc0002a),
+            ("nan(42)", 0x7fc0002a),
+            ("snan", 0x7f800001),
+            ("-snan(0x3fffff)", 0xffbfffff),
+        ] {
+            assert_eq!(Value::parse(s, f.clone(), &mut context).unwrap().to_bits(), expected, "{}", s);
+        }
+
+        assert_eq!(Value::parse("nan(0x400000)", f.clone(), &mut context).err(), Some(ParseValueError::InvalidNanPayload));
+        assert_eq!(Value::parse("snan(0)", f.clone(), &mut context).err(), Some(ParseValueError::InvalidNanPayload));
+        assert_eq!(Value::parse("nan(x)", f.clone(), &mut context).err(), Some(ParseValueError::InvalidNanPayload));
+        assert_eq!(Value::parse("nanny", f.clone(), &mut context).err(), Some(ParseValueError::InvalidSyntax));
+        assert!(context.flags().is_empty());
+
+        let e4m3 = Format::ocp_fp8_e4m3();
+
+        assert_eq!(Value::parse("-nan", e4m3.clone(), &mut context).unwrap().to_bits(), 0xff);
+        assert_eq!(Value::parse("inf", e4m3.clone(), &mut context).unwrap().to_bits(), 0x7f);
+        assert_eq!(Value::parse("snan", e4m3.clone(), &mut context).err(), Some(ParseValueError::InvalidNanPayload));
+
+        // Every NaN's display parses back to the same bits
+        let half = Format::new(5, 10);
+        for bits in (0x7c01..0x8000).chain(0xfc01..0x10000) {
+            let value = Value::from_bits(bits, half.clone());
+            let parsed = Value::parse(&value.to_string(), half.clone(), &mut context).unwrap();
+            assert_eq!(parsed.to_bits(), bits, "{}", value);
+        }
+        for &bits in &[0x7f, 0xff] {
+            let value = Value::from_bits(bits, e4m3.clone());
+            assert_eq!(Value::parse(&value.to_string(), e4m3.clone(), &mut context).unwrap().to_bits(), bits);
+        }
+
+        let mut saturate = Context::new().with_overflow_mode(OverflowMode::Saturate);
+
+        assert_eq!(Value::parse("-inf", e4m3.clone(), &mut saturate).unwrap().to_bits(), 0xfe);
+
+        let e2m1 = Format::ocp_fp4_e2m1();
+
+        assert_eq!(Value::parse("inf", e2m1.clone(), &mut context).unwrap().to_bits(), 0x7);
+        assert_eq!(Value::parse("nan", e2m1.clone(), &mut context).err(), Some(ParseValueError::NoNanEncoding));
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let f = Format::ieee754_single();
+        let mut context = nearest_even();
+
+        assert_eq!(Value::parse("", f.clone(), &mut context).err(), Some(ParseValueError::Empty));
+        for &s in &["-", ".", "e5", "1e", "1e+", "1.2.3", "1,5", "abc", " 1", "1 ", "0x", "--1", "infinite"] {
+            assert_eq!(Value::parse(s, f.clone(), &mut context).err(), Some(ParseValueError::InvalidSyntax), "{}", s);
+        }
+    }
+}
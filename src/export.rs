@@ -0,0 +1,360 @@
+// Stimulus and expected results for an op, written out for RTL testbenches: $readmemh hex, CSV, or TestFloat's text
+// format (as read by testfloat_ver and testfloat::parse_test_vectors)
+
+use std::io;
+use std::io::Write;
+
+use crate::context::*;
+use crate::coverage::*;
+use crate::flags::*;
+use crate::format::*;
+use crate::ops::*;
+use crate::value::*;
+use crate::verify::*;
+
+// Exhaustive stimulus is limited to 2^24 cases
+const MAX_EXHAUSTIVE_SOURCE_BITS: u32 = 24;
+
+// How source operands are chosen
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Stimulus {
+    // Every combination of encodings, in increasing (source1, source2, ...) bit order
+    Exhaustive,
+    // Uniformly random encodings, reproducible from seed
+    Random { num_cases: u64, seed: u64 },
+    // Every combination of corner values (see corner_values)
+    CornerCases,
+    // Up to num_cases source tuples that force target inside the op (see coverage::targeted_sources)
+    Targeted { target: Target, num_cases: u64, seed: u64 },
+}
+
+// One op evaluation: source encodings, and the result encoding and flags it produced
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TestCase {
+    pub(crate) sources: Vec<u32>,
+    pub(crate) result: u32,
+    pub(crate) flags: Flags,
+}
+
+impl TestCase {
+    pub fn sources(&self) -> &[u32] {
+        &self.sources
+    }
+
+    pub fn result(&self) -> u32 {
+        self.result
+    }
+
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+}
+
+// Encodings (of both signs) that tend to expose bugs: zeros, both ends of the subnormal and normal ranges, the values
+// around one, and whichever special values format has
+pub fn corner_values(format: &Format) -> Vec<u32> {
+    let min_normal = Value::min_normal(false, format.clone()).to_bits();
+    let max_finite = Value::max_finite(false, format.clone()).to_bits();
+    let mut values = vec![Value::zero(false, format.clone()).to_bits(), min_normal, min_normal + 1, max_finite - 1, max_finite];
+    if format.num_sig_bits > 0 {
+        values.push(Value::min_subnormal(false, format.clone()).to_bits());
+        values.push(format.sig_mask());
+    }
+
+    // One and its neighbors, if one is a normal value
+    let (max_exp, _) = format.max_finite_comps();
+    if format.exp_bias >= 1 && format.exp_bias <= max_exp as i32 {
+        let one = (format.exp_bias as u32) << format.num_sig_bits;
+        values.extend_from_slice(&[one - 1, one, one + 1]);
+    }
+
+    match format.special_encoding {
+        SpecialEncoding::Ieee => {
+            values.push(Value::infinity(false, format.clone()).to_bits());
+            if format.num_sig_bits > 0 {
+                values.push(Value::quiet_nan(format.clone()).to_bits());
+            }
+            if format.num_sig_bits > 1 {
+                values.push(Value::signaling_nan(format.clone()).to_bits());
+            }
+        }
+        SpecialEncoding::NanOnly => values.push(Value::quiet_nan(format.clone()).to_bits()),
+        SpecialEncoding::FiniteOnly => {}
+    }
+
+    // Drop neighbors that stepped past the max finite value into non-special encodings
+    let sign_bit = 1 << (format.num_storage_bits() - 1);
+    let mut values = values
+        .into_iter()
+        .filter(|&bits| {
+            let value = Value::from_bits(bits, format.clone());
+            bits <= max_finite || value.is_nan() || value.is_inf()
+        })
+        .flat_map(|bits| vec![bits, bits | sign_bit])
+        .collect::<Vec<_>>();
+    values.sort_unstable();
+    values.dedup();
+    values
+}
+
+// Evaluates op on the sources chosen by stimulus; each case starts with no flags raised
+pub fn generate_test_cases(op: &Op, format: &Format, stimulus: Stimulus, context: &mut Context) -> Vec<TestCase> {
+    let num_sources = op.num_sources();
+    let num_storage_bits = format.num_storage_bits();
+
+    let sources = match stimulus {
+        Stimulus::Exhaustive => {
+            let num_source_bits = num_sources as u32 * num_storage_bits;
+            if num_source_bits > MAX_EXHAUSTIVE_SOURCE_BITS {
+                panic!(
+                    "Requested exhaustive test cases over {} source bits, which exceeds the maximum of {} bits.",
+                    num_source_bits, MAX_EXHAUSTIVE_SOURCE_BITS
+                );
+            }
+
+            let mask = (1 << num_storage_bits) - 1;
+            (0..1u64 << num_source_bits)
+                .map(|index| (0..num_sources).rev().map(|i| ((index >> (i as u32 * num_storage_bits)) & mask) as u32).collect())
+                .collect::<Vec<Vec<u32>>>()
+        }
+        Stimulus::Random { num_cases, seed } => {
+            let mut random = Random::new(seed);
+            let mask = ((1u64 << num_storage_bits) - 1) as u32;
+            (0..num_cases).map(|_| (0..num_sources).map(|_| random.next_u32() & mask).collect()).collect()
+        }
+        Stimulus::CornerCases => {
+            let values = corner_values(format);
+            let num_combinations = values.len().pow(num_sources as u32);
+            (0..num_combinations)
+                .map(|index| {
+                    (0..num_sources)
+                        .rev()
+                        .map(|i| values[(index / values.len().pow(i as u32)) % values.len()])
+                        .collect()
+                })
+                .collect()
+        }
+        Stimulus::Targeted { target, num_cases, seed } => targeted_sources(op, format, target, num_cases, seed),
+    };
+
+    sources
+        .into_iter()
+        .map(|sources| {
+            context.clear_flags();
+            let result = op.evaluate(&sources, format, context).to_bits();
+            TestCase {
+                sources,
+                result,
+                flags: context.flags(),
+            }
+        })
+        .collect()
+}
+
+// Number of hex digits needed for a field of num_bits bits
+fn num_hex_digits(num_bits: u32) -> usize {
+    num_bits.div_ceil(4) as usize
+}
+
+// Flags take 5 bits, padded to 2 hex digits
+const NUM_FLAGS_HEX_DIGITS: usize = 2;
+
+// One packed word per line, for $readmemh: the sources, the result and the flags, each padded to whole hex digits and
+// separated by underscores (eg. "3c00_3c00_4000_00"), after a comment naming the fields
+pub fn write_readmemh<W: Write>(writer: &mut W, op: &Op, format: &Format, cases: &[TestCase]) -> io::Result<()> {
+    let source_digits = num_hex_digits(format.num_storage_bits());
+    let result_digits = num_hex_digits(op.result_format(format).num_storage_bits());
+
+    let field_names = (1..=op.num_sources()).map(|i| format!("source{}", i)).collect::<Vec<_>>().join(" ");
+    writeln!(writer, "// {} {}: {} result flags", op.name(), format, field_names)?;
+    for case in cases {
+        for source in &case.sources {
+            write!(writer, "{:0width$x}_", source, width = source_digits)?;
+        }
+        writeln!(writer, "{:0width$x}_{:02x}", case.result, case.flags.bits(), width = result_digits)?;
+    }
+    Ok(())
+}
+
+// A header row, then one row per case with every field in 0x-prefixed hex
+pub fn write_csv<W: Write>(writer: &mut W, op: &Op, format: &Format, cases: &[TestCase]) -> io::Result<()> {
+    let source_digits = num_hex_digits(format.num_storage_bits());
+    let result_digits = num_hex_digits(op.result_format(format).num_storage_bits());
+
+    for i in 1..=op.num_sources() {
+        write!(writer, "source{},", i)?;
+    }
+    writeln!(writer, "result,flags")?;
+    for case in cases {
+        for source in &case.sources {
+            write!(writer, "0x{:0width$x},", source, width = source_digits)?;
+        }
+        writeln!(writer, "0x{:0width$x},0x{:02x}", case.result, case.flags.bits(), width = result_digits)?;
+    }
+    Ok(())
+}
+
+// As testfloat_gen writes its vectors: space-separated uppercase hex, with the flags last
+pub fn write_testfloat<W: Write>(writer: &mut W, op: &Op, format: &Format, cases: &[TestCase]) -> io::Result<()> {
+    let source_digits = num_hex_digits(format.num_storage_bits());
+    let result_digits = num_hex_digits(op.result_format(format).num_storage_bits());
+
+    for case in cases {
+        for source in &case.sources {
+            write!(writer, "{:0width$X} ", source, width = source_digits)?;
+        }
+        writeln!(
+            writer,
+            "{:0width$X} {:0flags_width$X}",
+            case.result,
+            case.flags.bits(),
+            width = result_digits,
+            flags_width = NUM_FLAGS_HEX_DIGITS
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::rounding::*;
+    use crate::testfloat::*;
+
+    fn nearest_even() -> Context {
+        Context::new()
+            .with_rounding_mode(RoundingMode::NearestEven)
+            .with_flush_to_zero(false)
+            .with_denormals_are_zero(false)
+    }
+
+    #[test]
+    fn generate_exhaustive() {
+        let f = Format::ocp_fp4_e2m1();
+
+        let cases = generate_test_cases(&Op::Addition, &f, Stimulus::Exhaustive, &mut Context::new());
+
+        assert_eq!(cases.len(), 256);
+        assert_eq!(cases[0x13].sources(), &[0x1, 0x3]);
+        assert_eq!(cases[0x13].result(), 0x3); // 0.5 + 1.5 = 2.0
+        assert!(cases[0x13].flags().is_empty());
+
+        let cases = generate_test_cases(&Op::FusedMultiplyAdd, &f, Stimulus::Exhaustive, &mut Context::new());
+
+        assert_eq!(cases.len(), 4096);
+        assert_eq!(cases[0x123].sources(), &[0x1, 0x2, 0x3]);
+
+        let cases = generate_test_cases(&Op::Conversion(Format::ocp_fp4_e2m1()), &Format::ocp_fp8_e4m3(), Stimulus::Exhaustive, &mut Context::new());
+
+        assert_eq!(cases.len(), 256);
+        assert_eq!(cases[0x38].result(), 0x2); // 1.0
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested exhaustive test cases over 32 source bits, which exceeds the maximum of 24 bits.")]
+    fn generate_exhaustive_too_many() {
+        // Panic
+        let _ = generate_test_cases(&Op::Addition, &Format::new(5, 10), Stimulus::Exhaustive, &mut Context::new());
+    }
+
+    #[test]
+    fn generate_random() {
+        let f = Format::new(5, 10);
+        let stimulus = Stimulus::Random { num_cases: 1000, seed: 42 };
+
+        let cases = generate_test_cases(&Op::Multiplication, &f, stimulus, &mut Context::new());
+
+        assert_eq!(cases.len(), 1000);
+        assert!(cases.iter().all(|case| case.sources().iter().all(|&bits| bits >> 16 == 0)));
+        assert_eq!(cases, generate_test_cases(&Op::Multiplication, &f, stimulus, &mut Context::new()));
+        assert_ne!(cases, generate_test_cases(&Op::Multiplication, &f, Stimulus::Random { num_cases: 1000, seed: 43 }, &mut Context::new()));
+    }
+
+    #[test]
+    fn corner_values_formats() {
+        assert_eq!(
+            corner_values(&Format::new(5, 10)),
+            vec![
+                0x0000, 0x0001, 0x03ff, 0x0400, 0x0401, 0x3bff, 0x3c00, 0x3c01, 0x7bfe, 0x7bff, 0x7c00, 0x7c01, 0x7e00, //
+                0x8000, 0x8001, 0x83ff, 0x8400, 0x8401, 0xbbff, 0xbc00, 0xbc01, 0xfbfe, 0xfbff, 0xfc00, 0xfc01, 0xfe00,
+            ]
+        );
+
+        // No infinities, and the only NaN is all ones
+        let e4m3 = corner_values(&Format::ocp_fp8_e4m3());
+
+        assert!(e4m3.contains(&0x7e) && e4m3.contains(&0x7f) && e4m3.contains(&0xff));
+        assert_eq!(e4m3.len(), 2 * 11);
+
+        // One isn't representable, and there are no special values
+        let e2m1 = corner_values(&Format::ocp_fp4_e2m1().with_exp_bias(-3));
+
+        assert_eq!(e2m1, vec![0x0, 0x1, 0x2, 0x3, 0x6, 0x7, 0x8, 0x9, 0xa, 0xb, 0xe, 0xf]);
+
+        let cases = generate_test_cases(&Op::FusedMultiplyAdd, &Format::ocp_fp8_e5m2(), Stimulus::CornerCases, &mut Context::new());
+        let num_values = corner_values(&Format::ocp_fp8_e5m2()).len();
+
+        assert_eq!(cases.len(), num_values * num_values * num_values);
+    }
+
+    #[test]
+    fn targeted() {
+        let f = Format::new(5, 10);
+        let stimulus = Stimulus::Targeted { target: Target::Cancellation(11), num_cases: 20, seed: 42 };
+
+        let cases = generate_test_cases(&Op::Addition, &f, stimulus, &mut nearest_even());
+
+        assert_eq!(cases.len(), 20);
+        // Cancellation leaves the difference exact
+        assert!(cases.iter().all(|case| case.flags().is_empty()));
+    }
+
+    #[test]
+    fn write_formats() {
+        let f = Format::new(5, 10);
+        let cases = vec![
+            TestCase { sources: vec![0x3c00, 0x3c00], result: 0x4000, flags: Flags::empty() },
+            TestCase { sources: vec![0x7bff, 0x7bff], result: 0x7c00, flags: Flags::OVERFLOW | Flags::INEXACT },
+        ];
+
+        let mut readmemh = Vec::new();
+        write_readmemh(&mut readmemh, &Op::Addition, &f, &cases).unwrap();
+
+        assert_eq!(String::from_utf8(readmemh).unwrap(), "// addition e5m10: source1 source2 result flags\n3c00_3c00_4000_00\n7bff_7bff_7c00_05\n");
+
+        let mut csv = Vec::new();
+        write_csv(&mut csv, &Op::Addition, &f, &cases).unwrap();
+
+        assert_eq!(String::from_utf8(csv).unwrap(), "source1,source2,result,flags\n0x3c00,0x3c00,0x4000,0x00\n0x7bff,0x7bff,0x7c00,0x05\n");
+
+        let mut testfloat = Vec::new();
+        write_testfloat(&mut testfloat, &Op::Addition, &f, &cases).unwrap();
+
+        assert_eq!(String::from_utf8(testfloat).unwrap(), "3C00 3C00 4000 00\n7BFF 7BFF 7C00 05\n");
+
+        // Fields are padded to whole hex digits
+        let cases = vec![TestCase { sources: vec![0x3], result: 0x01, flags: Flags::INEXACT }];
+        let mut readmemh = Vec::new();
+        write_readmemh(&mut readmemh, &Op::Conversion(Format::ocp_fp4_e2m1()), &Format::ocp_fp6_e3m2(), &cases).unwrap();
+
+        assert_eq!(String::from_utf8(readmemh).unwrap(), "// conversion e3m2+finite: source1 result flags\n03_1_01\n");
+    }
+
+    #[test]
+    fn testfloat_round_trip() {
+        let f = Format::new(5, 10);
+
+        for &stimulus in &[Stimulus::CornerCases, Stimulus::Random { num_cases: 2000, seed: 7 }] {
+            let cases = generate_test_cases(&Op::Multiplication, &f, stimulus, &mut nearest_even());
+            let mut text = Vec::new();
+            write_testfloat(&mut text, &Op::Multiplication, &f, &cases).unwrap();
+
+            let function = TestFloatFunction::Multiplication(f.clone());
+            let vectors = parse_test_vectors(&String::from_utf8(text).unwrap(), &function).unwrap();
+
+            assert_eq!(vectors.len(), cases.len());
+            assert!(run_test_vectors(&function, &vectors, RoundingMode::NearestEven, TininessMode::BeforeRounding).is_empty());
+        }
+    }
+}
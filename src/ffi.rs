@@ -0,0 +1,276 @@
+// C ABI for golden-model use from testbenches (eg. through SystemVerilog DPI-C), declared in include/softy.h and
+// include/softy_dpi.svh. Every entry point takes the format as its widths and special encoding, the modes packed into
+// one word (see MODE_*), random bits for stochastic rounding, and raw source bits; it returns the raw result bits and
+// writes the flags word (in SoftFloat's bit positions) through flags. Invalid arguments return 0 and set FLAG_ERROR
+// instead of panicking across the ABI.
+
+use std::panic;
+use std::panic::AssertUnwindSafe;
+
+use crate::context::*;
+use crate::format::*;
+use crate::ops::*;
+use crate::rounding::*;
+
+// Rounding mode, in bits 0-2: 0 nearest even, 1 nearest away, 2 toward zero, 3 toward positive, 4 toward negative,
+// 5 stochastic (with the number of random bits in MODE_NUM_RANDOM_BITS_MASK)
+pub const MODE_ROUNDING_MASK: u32 = 0x7;
+pub const MODE_FLUSH_TO_ZERO: u32 = 1 << 3;
+pub const MODE_DENORMALS_ARE_ZERO: u32 = 1 << 4;
+pub const MODE_TININESS_AFTER_ROUNDING: u32 = 1 << 5;
+pub const MODE_SATURATE: u32 = 1 << 6;
+// NaN mode, in bits 7-8: 0 canonical, 1 propagate first, 2 x86, 3 ARM
+pub const MODE_NAN_SHIFT: u32 = 7;
+pub const MODE_NAN_MASK: u32 = 0x3 << MODE_NAN_SHIFT;
+pub const MODE_IEEE_ROUNDING: u32 = 1 << 9;
+// Number of random bits for stochastic rounding (1-32), in bits 16-21
+pub const MODE_NUM_RANDOM_BITS_SHIFT: u32 = 16;
+pub const MODE_NUM_RANDOM_BITS_MASK: u32 = 0x3f << MODE_NUM_RANDOM_BITS_SHIFT;
+
+// Special encoding argument values
+pub const SPECIAL_ENCODING_IEEE: u32 = 0;
+pub const SPECIAL_ENCODING_NAN_ONLY: u32 = 1;
+pub const SPECIAL_ENCODING_FINITE_ONLY: u32 = 2;
+
+// Set in the flags word (alone) when the arguments are invalid
+pub const FLAG_ERROR: u32 = 1 << 31;
+
+fn format(num_exp_bits: u32, num_sig_bits: u32, special_encoding: u32) -> Option<Format> {
+    let special_encoding = match special_encoding {
+        SPECIAL_ENCODING_IEEE => SpecialEncoding::Ieee,
+        SPECIAL_ENCODING_NAN_ONLY => SpecialEncoding::NanOnly,
+        SPECIAL_ENCODING_FINITE_ONLY => SpecialEncoding::FiniteOnly,
+        _ => return None,
+    };
+    Format::try_from_widths(num_exp_bits, num_sig_bits).ok()?.try_with_special_encoding(special_encoding).ok()
+}
+
+// Unpacks a modes word (see MODE_*), or None if it holds an invalid mode
+pub(crate) fn context(modes: u32, random_bits: u32) -> Option<Context> {
+    let rounding_mode = match modes & MODE_ROUNDING_MASK {
+        0 => RoundingMode::NearestEven,
+        1 => RoundingMode::NearestAway,
+        2 => RoundingMode::TowardZero,
+        3 => RoundingMode::TowardPositive,
+        4 => RoundingMode::TowardNegative,
+        5 => {
+            let num_random_bits = (modes & MODE_NUM_RANDOM_BITS_MASK) >> MODE_NUM_RANDOM_BITS_SHIFT;
+            if num_random_bits == 0 || num_random_bits > MAX_NUM_RANDOM_BITS {
+                return None;
+            }
+            RoundingMode::Stochastic { num_random_bits }
+        }
+        _ => return None,
+    };
+    let nan_mode = match (modes & MODE_NAN_MASK) >> MODE_NAN_SHIFT {
+        0 => NanMode::Canonical,
+        1 => NanMode::PropagateFirst,
+        2 => NanMode::X86,
+        _ => NanMode::Arm,
+    };
+
+    Some(
+        Context::new()
+            .with_rounding_mode(rounding_mode)
+            .with_flush_to_zero(modes & MODE_FLUSH_TO_ZERO != 0)
+            .with_denormals_are_zero(modes & MODE_DENORMALS_ARE_ZERO != 0)
+            .with_tininess_mode(if modes & MODE_TININESS_AFTER_ROUNDING != 0 {
+                TininessMode::AfterRounding
+            } else {
+                TininessMode::BeforeRounding
+            })
+            .with_overflow_mode(if modes & MODE_SATURATE != 0 { OverflowMode::Saturate } else { OverflowMode::Ieee })
+            .with_nan_mode(nan_mode)
+            .with_ieee_rounding(modes & MODE_IEEE_ROUNDING != 0)
+            .with_random_source(move |_| random_bits),
+    )
+}
+
+fn evaluate(op: Option<Op>, format: Option<Format>, modes: u32, random_bits: u32, sources: &[u32], flags: Option<&mut u32>) -> u32 {
+    let result = || {
+        let (op, format, mut context) = (op?, format?, context(modes, random_bits)?);
+        if sources.iter().any(|&source| (source as u64) >> format.num_storage_bits() != 0) {
+            return None;
+        }
+
+        let result = op.evaluate(sources, &format, &mut context);
+        Some((result.to_bits(), context.flags().bits()))
+    };
+
+    // Unwinding into C aborts, so a panic past the checks above is reported as an error too
+    let result = panic::catch_unwind(AssertUnwindSafe(result)).ok().flatten();
+    let (result, result_flags) = result.unwrap_or((0, FLAG_ERROR));
+    if let Some(flags) = flags {
+        *flags = result_flags;
+    }
+    result
+}
+
+#[no_mangle]
+pub extern "C" fn softy_addition(
+    num_exp_bits: u32,
+    num_sig_bits: u32,
+    special_encoding: u32,
+    modes: u32,
+    random_bits: u32,
+    source1: u32,
+    source2: u32,
+    flags: Option<&mut u32>,
+) -> u32 {
+    let format = format(num_exp_bits, num_sig_bits, special_encoding);
+    evaluate(Some(Op::Addition), format, modes, random_bits, &[source1, source2], flags)
+}
+
+#[no_mangle]
+pub extern "C" fn softy_multiplication(
+    num_exp_bits: u32,
+    num_sig_bits: u32,
+    special_encoding: u32,
+    modes: u32,
+    random_bits: u32,
+    source1: u32,
+    source2: u32,
+    flags: Option<&mut u32>,
+) -> u32 {
+    let format = format(num_exp_bits, num_sig_bits, special_encoding);
+    evaluate(Some(Op::Multiplication), format, modes, random_bits, &[source1, source2], flags)
+}
+
+#[no_mangle]
+pub extern "C" fn softy_fused_multiply_add(
+    num_exp_bits: u32,
+    num_sig_bits: u32,
+    special_encoding: u32,
+    modes: u32,
+    random_bits: u32,
+    source1: u32,
+    source2: u32,
+    source3: u32,
+    flags: Option<&mut u32>,
+) -> u32 {
+    let format = format(num_exp_bits, num_sig_bits, special_encoding);
+    evaluate(Some(Op::FusedMultiplyAdd), format, modes, random_bits, &[source1, source2, source3], flags)
+}
+
+// From the source format to the target format
+#[no_mangle]
+pub extern "C" fn softy_conversion(
+    num_exp_bits: u32,
+    num_sig_bits: u32,
+    special_encoding: u32,
+    target_num_exp_bits: u32,
+    target_num_sig_bits: u32,
+    target_special_encoding: u32,
+    modes: u32,
+    random_bits: u32,
+    source: u32,
+    flags: Option<&mut u32>,
+) -> u32 {
+    let format = format(num_exp_bits, num_sig_bits, special_encoding);
+    let op = self::format(target_num_exp_bits, target_num_sig_bits, target_special_encoding).map(Op::Conversion);
+    evaluate(op, format, modes, random_bits, &[source], flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::flags::*;
+
+    const NEAREST_EVEN: u32 = 0;
+    const TOWARD_ZERO: u32 = 2;
+    const STOCHASTIC: u32 = 5;
+
+    #[test]
+    fn ops() {
+        let mut flags = 0xff;
+
+        assert_eq!(softy_addition(8, 23, SPECIAL_ENCODING_IEEE, NEAREST_EVEN, 0, 0x3f800000, 0x3f800000, Some(&mut flags)), 0x40000000);
+        assert_eq!(flags, 0);
+        assert_eq!(softy_addition(8, 23, SPECIAL_ENCODING_IEEE, TOWARD_ZERO, 0, 0x3f800000, 0x33800001, Some(&mut flags)), 0x3f800000);
+        assert_eq!(flags, 0); // Addend truncated while aligning
+        assert_eq!(softy_addition(8, 23, SPECIAL_ENCODING_IEEE, TOWARD_ZERO | MODE_IEEE_ROUNDING, 0, 0x3f800000, 0x33800001, Some(&mut flags)), 0x3f800000);
+        assert_eq!(flags, Flags::INEXACT.bits());
+        assert_eq!(softy_multiplication(5, 10, SPECIAL_ENCODING_IEEE, NEAREST_EVEN, 0, 0x7bff, 0x7bff, Some(&mut flags)), 0x7c00);
+        assert_eq!(flags, (Flags::OVERFLOW | Flags::INEXACT).bits());
+        assert_eq!(softy_multiplication(5, 10, SPECIAL_ENCODING_IEEE, NEAREST_EVEN | MODE_SATURATE, 0, 0x7bff, 0x7bff, Some(&mut flags)), 0x7bff);
+        assert_eq!(softy_fused_multiply_add(4, 3, SPECIAL_ENCODING_NAN_ONLY, NEAREST_EVEN, 0, 0x38, 0x38, 0x38, Some(&mut flags)), 0x40); // 1 * 1 + 1
+        assert_eq!(flags, 0);
+        assert_eq!(softy_conversion(8, 23, SPECIAL_ENCODING_IEEE, 2, 1, SPECIAL_ENCODING_FINITE_ONLY, NEAREST_EVEN, 0, 0x3f800000, Some(&mut flags)), 0x2);
+        assert_eq!(flags, 0);
+
+        // Flags are optional
+        assert_eq!(softy_addition(8, 23, SPECIAL_ENCODING_IEEE, NEAREST_EVEN, 0, 0x3f800000, 0x3f800000, None), 0x40000000);
+    }
+
+    #[test]
+    fn modes() {
+        let mut flags = 0;
+
+        // Denormal source read as zero, or not
+        assert_eq!(softy_addition(5, 10, SPECIAL_ENCODING_IEEE, NEAREST_EVEN | MODE_DENORMALS_ARE_ZERO, 0, 0x0001, 0x0001, Some(&mut flags)), 0x0000);
+        assert_eq!(softy_addition(5, 10, SPECIAL_ENCODING_IEEE, NEAREST_EVEN, 0, 0x0001, 0x0001, Some(&mut flags)), 0x0002);
+
+        // Tiny result flushed to zero
+        assert_eq!(softy_multiplication(5, 10, SPECIAL_ENCODING_IEEE, NEAREST_EVEN | MODE_FLUSH_TO_ZERO, 0, 0x0400, 0x3800, Some(&mut flags)), 0x0000);
+        assert_eq!(flags, (Flags::UNDERFLOW | Flags::INEXACT).bits());
+
+        // Generated NaN
+        let x86 = 2 << MODE_NAN_SHIFT;
+        assert_eq!(softy_addition(5, 10, SPECIAL_ENCODING_IEEE, NEAREST_EVEN | x86, 0, 0x7c00, 0xfc00, Some(&mut flags)), 0xfe00);
+        assert_eq!(flags, Flags::INVALID.bits());
+
+        // 1 + 2^-11 rounds up iff the random bits reach the discarded half
+        let stochastic = STOCHASTIC | MODE_IEEE_ROUNDING | (1 << MODE_NUM_RANDOM_BITS_SHIFT);
+        assert_eq!(softy_addition(5, 10, SPECIAL_ENCODING_IEEE, stochastic, 0, 0x3c00, 0x1000, Some(&mut flags)), 0x3c00);
+        assert_eq!(softy_addition(5, 10, SPECIAL_ENCODING_IEEE, stochastic, 1, 0x3c00, 0x1000, Some(&mut flags)), 0x3c01);
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let mut flags = 0;
+
+        assert_eq!(softy_addition(1, 10, SPECIAL_ENCODING_IEEE, NEAREST_EVEN, 0, 0, 0, Some(&mut flags)), 0);
+        assert_eq!(flags, FLAG_ERROR);
+        assert_eq!(softy_addition(5, 10, 3, NEAREST_EVEN, 0, 0, 0, Some(&mut flags)), 0);
+        assert_eq!(flags, FLAG_ERROR);
+        assert_eq!(softy_addition(5, 10, SPECIAL_ENCODING_IEEE, 6, 0, 0, 0, Some(&mut flags)), 0);
+        assert_eq!(flags, FLAG_ERROR);
+        assert_eq!(softy_addition(5, 10, SPECIAL_ENCODING_IEEE, STOCHASTIC, 0, 0, 0, Some(&mut flags)), 0);
+        assert_eq!(flags, FLAG_ERROR);
+        assert_eq!(softy_addition(5, 10, SPECIAL_ENCODING_IEEE, NEAREST_EVEN, 0, 0x10000, 0, Some(&mut flags)), 0);
+        assert_eq!(flags, FLAG_ERROR);
+        assert_eq!(softy_conversion(5, 10, SPECIAL_ENCODING_IEEE, 2, 0, SPECIAL_ENCODING_NAN_ONLY, NEAREST_EVEN, 0, 0, Some(&mut flags)), 0);
+        assert_eq!(flags, FLAG_ERROR);
+        assert_eq!(softy_conversion(8, 23, SPECIAL_ENCODING_IEEE, 8, 0, SPECIAL_ENCODING_IEEE, NEAREST_EVEN, 0, 0x7fc00000, Some(&mut flags)), 0);
+        assert_eq!(flags, FLAG_ERROR); // No NaN encoding in the target
+
+        // Without sig bits, only finite-only formats are valid
+        assert_eq!(softy_conversion(8, 23, SPECIAL_ENCODING_IEEE, 8, 0, SPECIAL_ENCODING_FINITE_ONLY, NEAREST_EVEN, 0, 0x3f800000, Some(&mut flags)), 0x7f);
+        assert_eq!(flags, 0);
+    }
+
+    #[test]
+    fn declarations() {
+        let header = include_str!("../include/softy.h");
+        let dpi = include_str!("../include/softy_dpi.svh");
+
+        for name in &["softy_addition", "softy_multiplication", "softy_fused_multiply_add", "softy_conversion"] {
+            assert!(header.contains(&format!("uint32_t {}(", name)), "{} missing from softy.h", name);
+            assert!(dpi.contains(&format!("int unsigned {}(", name)), "{} missing from softy_dpi.svh", name);
+        }
+        for (name, value) in &[
+            ("MODE_FLUSH_TO_ZERO", MODE_FLUSH_TO_ZERO),
+            ("MODE_DENORMALS_ARE_ZERO", MODE_DENORMALS_ARE_ZERO),
+            ("MODE_TININESS_AFTER_ROUNDING", MODE_TININESS_AFTER_ROUNDING),
+            ("MODE_SATURATE", MODE_SATURATE),
+            ("MODE_NAN_SHIFT", MODE_NAN_SHIFT),
+            ("MODE_IEEE_ROUNDING", MODE_IEEE_ROUNDING),
+            ("MODE_NUM_RANDOM_BITS_SHIFT", MODE_NUM_RANDOM_BITS_SHIFT),
+            ("FLAG_ERROR", FLAG_ERROR),
+        ] {
+            assert!(header.contains(&format!("#define SOFTY_{} {:#x}u", name, value)), "SOFTY_{} missing from softy.h", name);
+            assert!(dpi.contains(&format!("SOFTY_{} = 32'h{:x};", name, value)), "SOFTY_{} missing from softy_dpi.svh", name);
+        }
+    }
+}
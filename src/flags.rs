@@ -0,0 +1,121 @@
+use std::fmt;
+use std::ops::{BitOr, BitOrAssign};
+
+// Accrued exception flags, using Berkeley SoftFloat's bit assignments
+#[derive(Clone, Copy, Default, Eq, PartialEq)]
+pub struct Flags {
+    bits: u32,
+}
+
+impl Flags {
+    pub const INEXACT: Flags = Flags { bits: 1 << 0 };
+    pub const UNDERFLOW: Flags = Flags { bits: 1 << 1 };
+    pub const OVERFLOW: Flags = Flags { bits: 1 << 2 };
+    // SoftFloat's "infinite": an exact infinite result from finite sources (eg. 1 / 0)
+    pub const DIVIDE_BY_ZERO: Flags = Flags { bits: 1 << 3 };
+    pub const INVALID: Flags = Flags { bits: 1 << 4 };
+
+    pub(crate) const NAMES: &'static [(Flags, &'static str)] = &[
+        (Flags::INEXACT, "INEXACT"),
+        (Flags::UNDERFLOW, "UNDERFLOW"),
+        (Flags::OVERFLOW, "OVERFLOW"),
+        (Flags::DIVIDE_BY_ZERO, "DIVIDE_BY_ZERO"),
+        (Flags::INVALID, "INVALID"),
+    ];
+
+    pub fn empty() -> Flags {
+        Flags { bits: 0 }
+    }
+
+    pub fn from_bits(bits: u32) -> Flags {
+        Flags { bits }
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    pub fn contains(&self, other: Flags) -> bool {
+        (self.bits & other.bits) == other.bits
+    }
+
+    pub fn insert(&mut self, other: Flags) {
+        self.bits |= other.bits;
+    }
+}
+
+impl BitOr for Flags {
+    type Output = Flags;
+
+    fn bitor(self, other: Flags) -> Flags {
+        Flags { bits: self.bits | other.bits }
+    }
+}
+
+impl BitOrAssign for Flags {
+    fn bitor_assign(&mut self, other: Flags) {
+        self.insert(other);
+    }
+}
+
+impl fmt::Debug for Flags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = Flags::NAMES
+            .iter()
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|(_, name)| *name)
+            .collect::<Vec<_>>();
+        write!(f, "Flags({})", if names.is_empty() { "empty".to_string() } else { names.join(" | ") })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty() {
+        let flags = Flags::empty();
+
+        assert!(flags.is_empty());
+        assert_eq!(flags.bits(), 0);
+        assert_eq!(flags, Flags::default());
+        assert!(!flags.contains(Flags::OVERFLOW));
+    }
+
+    #[test]
+    fn insert() {
+        let mut flags = Flags::empty();
+        flags.insert(Flags::OVERFLOW);
+
+        assert!(!flags.is_empty());
+        assert!(flags.contains(Flags::OVERFLOW));
+        assert_eq!(flags.bits(), 0x04);
+        assert_eq!(flags, Flags::from_bits(0x04));
+
+        let mut flags = Flags::empty();
+        flags |= Flags::OVERFLOW;
+
+        assert_eq!(flags, Flags::empty() | Flags::OVERFLOW);
+
+        flags |= Flags::INVALID;
+
+        assert!(flags.contains(Flags::OVERFLOW));
+        assert!(flags.contains(Flags::INVALID));
+        assert!(flags.contains(Flags::OVERFLOW | Flags::INVALID));
+        assert_eq!(flags.bits(), 0x14);
+    }
+
+    #[test]
+    fn debug() {
+        assert_eq!(format!("{:?}", Flags::empty()), "Flags(empty)");
+        assert_eq!(format!("{:?}", Flags::OVERFLOW), "Flags(OVERFLOW)");
+        assert_eq!(format!("{:?}", Flags::INVALID | Flags::OVERFLOW), "Flags(OVERFLOW | INVALID)");
+        assert_eq!(format!("{:?}", Flags::from_bits(0x13)), "Flags(INEXACT | UNDERFLOW | INVALID)");
+        assert_eq!(format!("{:?}", Flags::DIVIDE_BY_ZERO), "Flags(DIVIDE_BY_ZERO)");
+    }
+}
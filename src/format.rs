@@ -1,32 +1,203 @@
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SpecialEncoding {
+    // IEEE 754: the max exponent encodes infinities (zero sig) and NaNs (nonzero sig)
+    Ieee,
+    // No infinities; only the max exponent with an all-ones sig encodes NaN (eg. OCP FP8 E4M3)
+    NanOnly,
+    // No infinities or NaNs; every encoding is a finite value (eg. OCP FP6 and FP4)
+    FiniteOnly,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Format {
     pub(crate) num_exp_bits: u32,
     pub(crate) num_sig_bits: u32,
+    pub(crate) special_encoding: SpecialEncoding,
+    pub(crate) exp_bias: i32,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FormatError {
+    NotEnoughExpBits,
+    TooManyStorageBits { num_storage_bits: u64 },
+    NanOnlyWithoutSigBits,
+    IeeeWithoutSigBits,
+    ExpBiasOutOfRange { exp_bias: i64, min_exp_bias: i64, max_exp_bias: i64 },
+    UnsignedUnsupported,
+    InvalidSpec(String),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FormatError::NotEnoughExpBits => write!(f, "Requested format must have at least {} exponent bits.", MIN_EXP_BITS),
+            FormatError::TooManyStorageBits { num_storage_bits } => write!(
+                f,
+                "Requested format requires {} storage bits, which exceeds the maximum storage bit width of {} bits.",
+                num_storage_bits, MAX_STORAGE_BITS
+            ),
+            FormatError::NanOnlyWithoutSigBits => {
+                write!(f, "Requested format must have at least 1 significand bit to encode NaN without infinity.")
+            }
+            FormatError::IeeeWithoutSigBits => {
+                write!(f, "Requested format must have at least 1 significand bit to encode NaN alongside infinity.")
+            }
+            FormatError::ExpBiasOutOfRange { exp_bias, min_exp_bias, max_exp_bias } => write!(
+                f,
+                "Requested format has exponent bias {}, but it must be between {} and {}.",
+                exp_bias, min_exp_bias, max_exp_bias
+            ),
+            FormatError::UnsignedUnsupported => write!(f, "Requested format is unsigned, but all formats have a sign bit."),
+            FormatError::InvalidSpec(spec) => write!(
+                f,
+                "Requested format \"{}\" is not a valid specification; expected eg. \"e5m10\", \"e4m3fn\", \"e2m1+finite\", \"e8m7+bias120\", \"fp16\" or \"bf16\".",
+                spec
+            ),
+        }
+    }
 }
 
+impl error::Error for FormatError {}
+
+const MIN_EXP_BITS: u32 = 2;
+const MAX_STORAGE_BITS: u32 = 32;
+
+// Unbiased exps are kept within +/-2^30, so sums of a few of them (as in products) can't overflow an i32
+const MAX_EXP_MAGNITUDE: i64 = 1 << 30;
+
 impl Format {
     pub fn new(num_exp_bits: u32, num_sig_bits: u32) -> Format {
-        let min_exp_bits = 2;
-        if num_exp_bits < min_exp_bits {
-            panic!("Requested format must have at least 2 exponent bits.");
+        match Format::try_new(num_exp_bits, num_sig_bits) {
+            Ok(format) => format,
+            Err(error) => panic!("{}", error),
         }
+    }
 
-        let num_storage_bits = 1 + num_exp_bits + num_sig_bits;
-        let max_storage_bits = 32;
-        if num_storage_bits > max_storage_bits {
-            panic!("Requested format requires {} storage bits, which exceeds the maximum storage bit width of {} bits.", num_storage_bits, max_storage_bits);
+    pub fn try_new(num_exp_bits: u32, num_sig_bits: u32) -> Result<Format, FormatError> {
+        let format = Format::try_from_widths(num_exp_bits, num_sig_bits)?;
+        format.check_special_encoding()?;
+        Ok(format)
+    }
+
+    // Checks the widths only, leaving the (IEEE) special encoding for the caller to check once it's final
+    pub(crate) fn try_from_widths(num_exp_bits: u32, num_sig_bits: u32) -> Result<Format, FormatError> {
+        if num_exp_bits < MIN_EXP_BITS {
+            return Err(FormatError::NotEnoughExpBits);
+        }
+
+        let num_storage_bits = 1 + num_exp_bits as u64 + num_sig_bits as u64;
+        if num_storage_bits > MAX_STORAGE_BITS as u64 {
+            return Err(FormatError::TooManyStorageBits { num_storage_bits });
         }
 
-        Format {
+        Ok(Format {
             num_exp_bits,
             num_sig_bits,
+            special_encoding: SpecialEncoding::Ieee,
+            exp_bias: Format::default_exp_bias(num_exp_bits),
+        })
+    }
+
+    fn default_exp_bias(num_exp_bits: u32) -> i32 {
+        (1 << (num_exp_bits - 1)) - 1
+    }
+
+    // Without sig bits, only finite values can be encoded
+    fn check_special_encoding(&self) -> Result<(), FormatError> {
+        if self.num_sig_bits == 0 {
+            match self.special_encoding {
+                SpecialEncoding::Ieee => return Err(FormatError::IeeeWithoutSigBits),
+                SpecialEncoding::NanOnly => return Err(FormatError::NanOnlyWithoutSigBits),
+                SpecialEncoding::FiniteOnly => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn with_exp_bias(self, exp_bias: i32) -> Format {
+        match self.try_with_exp_bias(exp_bias) {
+            Ok(format) => format,
+            Err(error) => panic!("{}", error),
         }
     }
 
+    pub fn try_with_exp_bias(self, exp_bias: i32) -> Result<Format, FormatError> {
+        self.check_exp_bias(exp_bias as i64)?;
+
+        Ok(Format {
+            exp_bias,
+            ..self
+        })
+    }
+
+    // Biases are bounded by the exponent range (either way), and by keeping every unbiased exp within
+    // MAX_EXP_MAGNITUDE
+    fn check_exp_bias(&self, exp_bias: i64) -> Result<(), FormatError> {
+        let num_exps = 1i64 << self.num_exp_bits;
+        let min_exp_bias = (-num_exps).max((num_exps - 1) - MAX_EXP_MAGNITUDE);
+        let max_exp_bias = num_exps.min(1 - self.num_sig_bits as i64 + MAX_EXP_MAGNITUDE);
+        if exp_bias < min_exp_bias || exp_bias > max_exp_bias {
+            return Err(FormatError::ExpBiasOutOfRange { exp_bias, min_exp_bias, max_exp_bias });
+        }
+        Ok(())
+    }
+
+    pub fn with_special_encoding(self, special_encoding: SpecialEncoding) -> Format {
+        match self.try_with_special_encoding(special_encoding) {
+            Ok(format) => format,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    pub fn try_with_special_encoding(self, special_encoding: SpecialEncoding) -> Result<Format, FormatError> {
+        let format = Format {
+            special_encoding,
+            ..self
+        };
+        format.check_special_encoding()?;
+        Ok(format)
+    }
+
     pub fn ieee754_single() -> Format {
         Format::new(8, 23)
     }
 
+    pub fn ocp_fp8_e5m2() -> Format {
+        Format::new(5, 2)
+    }
+
+    pub fn ocp_fp8_e4m3() -> Format {
+        Format::new(4, 3).with_special_encoding(SpecialEncoding::NanOnly)
+    }
+
+    pub fn ocp_fp6_e3m2() -> Format {
+        Format::new(3, 2).with_special_encoding(SpecialEncoding::FiniteOnly)
+    }
+
+    pub fn ocp_fp6_e2m3() -> Format {
+        Format::new(2, 3).with_special_encoding(SpecialEncoding::FiniteOnly)
+    }
+
+    pub fn ocp_fp4_e2m1() -> Format {
+        Format::new(2, 1).with_special_encoding(SpecialEncoding::FiniteOnly)
+    }
+
+    pub fn num_exp_bits(&self) -> u32 {
+        self.num_exp_bits
+    }
+
+    pub fn num_sig_bits(&self) -> u32 {
+        self.num_sig_bits
+    }
+
+    pub fn special_encoding(&self) -> SpecialEncoding {
+        self.special_encoding
+    }
+
     pub fn num_storage_bits(&self) -> u32 {
         1 + self.num_exp_bits + self.num_sig_bits
     }
@@ -34,6 +205,152 @@ impl Format {
     pub fn exp_max(&self) -> u32 {
         (1 << self.num_exp_bits) - 1
     }
+
+    pub fn exp_bias(&self) -> i32 {
+        self.exp_bias
+    }
+
+    // Unbiased exponent of the largest normal value
+    pub fn max_normal_exp(&self) -> i32 {
+        self.max_finite_comps().0 as i32 - self.exp_bias
+    }
+
+    // Unbiased exponent of the smallest normal value
+    pub fn min_normal_exp(&self) -> i32 {
+        1 - self.exp_bias
+    }
+
+    // Unbiased exponent of the smallest subnormal value
+    pub fn min_subnormal_exp(&self) -> i32 {
+        self.min_normal_exp() - self.num_sig_bits as i32
+    }
+
+    // Significand bits, including the hidden bit
+    pub fn precision(&self) -> u32 {
+        self.num_sig_bits + 1
+    }
+
+    // Significant decimal digits (log10 of 2^precision)
+    pub fn decimal_digits(&self) -> f64 {
+        self.precision() as f64 * 2f64.log10()
+    }
+
+    // Binades spanned by the finite nonzero values (log2 of largest finite over smallest subnormal)
+    pub fn dynamic_range(&self) -> f64 {
+        let (max_exp, max_sig) = self.max_finite_comps();
+        let max_sig_including_hidden_bit = if max_exp == 0 { max_sig } else { (1 << self.num_sig_bits) | max_sig };
+        let max_exp = max_exp.max(1) as i32 - self.exp_bias - self.num_sig_bits as i32;
+        (max_exp - self.min_subnormal_exp()) as f64 + (max_sig_including_hidden_bit as f64).log2()
+    }
+
+    pub(crate) fn sig_quiet_bit(&self) -> u32 {
+        1 << (self.num_sig_bits - 1)
+    }
+
+    pub(crate) fn sig_mask(&self) -> u32 {
+        (1 << self.num_sig_bits) - 1
+    }
+
+    pub(crate) fn storage_mask(&self) -> u32 {
+        ((1u64 << self.num_storage_bits()) - 1) as u32
+    }
+
+    // Biased exponent and sig of the largest finite magnitude
+    pub(crate) fn max_finite_comps(&self) -> (u32, u32) {
+        match self.special_encoding {
+            SpecialEncoding::Ieee => (self.exp_max() - 1, self.sig_mask()),
+            SpecialEncoding::NanOnly => (self.exp_max(), self.sig_mask() - 1),
+            SpecialEncoding::FiniteOnly => (self.exp_max(), self.sig_mask()),
+        }
+    }
+}
+
+// Format specifications: "e<exp bits>m<sig bits>", optionally suffixed with "fn" (no infinities, as in E4M3FN),
+// followed by any of "+noinf" (same as "fn"), "+finite" (no infinities or NaNs) and "+bias<bias>"; or a well-known
+// name ("fp32", "fp16", "bf16", "tf32"). Case-insensitive.
+impl FromStr for Format {
+    type Err = FormatError;
+
+    fn from_str(s: &str) -> Result<Format, FormatError> {
+        let invalid = || FormatError::InvalidSpec(s.to_string());
+
+        let lower = s.to_ascii_lowercase();
+        let mut parts = lower.split('+');
+        let base = parts.next().unwrap();
+
+        let (base, is_fn) = match base.strip_suffix("fn") {
+            Some(base) => (base, true),
+            None => (base, false),
+        };
+        let mut format = match base {
+            "fp32" | "f32" | "single" | "binary32" => Format::ieee754_single(),
+            "fp16" | "f16" | "half" | "binary16" => Format::new(5, 10),
+            "bf16" | "bfloat16" => Format::new(8, 7),
+            "tf32" => Format::new(8, 10),
+            _ => {
+                if base.starts_with('u') {
+                    return Err(FormatError::UnsignedUnsupported);
+                }
+
+                let widths = base.strip_prefix('e').ok_or_else(invalid)?;
+                let (num_exp_bits, num_sig_bits) = widths.split_at(widths.find('m').ok_or_else(invalid)?);
+                let parse_width = |s: &str| {
+                    if s.is_empty() || !s.bytes().all(|c| c.is_ascii_digit()) {
+                        return Err(invalid());
+                    }
+                    s.parse::<u32>().map_err(|_| invalid())
+                };
+                Format::try_from_widths(parse_width(num_exp_bits)?, parse_width(&num_sig_bits[1..])?)?
+            }
+        };
+        if is_fn {
+            format.special_encoding = SpecialEncoding::NanOnly;
+        }
+
+        // The special encoding is checked once all modifiers are applied, so eg. "e8m0+finite" is valid
+        for modifier in parts {
+            format = if modifier == "noinf" {
+                Format {
+                    special_encoding: SpecialEncoding::NanOnly,
+                    ..format
+                }
+            } else if modifier == "finite" {
+                Format {
+                    special_encoding: SpecialEncoding::FiniteOnly,
+                    ..format
+                }
+            } else if let Some(exp_bias) = modifier.strip_prefix("bias") {
+                let is_valid = !exp_bias.is_empty() && exp_bias.trim_start_matches('-').bytes().all(|c| c.is_ascii_digit());
+                if !is_valid {
+                    return Err(invalid());
+                }
+                let exp_bias = exp_bias.parse::<i64>().map_err(|_| invalid())?;
+                format.check_exp_bias(exp_bias)?;
+                format.try_with_exp_bias(exp_bias as i32)?
+            } else {
+                return Err(invalid());
+            };
+        }
+        format.check_special_encoding()?;
+
+        Ok(format)
+    }
+}
+
+// Prints the canonical specification, which parses back to an identical format
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "e{}m{}", self.num_exp_bits, self.num_sig_bits)?;
+        match self.special_encoding {
+            SpecialEncoding::Ieee => {}
+            SpecialEncoding::NanOnly => write!(f, "fn")?,
+            SpecialEncoding::FiniteOnly => write!(f, "+finite")?,
+        }
+        if self.exp_bias != Format::default_exp_bias(self.num_exp_bits) {
+            write!(f, "+bias{}", self.exp_bias)?;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -100,6 +417,127 @@ mod tests {
         assert_eq!(format.num_sig_bits, 23);
     }
 
+    #[test]
+    fn ocp_formats() {
+        let e5m2 = Format::ocp_fp8_e5m2();
+        assert_eq!(e5m2.num_storage_bits(), 8);
+        assert_eq!(e5m2.special_encoding(), SpecialEncoding::Ieee);
+
+        let e4m3 = Format::ocp_fp8_e4m3();
+        assert_eq!(e4m3.num_storage_bits(), 8);
+        assert_eq!(e4m3.special_encoding(), SpecialEncoding::NanOnly);
+
+        let e3m2 = Format::ocp_fp6_e3m2();
+        assert_eq!(e3m2.num_storage_bits(), 6);
+        assert_eq!(e3m2.special_encoding(), SpecialEncoding::FiniteOnly);
+
+        let e2m3 = Format::ocp_fp6_e2m3();
+        assert_eq!(e2m3.num_storage_bits(), 6);
+        assert_eq!(e2m3.special_encoding(), SpecialEncoding::FiniteOnly);
+
+        let e2m1 = Format::ocp_fp4_e2m1();
+        assert_eq!(e2m1.num_storage_bits(), 4);
+        assert_eq!(e2m1.special_encoding(), SpecialEncoding::FiniteOnly);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested format must have at least 1 significand bit to encode NaN without infinity.")]
+    fn with_special_encoding_nan_only_without_sig_bits() {
+        // Panic
+        let _ = "e8m0+finite".parse::<Format>().unwrap().with_special_encoding(SpecialEncoding::NanOnly);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested format must have at least 1 significand bit to encode NaN alongside infinity.")]
+    fn new_ieee_without_sig_bits() {
+        // Panic
+        let _ = Format::new(8, 0);
+    }
+
+    #[test]
+    fn max_finite_comps() {
+        assert_eq!(Format::ieee754_single().max_finite_comps(), (254, 0x7fffff));
+        assert_eq!(Format::ocp_fp8_e5m2().max_finite_comps(), (30, 3));
+        assert_eq!(Format::ocp_fp8_e4m3().max_finite_comps(), (15, 6));
+        assert_eq!(Format::ocp_fp6_e3m2().max_finite_comps(), (7, 3));
+        assert_eq!(Format::ocp_fp4_e2m1().max_finite_comps(), (3, 1));
+    }
+
+    #[test]
+    fn precision_and_range() {
+        let f = Format::ieee754_single();
+        assert_eq!(f.precision(), 24);
+        assert_eq!(f.min_subnormal_exp(), -149);
+        assert!((f.decimal_digits() - 7.2247).abs() < 1e-4);
+        assert!((f.dynamic_range() - (128.0 + 149.0 + (1.0 - 2f64.powi(-24)).log2())).abs() < 1e-9);
+
+        let f = Format::ocp_fp8_e4m3();
+        assert_eq!(f.precision(), 4);
+        assert_eq!(f.min_subnormal_exp(), -9);
+        assert!((f.dynamic_range() - (448f64 / 2f64.powi(-9)).log2()).abs() < 1e-9);
+
+        let f = Format::ocp_fp4_e2m1();
+        assert_eq!(f.precision(), 2);
+        assert_eq!(f.min_subnormal_exp(), -1);
+        assert!((f.dynamic_range() - 12f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn exp_bias() {
+        assert_eq!(Format::ieee754_single().exp_bias(), 127);
+        assert_eq!(Format::new(5, 10).exp_bias(), 15);
+        assert_eq!(Format::ocp_fp8_e4m3().exp_bias(), 7);
+        assert_eq!(Format::ocp_fp4_e2m1().exp_bias(), 1);
+
+        let f = Format::new(4, 3).with_exp_bias(8);
+        assert_eq!(f.exp_bias(), 8);
+        assert_eq!(f.num_exp_bits, 4);
+        assert_eq!(f.num_sig_bits, 3);
+
+        let f = Format::new(3, 4).with_exp_bias(-2);
+        assert_eq!(f.exp_bias(), -2);
+
+        // Within the exponent range either way
+        assert!(Format::new(3, 4).try_with_exp_bias(8).is_ok());
+        assert!(Format::new(3, 4).try_with_exp_bias(-8).is_ok());
+        assert_eq!(
+            Format::new(3, 4).try_with_exp_bias(9),
+            Err(FormatError::ExpBiasOutOfRange { exp_bias: 9, min_exp_bias: -8, max_exp_bias: 8 })
+        );
+
+        // Wide exponents are bounded by keeping exps well inside i32
+        let f = "e31m0+finite".parse::<Format>().unwrap();
+        assert!(f.clone().try_with_exp_bias(f.exp_bias()).is_ok());
+        assert!(f.clone().try_with_exp_bias(0).is_err());
+        assert_eq!(f.min_normal_exp(), 2 - (1 << 30));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested format has exponent bias 2147483647, but it must be between -32 and 32.")]
+    fn with_exp_bias_out_of_range() {
+        // Panic
+        let _ = Format::new(5, 10).with_exp_bias(i32::MAX);
+    }
+
+    #[test]
+    fn exp_range() {
+        let single = Format::ieee754_single();
+        assert_eq!(single.max_normal_exp(), 127);
+        assert_eq!(single.min_normal_exp(), -126);
+
+        let e4m3 = Format::ocp_fp8_e4m3();
+        assert_eq!(e4m3.max_normal_exp(), 8);
+        assert_eq!(e4m3.min_normal_exp(), -6);
+
+        let e4m3_bias_11 = Format::ocp_fp8_e4m3().with_exp_bias(11);
+        assert_eq!(e4m3_bias_11.max_normal_exp(), 4);
+        assert_eq!(e4m3_bias_11.min_normal_exp(), -10);
+
+        let unbalanced = Format::new(8, 23).with_exp_bias(-10);
+        assert_eq!(unbalanced.max_normal_exp(), 264);
+        assert_eq!(unbalanced.min_normal_exp(), 11);
+    }
+
     #[test]
     fn num_storage_bits() {
         let single = Format::ieee754_single();
@@ -129,4 +567,93 @@ mod tests {
         let fp24 = Format::new(7, 16);
         assert_eq!(fp24.exp_max(), 127);
     }
+
+    #[test]
+    fn from_str() {
+        for &(spec, num_exp_bits, num_sig_bits, special_encoding, exp_bias) in &[
+            ("e8m23", 8, 23, SpecialEncoding::Ieee, 127),
+            ("fp32", 8, 23, SpecialEncoding::Ieee, 127),
+            ("fp16", 5, 10, SpecialEncoding::Ieee, 15),
+            ("bf16", 8, 7, SpecialEncoding::Ieee, 127),
+            ("tf32", 8, 10, SpecialEncoding::Ieee, 127),
+            ("E4M3FN", 4, 3, SpecialEncoding::NanOnly, 7),
+            ("e4m3+noinf", 4, 3, SpecialEncoding::NanOnly, 7),
+            ("e2m1+finite", 2, 1, SpecialEncoding::FiniteOnly, 1),
+            ("e5m10+bias15", 5, 10, SpecialEncoding::Ieee, 15),
+            ("e4m3fn+bias8", 4, 3, SpecialEncoding::NanOnly, 8),
+            ("e5m2+bias-3+finite", 5, 2, SpecialEncoding::FiniteOnly, -3),
+            ("e8m0+finite", 8, 0, SpecialEncoding::FiniteOnly, 127),
+            ("e8m0fn+finite", 8, 0, SpecialEncoding::FiniteOnly, 127),
+        ] {
+            let format = spec.parse::<Format>().unwrap();
+
+            assert_eq!(format.num_exp_bits, num_exp_bits, "{}", spec);
+            assert_eq!(format.num_sig_bits, num_sig_bits, "{}", spec);
+            assert_eq!(format.special_encoding, special_encoding, "{}", spec);
+            assert_eq!(format.exp_bias, exp_bias, "{}", spec);
+        }
+
+        assert_eq!("e4m3fn".parse::<Format>(), Ok(Format::ocp_fp8_e4m3()));
+        assert_eq!("e2m3+finite".parse::<Format>(), Ok(Format::ocp_fp6_e2m3()));
+    }
+
+    #[test]
+    fn from_str_errors() {
+        assert_eq!("e1m10".parse::<Format>(), Err(FormatError::NotEnoughExpBits));
+        assert_eq!("e8m24".parse::<Format>(), Err(FormatError::TooManyStorageBits { num_storage_bits: 33 }));
+        assert_eq!("e8m0fn".parse::<Format>(), Err(FormatError::NanOnlyWithoutSigBits));
+        assert_eq!("e8m0".parse::<Format>(), Err(FormatError::IeeeWithoutSigBits));
+        assert_eq!("e8m0+finite+noinf".parse::<Format>(), Err(FormatError::NanOnlyWithoutSigBits));
+        assert_eq!("ue8m0".parse::<Format>(), Err(FormatError::UnsignedUnsupported));
+        assert_eq!(
+            "e8m23+bias-2147483648".parse::<Format>(),
+            Err(FormatError::ExpBiasOutOfRange { exp_bias: -2147483648, min_exp_bias: -256, max_exp_bias: 256 })
+        );
+        assert_eq!(
+            "e5m10+bias99999999999".parse::<Format>(),
+            Err(FormatError::ExpBiasOutOfRange { exp_bias: 99999999999, min_exp_bias: -32, max_exp_bias: 32 })
+        );
+
+        for &spec in &["", "e8", "m23", "e8m", "em23", "e+8m23", "e8m23+", "e8m23+bias", "e8m23+bias1.5", "e8m23+fast", "fp17", "e99999999999m1"] {
+            assert_eq!(spec.parse::<Format>(), Err(FormatError::InvalidSpec(spec.to_string())), "{}", spec);
+        }
+
+        assert_eq!(
+            "e8m24".parse::<Format>().unwrap_err().to_string(),
+            "Requested format requires 33 storage bits, which exceeds the maximum storage bit width of 32 bits."
+        );
+        assert_eq!(
+            "e8m".parse::<Format>().unwrap_err().to_string(),
+            "Requested format \"e8m\" is not a valid specification; expected eg. \"e5m10\", \"e4m3fn\", \"e2m1+finite\", \"e8m7+bias120\", \"fp16\" or \"bf16\"."
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(Format::ieee754_single().to_string(), "e8m23");
+        assert_eq!(Format::ocp_fp8_e4m3().to_string(), "e4m3fn");
+        assert_eq!(Format::ocp_fp4_e2m1().to_string(), "e2m1+finite");
+        assert_eq!(Format::new(5, 10).with_exp_bias(11).to_string(), "e5m10+bias11");
+        assert_eq!(Format::new(4, 3).with_special_encoding(SpecialEncoding::NanOnly).with_exp_bias(-1).to_string(), "e4m3fn+bias-1");
+
+        // Round trip
+        for num_exp_bits in 2..10 {
+            for num_sig_bits in 0..(32 - num_exp_bits) {
+                for &special_encoding in &[SpecialEncoding::Ieee, SpecialEncoding::NanOnly, SpecialEncoding::FiniteOnly] {
+                    let format = match Format::try_from_widths(num_exp_bits, num_sig_bits).unwrap().try_with_special_encoding(special_encoding) {
+                        Ok(format) => format,
+                        Err(_) => continue,
+                    };
+                    for &exp_bias in &[format.exp_bias, 0, 20, -5] {
+                        let format = match format.clone().try_with_exp_bias(exp_bias) {
+                            Ok(format) => format,
+                            Err(_) => continue,
+                        };
+
+                        assert_eq!(format.to_string().parse::<Format>(), Ok(format));
+                    }
+                }
+            }
+        }
+    }
 }
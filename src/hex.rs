@@ -0,0 +1,288 @@
+use crate::context::*;
+use crate::decimal::*;
+use crate::format::*;
+use crate::rounding::*;
+use crate::value::*;
+
+// How hex strings show subnormal values
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SubnormalNotation {
+    // With a leading 1 and an exponent below the smallest normal's, eg. "0x1p-149"
+    Normalized,
+    // With a leading 0 and the smallest normal's exponent, eg. "0x0.000002p-126"
+    Denormalized,
+}
+
+impl Value {
+    // NaN with its sign, kind and payload in Value::parse's syntax, eg. "-snan(0x2)"; the payload is left out when it's
+    // the default one for the kind
+    pub(crate) fn nan_string(&self) -> String {
+        let sign = if self.sign { "-" } else { "" };
+        let format = &self.format;
+        if format.special_encoding != SpecialEncoding::Ieee {
+            return format!("{}nan", sign);
+        }
+
+        let payload = self.sig & (format.sig_quiet_bit() - 1);
+        let kind = if self.is_signaling_nan() { "snan" } else { "nan" };
+        if payload == 0 || (self.is_signaling_nan() && payload == 1) {
+            format!("{}{}", sign, kind)
+        } else {
+            format!("{}{}({:#x})", sign, kind, payload)
+        }
+    }
+
+    // C99 hex float notation (as printed by %a), eg. "0x1.8p+1"; NaNs print their payload in Value::parse's syntax
+    pub fn to_hex_string(&self, subnormal_notation: SubnormalNotation) -> String {
+        let sign = if self.sign { "-" } else { "" };
+        let format = &self.format;
+
+        if self.is_nan() {
+            return self.nan_string();
+        }
+        if self.is_inf() {
+            return format!("{}inf", sign);
+        }
+        if self.is_zero() {
+            return format!("{}0x0p+0", sign);
+        }
+
+        let num_sig_bits = format.num_sig_bits;
+        let (leading_digit, fraction, exp) = if self.exp != 0 {
+            (1, self.sig, self.exp as i32 - format.exp_bias)
+        } else {
+            match subnormal_notation {
+                SubnormalNotation::Normalized => {
+                    // Shift the leading one out into the hidden bit
+                    let shift = self.sig.leading_zeros() - (32 - num_sig_bits) + 1;
+                    let fraction = (self.sig << shift) & format.sig_mask();
+                    (1, fraction, format.min_normal_exp() - shift as i32)
+                }
+                SubnormalNotation::Denormalized => (0, self.sig, format.min_normal_exp()),
+            }
+        };
+
+        // Left-align the fraction to whole hex digits, and drop trailing zero digits
+        let num_digits = num_sig_bits.div_ceil(4);
+        let fraction = (fraction as u64) << (num_digits * 4 - num_sig_bits);
+        let mut digits = format!("{:0width$x}", fraction, width = num_digits as usize);
+        while digits.ends_with('0') {
+            digits.pop();
+        }
+
+        let point = if digits.is_empty() { "" } else { "." };
+        format!("{}0x{}{}{}p{:+}", sign, leading_digit, point, digits, exp)
+    }
+}
+
+// Parses the hex digits and binary exponent following "0x" (eg. "1.8p+1"), correctly rounded into format
+pub(crate) fn parse_hex(sign: bool, s: &str, format: &Format, context: &mut Context) -> Result<Value, ParseValueError> {
+    let (mantissa, exp) = match s.find(['p', 'P']) {
+        Some(i) => (&s[..i], &s[i + 1..]),
+        None => (s, "0"),
+    };
+
+    let (integer, fraction) = match mantissa.find('.') {
+        Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+        None => (mantissa, ""),
+    };
+    let is_hex_digits = |s: &str| s.bytes().all(|c| c.is_ascii_hexdigit());
+    if (integer.is_empty() && fraction.is_empty()) || !is_hex_digits(integer) || !is_hex_digits(fraction) {
+        return Err(ParseValueError::InvalidSyntax);
+    }
+
+    let (exp_sign, exp_digits) = match exp.as_bytes().first() {
+        Some(b'-') => (-1, &exp[1..]),
+        Some(b'+') => (1, &exp[1..]),
+        _ => (1, exp),
+    };
+    if exp_digits.is_empty() || !exp_digits.bytes().all(|c| c.is_ascii_digit()) {
+        return Err(ParseValueError::InvalidSyntax);
+    }
+    let mut exp = exp_sign * exp_digits
+        .bytes()
+        .fold(0i64, |exp, c| (exp * 10 + (c - b'0') as i64).min(1 << 40));
+
+    // Accumulate digits until the sig is (more than) wide enough, then fold the rest into a sticky bit
+    let mut sig = 0u128;
+    let mut sticky = false;
+    for (i, c) in integer.bytes().chain(fraction.bytes()).enumerate() {
+        let digit = (c as char).to_digit(16).unwrap() as u128;
+        let is_fraction = i >= integer.len();
+        if sig >> 116 == 0 {
+            sig = (sig << 4) | digit;
+            if is_fraction {
+                exp -= 4;
+            }
+        } else {
+            sticky |= digit != 0;
+            if !is_fraction {
+                exp += 4;
+            }
+        }
+    }
+
+    if sig == 0 {
+        return Ok(Value::zero(sign, format.clone()));
+    }
+
+    // As for decimal literals, values far outside of format's range are replaced by ones that round the same way
+    let msb_exp = exp + (127 - sig.leading_zeros()) as i64;
+    if msb_exp > (format.max_normal_exp() + 2) as i64 {
+        return Ok(round(sign, 1, format.max_normal_exp() + 2, format, context));
+    }
+    if msb_exp < (format.min_subnormal_exp() - MAX_NUM_RANDOM_BITS as i32 - 2) as i64 {
+        return Ok(round(sign, 1, format.min_subnormal_exp() - 64, format, context));
+    }
+
+    let sticky = if sticky { 1 } else { 0 };
+    Ok(round(sign, (sig << 1) | sticky, exp as i32 - 1, format, context))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::flags::*;
+
+    fn nearest_even() -> Context {
+        let mut context = Context::new().with_rounding_mode(RoundingMode::NearestEven);
+        context.flush_to_zero = false;
+        context
+    }
+
+    #[test]
+    fn to_hex_string() {
+        let f = Format::ieee754_single();
+
+        for &(bits, normalized, denormalized) in &[
+            (0x3f800000, "0x1p+0", "0x1p+0"),
+            (0xc0400000, "-0x1.8p+1", "-0x1.8p+1"),
+            (0x3dcccccd, "0x1.99999ap-4", "0x1.99999ap-4"),
+            (0x7f7fffff, "0x1.fffffep+127", "0x1.fffffep+127"),
+            (0x00800000, "0x1p-126", "0x1p-126"),
+            (0x00000001, "0x1p-149", "0x0.000002p-126"),
+            (0x807fffff, "-0x1.fffffcp-127", "-0x0.fffffep-126"),
+            (0x00000000, "0x0p+0", "0x0p+0"),
+            (0x80000000, "-0x0p+0", "-0x0p+0"),
+            (0xff800000, "-inf", "-inf"),
+            (0x7fc00000, "nan", "nan"),
+            (0xffc0002a, "-nan(0x2a)", "-nan(0x2a)"),
+            (0x7f800001, "snan", "snan"),
+            (0x7f800002, "snan(0x2)", "snan(0x2)"),
+        ] {
+            let x = Value::from_bits(bits, f.clone());
+
+            assert_eq!(x.to_hex_string(SubnormalNotation::Normalized), normalized);
+            assert_eq!(x.to_hex_string(SubnormalNotation::Denormalized), denormalized);
+        }
+
+        let half = Format::new(5, 10);
+
+        assert_eq!(Value::from_bits(0x3c01, half.clone()).to_hex_string(SubnormalNotation::Normalized), "0x1.004p+0");
+        assert_eq!(Value::from_bits(0x0001, half.clone()).to_hex_string(SubnormalNotation::Denormalized), "0x0.004p-14");
+
+        let e4m3 = Format::ocp_fp8_e4m3();
+
+        assert_eq!(Value::from_bits(0x7e, e4m3.clone()).to_hex_string(SubnormalNotation::Normalized), "0x1.cp+8");
+        assert_eq!(Value::from_bits(0xff, e4m3.clone()).to_hex_string(SubnormalNotation::Normalized), "-nan");
+
+        let e2m1 = Format::ocp_fp4_e2m1();
+
+        assert_eq!(Value::from_bits(0x1, e2m1.clone()).to_hex_string(SubnormalNotation::Normalized), "0x1p-1");
+        assert_eq!(Value::from_bits(0x1, e2m1.clone()).to_hex_string(SubnormalNotation::Denormalized), "0x0.8p+0");
+
+        // Formats without sig bits have no fraction
+        let e8m0 = "e8m0+finite".parse::<Format>().unwrap();
+
+        assert_eq!(Value::from_bits(0x80, e8m0.clone()).to_hex_string(SubnormalNotation::Normalized), "0x1p+1");
+    }
+
+    #[test]
+    fn parse_hex() {
+        let f = Format::ieee754_single();
+
+        for &(s, expected) in &[
+            ("0x1p+0", 0x3f800000),
+            ("-0x1.8p1", 0xc0400000),
+            ("0X1.99999AP-4", 0x3dcccccd),
+            ("0x0.000002p-126", 0x00000001),
+            ("0x1p-149", 0x00000001),
+            ("0xA", 0x41200000),
+            ("0x.8", 0x3f000000),
+            ("0x1.", 0x3f800000),
+            ("-0x0p+0", 0x80000000),
+            ("0x00000000000000000000000000000000001p0", 0x3f800000),
+            ("0x100000000000000000000000000000000000p-140", 0x3f800000),
+        ] {
+            let mut context = nearest_even();
+
+            assert_eq!(Value::parse(s, f.clone(), &mut context).unwrap().to_bits(), expected, "{}", s);
+            assert!(context.flags().is_empty(), "{}", s);
+        }
+
+        // Excess digits are rounded
+        let mut context = nearest_even();
+
+        assert_eq!(Value::parse("0x1.000001p+0", f.clone(), &mut context).unwrap().to_bits(), 0x3f800000); // tie to even
+        assert_eq!(context.flags(), Flags::INEXACT);
+        assert_eq!(Value::parse("0x1.000003p+0", f.clone(), &mut context).unwrap().to_bits(), 0x3f800002); // tie to even
+        assert_eq!(Value::parse("0x1.00000100000000000000000000000000001p+0", f.clone(), &mut context).unwrap().to_bits(), 0x3f800001);
+        assert_eq!(Value::parse("0x1.000001p+0", f.clone(), &mut Context::new()).unwrap().to_bits(), 0x3f800000);
+
+        let mut context = nearest_even();
+
+        assert_eq!(Value::parse("0x1p+128", f.clone(), &mut context).unwrap().to_bits(), 0x7f800000);
+        assert_eq!(context.flags(), Flags::OVERFLOW | Flags::INEXACT);
+
+        let mut context = nearest_even();
+
+        assert_eq!(Value::parse("-0x1p-99999999999999", f.clone(), &mut context).unwrap().to_bits(), 0x80000000);
+        assert_eq!(context.flags(), Flags::UNDERFLOW | Flags::INEXACT);
+
+        let mut context = nearest_even();
+
+        assert_eq!(Value::parse("0x1.cp+8", Format::ocp_fp8_e4m3(), &mut context).unwrap().to_bits(), 0x7e);
+        assert_eq!(Value::parse("0x1.8p+0", Format::ocp_fp4_e2m1(), &mut context).unwrap().to_bits(), 0x3);
+
+        for &s in &["0x", "0xp1", "0x1p", "0x1g", "0x1.2.3", "0x1p1.5", "0x-1"] {
+            assert_eq!(Value::parse(s, f.clone(), &mut context).err(), Some(ParseValueError::InvalidSyntax), "{}", s);
+        }
+    }
+
+    #[test]
+    fn parse_hex_stochastic() {
+        let f = Format::ieee754_single();
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 32 })
+            .with_random_source(|_| 0xffffffff);
+        context.flush_to_zero = false;
+
+        // Values within the top 32 bits below the smallest subnormal can round up, but not those further below
+        assert_eq!(Value::parse("0x1p-156", f.clone(), &mut context).unwrap().to_bits(), 0x00000001); // 2^-7 ulp
+        assert_eq!(Value::parse("0x1p-181", f.clone(), &mut context).unwrap().to_bits(), 0x00000001); // 2^-32 ulp
+        assert_eq!(Value::parse("0x1.fffp-183", f.clone(), &mut context).unwrap().to_bits(), 0x00000000);
+        assert_eq!(Value::parse("0x1p-99999999999999", f.clone(), &mut context).unwrap().to_bits(), 0x00000000);
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        for f in &[Format::ieee754_single(), Format::new(5, 10), Format::ocp_fp8_e4m3(), "e3m0+finite".parse().unwrap()] {
+            let num_encodings = 1u64 << f.num_storage_bits();
+            let stride = (num_encodings / 5000).max(1) | 1;
+            let mut bits = 0;
+            while bits < num_encodings {
+                let x = Value::from_bits(bits as u32, f.clone());
+                if !x.is_inf() || f.special_encoding() == SpecialEncoding::Ieee {
+                    for &notation in &[SubnormalNotation::Normalized, SubnormalNotation::Denormalized] {
+                        let s = x.to_hex_string(notation);
+
+                        assert_eq!(Value::parse(&s, f.clone(), &mut nearest_even()).unwrap().to_bits(), bits as u32, "{}", s);
+                    }
+                }
+
+                bits += stride;
+            }
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use crate::format::*;
+use crate::value::*;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValueFilter {
+    All,
+    // Zeros, subnormals and normals
+    Finite,
+    Normal,
+    Subnormal,
+    Nan,
+}
+
+impl ValueFilter {
+    fn matches(self, value: &Value) -> bool {
+        match self {
+            ValueFilter::All => true,
+            ValueFilter::Finite => value.is_finite(),
+            ValueFilter::Normal => value.is_normal(),
+            ValueFilter::Subnormal => value.is_subnormal(),
+            ValueFilter::Nan => value.is_nan(),
+        }
+    }
+}
+
+// Every encoding of a format as a Value, in increasing bit order
+pub struct Values {
+    format: Format,
+    filter: ValueFilter,
+    next_bits: u64,
+}
+
+impl Values {
+    pub fn with_filter(self, filter: ValueFilter) -> Values {
+        Values {
+            filter,
+            ..self
+        }
+    }
+}
+
+impl Iterator for Values {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        let num_encodings = 1 << self.format.num_storage_bits();
+        while self.next_bits < num_encodings {
+            let value = Value::from_bits(self.next_bits as u32, self.format.clone());
+            self.next_bits += 1;
+            if self.filter.matches(&value) {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+// Every (lhs, rhs) pair of encodings of a format, in increasing (lhs bits, rhs bits) order, optionally restricted to
+// one of several contiguous, deterministic shards
+pub struct ValuePairs {
+    format: Format,
+    filter: ValueFilter,
+    next_index: u64,
+    end_index: u64,
+}
+
+impl ValuePairs {
+    // Applies to both values of each pair
+    pub fn with_filter(self, filter: ValueFilter) -> ValuePairs {
+        ValuePairs {
+            filter,
+            ..self
+        }
+    }
+
+    pub fn with_shard(self, shard_index: u64, num_shards: u64) -> ValuePairs {
+        if shard_index >= num_shards {
+            panic!("Requested shard {} of {} shards, but the shard index must be less than the number of shards.", shard_index, num_shards);
+        }
+
+        let num_pairs = self.end_index as u128;
+        let start = num_pairs * shard_index as u128 / num_shards as u128;
+        let end = num_pairs * (shard_index + 1) as u128 / num_shards as u128;
+        ValuePairs {
+            next_index: start as u64,
+            end_index: end as u64,
+            ..self
+        }
+    }
+}
+
+impl Iterator for ValuePairs {
+    type Item = (Value, Value);
+
+    fn next(&mut self) -> Option<(Value, Value)> {
+        let num_storage_bits = self.format.num_storage_bits();
+        while self.next_index < self.end_index {
+            let lhs_bits = (self.next_index >> num_storage_bits) as u32;
+            let rhs_bits = (self.next_index & ((1 << num_storage_bits) - 1)) as u32;
+            self.next_index += 1;
+
+            let lhs = Value::from_bits(lhs_bits, self.format.clone());
+            if !self.filter.matches(&lhs) {
+                // Skip straight to the next lhs
+                self.next_index = self.end_index.min(((lhs_bits as u64) + 1) << num_storage_bits);
+                continue;
+            }
+            let rhs = Value::from_bits(rhs_bits, self.format.clone());
+            if self.filter.matches(&rhs) {
+                return Some((lhs, rhs));
+            }
+        }
+        None
+    }
+}
+
+impl Format {
+    pub fn values(&self) -> Values {
+        Values {
+            format: self.clone(),
+            filter: ValueFilter::All,
+            next_bits: 0,
+        }
+    }
+
+    pub fn value_pairs(&self) -> ValuePairs {
+        let num_storage_bits = self.num_storage_bits();
+        let max_storage_bits = 31;
+        if num_storage_bits > max_storage_bits {
+            panic!("Requested value pairs of a format with {} storage bits, which exceeds the maximum of {} bits.", num_storage_bits, max_storage_bits);
+        }
+
+        ValuePairs {
+            format: self.clone(),
+            filter: ValueFilter::All,
+            next_index: 0,
+            end_index: 1 << (2 * num_storage_bits),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values() {
+        let f = Format::ocp_fp8_e5m2();
+
+        assert_eq!(f.values().count(), 256);
+        assert_eq!(f.values().map(|x| x.to_bits()).collect::<Vec<_>>(), (0..256).collect::<Vec<_>>());
+        assert_eq!(f.values().with_filter(ValueFilter::Finite).count(), 256 - 8);
+        assert_eq!(f.values().with_filter(ValueFilter::Normal).count(), 256 - 8 - 8);
+        assert_eq!(f.values().with_filter(ValueFilter::Subnormal).count(), 6);
+        assert_eq!(f.values().with_filter(ValueFilter::Nan).count(), 6);
+
+        let f = Format::ocp_fp8_e4m3();
+
+        assert_eq!(f.values().with_filter(ValueFilter::Finite).count(), 254);
+        assert_eq!(f.values().with_filter(ValueFilter::Nan).map(|x| x.to_bits()).collect::<Vec<_>>(), vec![0x7f, 0xff]);
+
+        let f = Format::ocp_fp4_e2m1();
+
+        assert_eq!(f.values().with_filter(ValueFilter::Finite).count(), 16);
+        assert_eq!(f.values().with_filter(ValueFilter::Subnormal).map(|x| x.to_bits()).collect::<Vec<_>>(), vec![0x1, 0x9]);
+
+        assert_eq!(Format::new(5, 10).values().count(), 65536);
+    }
+
+    #[test]
+    fn value_pairs() {
+        let f = Format::ocp_fp4_e2m1();
+
+        let pairs = f.value_pairs().map(|(a, b)| (a.to_bits(), b.to_bits())).collect::<Vec<_>>();
+
+        assert_eq!(pairs.len(), 256);
+        assert_eq!(pairs[0], (0x0, 0x0));
+        assert_eq!(pairs[1], (0x0, 0x1));
+        assert_eq!(pairs[16], (0x1, 0x0));
+        assert_eq!(pairs[255], (0xf, 0xf));
+
+        let f = Format::ocp_fp8_e5m2();
+
+        assert_eq!(f.value_pairs().with_filter(ValueFilter::Finite).count(), 248 * 248);
+        assert_eq!(f.value_pairs().with_filter(ValueFilter::Nan).count(), 6 * 6);
+        assert!(f.value_pairs().with_filter(ValueFilter::Subnormal).all(|(a, b)| a.is_subnormal() && b.is_subnormal()));
+    }
+
+    #[test]
+    fn value_pairs_shards() {
+        let f = Format::ocp_fp6_e3m2();
+
+        let all = f.value_pairs().with_filter(ValueFilter::Normal).map(|(a, b)| (a.to_bits(), b.to_bits())).collect::<Vec<_>>();
+
+        for &num_shards in &[1, 2, 3, 7, 4096, 5000] {
+            let sharded = (0..num_shards)
+                .flat_map(|shard_index| f.value_pairs().with_filter(ValueFilter::Normal).with_shard(shard_index, num_shards))
+                .map(|(a, b)| (a.to_bits(), b.to_bits()))
+                .collect::<Vec<_>>();
+
+            assert_eq!(sharded, all);
+        }
+
+        // Shards are balanced by pair count before filtering
+        let shard = f.value_pairs().with_shard(1, 4).map(|(a, b)| (a.to_bits(), b.to_bits())).collect::<Vec<_>>();
+
+        assert_eq!(shard.len(), 1024);
+        assert_eq!(shard[0], (16, 0));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested shard 3 of 3 shards, but the shard index must be less than the number of shards.")]
+    fn value_pairs_invalid_shard() {
+        // Panic
+        let _ = Format::ocp_fp4_e2m1().value_pairs().with_shard(3, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested value pairs of a format with 32 storage bits, which exceeds the maximum of 31 bits.")]
+    fn value_pairs_too_wide() {
+        // Panic
+        let _ = Format::ieee754_single().value_pairs();
+    }
+}
@@ -1,3 +1,22 @@
+mod bignum;
+pub mod batch;
+pub mod cli;
+pub mod context;
+pub mod coverage;
+pub mod decimal;
+pub mod export;
+pub mod flags;
+pub mod ffi;
 pub mod format;
+pub mod hex;
+pub mod iter;
+pub mod mx;
+pub mod oracle;
 pub mod ops;
+pub mod rounding;
+pub mod server;
+pub mod table;
+pub mod testfloat;
+pub mod trace;
 pub mod value;
+pub mod verify;
@@ -0,0 +1,15 @@
+// Command-line calculator (see softy::cli)
+
+use std::env;
+use std::io;
+use std::process;
+
+use softy::cli::*;
+
+fn main() {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if let Err(message) = run(&args, &mut io::stdout().lock()) {
+        eprintln!("{}", message.trim_end());
+        process::exit(2);
+    }
+}
@@ -0,0 +1,535 @@
+use crate::context::*;
+use crate::format::*;
+use crate::rounding::*;
+use crate::value::*;
+
+pub const BLOCK_SIZE: usize = 32;
+
+// Shared E8M0 block scale: an unsigned power of two 2^(bits - 127), with 0xff encoding NaN
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Scale {
+    bits: u8,
+}
+
+impl Scale {
+    pub fn from_bits(bits: u8) -> Scale {
+        Scale { bits }
+    }
+
+    pub fn from_exp(exp: i32) -> Scale {
+        if !(-127..=127).contains(&exp) {
+            panic!("Requested scale exponent {} is outside of the E8M0 range of [-127, 127].", exp);
+        }
+
+        Scale { bits: (exp + 127) as u8 }
+    }
+
+    pub fn nan() -> Scale {
+        Scale { bits: 0xff }
+    }
+
+    pub fn to_bits(&self) -> u8 {
+        self.bits
+    }
+
+    pub fn is_nan(&self) -> bool {
+        self.bits == 0xff
+    }
+
+    pub fn exp(&self) -> i32 {
+        self.bits as i32 - 127
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ElementType {
+    Float(Format),
+    // Two's complement with an implicit scale of 2^-6
+    Int8,
+}
+
+enum Element {
+    Nan,
+    Inf(bool),
+    Finite(bool, u32, i32),
+}
+
+impl ElementType {
+    pub fn mxfp8_e5m2() -> ElementType {
+        ElementType::Float(Format::ocp_fp8_e5m2())
+    }
+
+    pub fn mxfp8_e4m3() -> ElementType {
+        ElementType::Float(Format::ocp_fp8_e4m3())
+    }
+
+    pub fn mxfp6_e3m2() -> ElementType {
+        ElementType::Float(Format::ocp_fp6_e3m2())
+    }
+
+    pub fn mxfp6_e2m3() -> ElementType {
+        ElementType::Float(Format::ocp_fp6_e2m3())
+    }
+
+    pub fn mxfp4_e2m1() -> ElementType {
+        ElementType::Float(Format::ocp_fp4_e2m1())
+    }
+
+    pub fn mxint8() -> ElementType {
+        ElementType::Int8
+    }
+
+    fn validate(&self) {
+        if let ElementType::Float(format) = self {
+            // Keeps exact block dot products within 128 bits
+            if format.num_storage_bits() > 8 || format.num_exp_bits > 5 {
+                panic!("Requested element format must have at most 8 storage bits and 5 exponent bits.");
+            }
+        }
+    }
+
+    // Unbiased exponent of the largest normal element
+    fn emax(&self) -> i32 {
+        match self {
+            ElementType::Float(format) => format.max_normal_exp(),
+            ElementType::Int8 => 0,
+        }
+    }
+
+    fn decode(&self, bits: u32) -> Element {
+        match self {
+            ElementType::Float(format) => {
+                let value = Value::from_bits(bits, format.clone());
+                if value.is_nan() {
+                    Element::Nan
+                } else if value.is_inf() {
+                    Element::Inf(value.sign)
+                } else {
+                    let (sig, exp) = value.decode_finite();
+                    Element::Finite(value.sign, sig, exp)
+                }
+            }
+            ElementType::Int8 => {
+                let x = bits as u8 as i8;
+                Element::Finite(x < 0, x.unsigned_abs() as u32, -6)
+            }
+        }
+    }
+
+    // Rounds (-1)^sign * sig * 2^exp to nearest even, clamping to the largest finite element
+    fn encode(&self, sign: bool, sig: u128, exp: i32) -> u32 {
+        match self {
+            ElementType::Float(format) => {
+                round(sign, sig, exp, format, &mut nearest_even_context(OverflowMode::Saturate)).to_bits()
+            }
+            ElementType::Int8 => {
+                let shift = exp + 6;
+                let magnitude = if sig == 0 {
+                    0
+                } else if shift > 7 {
+                    127
+                } else if shift >= 0 {
+                    (sig << shift).min(127)
+                } else {
+                    shift_right_rounded(false, sig, (-shift) as u32, RoundingMode::NearestEven, 0).min(127)
+                } as i8;
+                (if sign { -magnitude } else { magnitude }) as u8 as u32
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Block {
+    pub(crate) element_type: ElementType,
+    pub(crate) scale: Scale,
+    pub(crate) elements: Vec<u32>,
+}
+
+impl Block {
+    pub fn from_parts(element_type: ElementType, scale: Scale, elements: Vec<u32>) -> Block {
+        element_type.validate();
+        check_block_size(elements.len());
+
+        Block {
+            element_type,
+            scale,
+            elements,
+        }
+    }
+
+    pub fn quantize(values: &[Value], element_type: ElementType) -> Block {
+        element_type.validate();
+        check_block_size(values.len());
+
+        // Any non-finite input makes the whole block NaN
+        if values.iter().any(|value| value.is_nan() || value.is_inf()) {
+            return Block {
+                element_type,
+                scale: Scale::nan(),
+                elements: vec![0; values.len()],
+            };
+        }
+
+        // Choose the shared exp so the largest magnitude lands in the element format's top binade
+        let max_exp = values
+            .iter()
+            .filter(|value| value.exp != 0 || value.sig != 0)
+            .map(|value| {
+                let (sig, exp) = value.decode_finite();
+                exp + 31 - sig.leading_zeros() as i32
+            })
+            .max();
+        let shared_exp = match max_exp {
+            Some(max_exp) => (max_exp - element_type.emax()).clamp(-127, 127),
+            None => -127,
+        };
+
+        // Quantize scaled elements
+        let elements = values
+            .iter()
+            .map(|value| {
+                let (sig, exp) = value.decode_finite();
+                element_type.encode(value.sign, sig as u128, exp - shared_exp)
+            })
+            .collect();
+
+        Block {
+            element_type,
+            scale: Scale::from_exp(shared_exp),
+            elements,
+        }
+    }
+
+    pub fn quantize_f32(values: &[f32], element_type: ElementType) -> Block {
+        let values = values.iter().map(|&x| Value::from_f32(x)).collect::<Vec<_>>();
+        Block::quantize(&values, element_type)
+    }
+
+    pub fn dequantize(&self, format: &Format) -> Vec<Value> {
+        let mut context = nearest_even_context(OverflowMode::Ieee);
+
+        self.elements
+            .iter()
+            .map(|&bits| {
+                if self.scale.is_nan() {
+                    return Value::quiet_nan(format.clone());
+                }
+
+                match self.element_type.decode(bits) {
+                    Element::Nan => Value::quiet_nan(format.clone()),
+                    Element::Inf(sign) => Value::infinity(sign, format.clone()),
+                    Element::Finite(sign, sig, exp) => round(sign, sig as u128, exp + self.scale.exp(), format, &mut context),
+                }
+            })
+            .collect()
+    }
+
+    pub fn dequantize_f32(&self) -> Vec<f32> {
+        self.dequantize(&Format::ieee754_single()).iter().map(|value| value.to_f32()).collect()
+    }
+
+    // Products are summed exactly, then scaled and rounded to FP32 once
+    pub fn dot(&self, other: &Block) -> f32 {
+        assert_eq!(self.elements.len(), other.elements.len());
+
+        if self.scale.is_nan() || other.scale.is_nan() {
+            return f32::NAN;
+        }
+
+        let mut products = Vec::with_capacity(self.elements.len());
+        let mut inf_sign = None;
+        for (&a, &b) in self.elements.iter().zip(other.elements.iter()) {
+            let product_inf_sign = match (self.element_type.decode(a), other.element_type.decode(b)) {
+                (Element::Nan, _) | (_, Element::Nan) => return f32::NAN,
+                (Element::Inf(sign1), Element::Inf(sign2)) => sign1 ^ sign2,
+                (Element::Inf(sign1), Element::Finite(sign2, sig, _)) | (Element::Finite(sign2, sig, _), Element::Inf(sign1)) => {
+                    // inf * 0
+                    if sig == 0 {
+                        return f32::NAN;
+                    }
+                    sign1 ^ sign2
+                }
+                (Element::Finite(sign1, sig1, exp1), Element::Finite(sign2, sig2, exp2)) => {
+                    products.push((sign1 ^ sign2, sig1 as i128 * sig2 as i128, exp1 + exp2));
+                    continue;
+                }
+            };
+
+            // inf - inf
+            if inf_sign.is_some() && inf_sign != Some(product_inf_sign) {
+                return f32::NAN;
+            }
+            inf_sign = Some(product_inf_sign);
+        }
+
+        match inf_sign {
+            Some(true) => return f32::NEG_INFINITY,
+            Some(false) => return f32::INFINITY,
+            None => (),
+        }
+
+        // Accumulate in fixed point aligned to the smallest product's lsb
+        let min_exp = products.iter().map(|&(_, _, exp)| exp).min().unwrap_or(0);
+        let sum = products
+            .iter()
+            .map(|&(sign, sig, exp)| {
+                let sig = sig << (exp - min_exp);
+                if sign {
+                    -sig
+                } else {
+                    sig
+                }
+            })
+            .sum::<i128>();
+
+        let exp = min_exp + self.scale.exp() + other.scale.exp();
+        round(sum < 0, sum.unsigned_abs(), exp, &Format::ieee754_single(), &mut nearest_even_context(OverflowMode::Ieee)).to_f32()
+    }
+
+    pub fn element_type(&self) -> &ElementType {
+        &self.element_type
+    }
+
+    pub fn scale(&self) -> Scale {
+        self.scale
+    }
+
+    pub fn elements(&self) -> &[u32] {
+        &self.elements
+    }
+}
+
+// The spec rounds to nearest even, with gradual underflow
+fn nearest_even_context(overflow_mode: OverflowMode) -> Context {
+    Context {
+        flush_to_zero: false,
+        ..Context::new().with_rounding_mode(RoundingMode::NearestEven).with_overflow_mode(overflow_mode)
+    }
+}
+
+fn check_block_size(num_elements: usize) {
+    if num_elements > BLOCK_SIZE {
+        panic!("Requested block has {} elements, which exceeds the MX block size of {} elements.", num_elements, BLOCK_SIZE);
+    }
+}
+
+pub fn quantize_blocks(values: &[Value], element_type: &ElementType) -> Vec<Block> {
+    values.chunks(BLOCK_SIZE).map(|chunk| Block::quantize(chunk, element_type.clone())).collect()
+}
+
+pub fn quantize_blocks_f32(values: &[f32], element_type: &ElementType) -> Vec<Block> {
+    values.chunks(BLOCK_SIZE).map(|chunk| Block::quantize_f32(chunk, element_type.clone())).collect()
+}
+
+pub fn dequantize_blocks_f32(blocks: &[Block]) -> Vec<f32> {
+    blocks.iter().flat_map(|block| block.dequantize_f32()).collect()
+}
+
+// Block dot products accumulated in FP32, in order
+pub fn dot_general(a: &[Block], b: &[Block]) -> f32 {
+    assert_eq!(a.len(), b.len());
+
+    a.iter().zip(b.iter()).fold(0.0, |acc, (a, b)| acc + a.dot(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scale() {
+        let x = Scale::from_exp(0);
+
+        assert_eq!(x.to_bits(), 127);
+        assert_eq!(x.exp(), 0);
+        assert!(!x.is_nan());
+
+        let x = Scale::from_exp(-127);
+
+        assert_eq!(x.to_bits(), 0);
+
+        let x = Scale::from_bits(0xff);
+
+        assert!(x.is_nan());
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested scale exponent 128 is outside of the E8M0 range of [-127, 127].")]
+    fn scale_out_of_range() {
+        // Panic
+        let _ = Scale::from_exp(128);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested element format must have at most 8 storage bits and 5 exponent bits.")]
+    fn quantize_unsupported_element_format() {
+        // Panic
+        let _ = Block::quantize_f32(&[1.0], ElementType::Float(Format::ieee754_single()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested block has 33 elements, which exceeds the MX block size of 32 elements.")]
+    fn quantize_too_many_elements() {
+        // Panic
+        let _ = Block::quantize_f32(&[1.0; 33], ElementType::mxint8());
+    }
+
+    #[test]
+    fn quantize_fp4() {
+        let block = Block::quantize_f32(&[1.0, 2.0, -3.0, 6.0], ElementType::mxfp4_e2m1());
+
+        assert_eq!(block.scale().exp(), 0);
+        assert_eq!(block.elements(), &[0x2, 0x4, 0xd, 0x7]);
+
+        let block = Block::quantize_f32(&[2.0, 4.0, -6.0, 12.0], ElementType::mxfp4_e2m1());
+
+        assert_eq!(block.scale().exp(), 1);
+        assert_eq!(block.elements(), &[0x2, 0x4, 0xd, 0x7]);
+
+        // 7.0 rounds to 8.0, which saturates
+        let block = Block::quantize_f32(&[7.0, 0.25, 0.2], ElementType::mxfp4_e2m1());
+
+        assert_eq!(block.scale().exp(), 0);
+        assert_eq!(block.elements(), &[0x7, 0x0, 0x0]);
+    }
+
+    #[test]
+    fn quantize_fp8() {
+        let block = Block::quantize_f32(&[1792.0, 1.0, -0.0], ElementType::mxfp8_e4m3());
+
+        assert_eq!(block.scale().exp(), 2);
+        assert_eq!(block.elements(), &[0x7e, 0x28, 0x80]);
+
+        let block = Block::quantize_f32(&[1.0, 1.0e-30], ElementType::mxfp8_e5m2());
+
+        assert_eq!(block.scale().exp(), -15);
+        assert_eq!(block.elements(), &[0x78, 0x00]);
+    }
+
+    #[test]
+    fn quantize_int8() {
+        let block = Block::quantize_f32(&[1.0, -0.5, 1.99, 1.999], ElementType::mxint8());
+
+        assert_eq!(block.scale().exp(), 0);
+        assert_eq!(block.elements(), &[0x40, 0xe0, 0x7f, 0x7f]);
+    }
+
+    #[test]
+    fn quantize_special() {
+        let block = Block::quantize_f32(&[0.0, -0.0], ElementType::mxfp6_e3m2());
+
+        assert_eq!(block.scale().exp(), -127);
+        assert_eq!(block.elements(), &[0x00, 0x20]);
+
+        let block = Block::quantize_f32(&[1.0, f32::INFINITY], ElementType::mxfp8_e5m2());
+
+        assert!(block.scale().is_nan());
+
+        let block = Block::quantize_f32(&[f32::NAN, 1.0], ElementType::mxint8());
+
+        assert!(block.scale().is_nan());
+        assert!(block.dequantize_f32().iter().all(|x| x.is_nan()));
+    }
+
+    #[test]
+    fn dequantize() {
+        let values = [1.5, -0.375, 3.0, 0.0];
+
+        for element_type in &[
+            ElementType::mxfp8_e5m2(),
+            ElementType::mxfp8_e4m3(),
+            ElementType::mxfp6_e3m2(),
+            ElementType::mxfp6_e2m3(),
+            ElementType::mxfp4_e2m1(),
+            ElementType::mxint8(),
+        ] {
+            let block = Block::quantize_f32(&[4.0, 2.0], element_type.clone());
+
+            assert_eq!(block.dequantize_f32(), vec![4.0, 2.0]);
+
+            if *element_type != ElementType::mxfp4_e2m1() {
+                let block = Block::quantize_f32(&values, element_type.clone());
+
+                assert_eq!(block.dequantize_f32(), values.to_vec());
+            }
+        }
+
+        let block = Block::from_parts(ElementType::mxfp4_e2m1(), Scale::from_exp(-3), vec![0x3, 0xf]);
+
+        let res = block.dequantize(&Format::new(5, 10));
+
+        assert_eq!(res[0].to_bits(), 0x3200); // 0.1875
+        assert_eq!(res[1].to_bits(), 0xba00); // -0.75
+
+        let block = Block::from_parts(ElementType::mxfp8_e5m2(), Scale::from_exp(0), vec![0x7c, 0x7f, 0xfb]);
+
+        let res = block.dequantize_f32();
+
+        assert_eq!(res[0], f32::INFINITY);
+        assert!(res[1].is_nan());
+        assert_eq!(res[2], -57344.0);
+    }
+
+    #[test]
+    fn dot() {
+        let a = Block::quantize_f32(&[1.0, 2.0, -3.0, 6.0], ElementType::mxfp4_e2m1());
+        let b = Block::quantize_f32(&[2.0, 2.0, 2.0, 2.0], ElementType::mxfp4_e2m1());
+
+        assert_eq!(a.dot(&b), 12.0);
+
+        let b = Block::quantize_f32(&[0.5, 0.25, 0.125, 1.0], ElementType::mxint8());
+
+        assert_eq!(a.dot(&b), 6.625);
+
+        // Products are accumulated exactly, so tiny terms survive cancellation
+        let a = Block::quantize_f32(&[57344.0, -57344.0, 1.0e-4], ElementType::mxfp8_e5m2());
+        let b = Block::quantize_f32(&[57344.0, 57344.0, 1.0e-4], ElementType::mxfp8_e5m2());
+
+        let expected = a.dequantize_f32()[2] * b.dequantize_f32()[2];
+        assert_ne!(expected, 0.0);
+        assert_eq!(a.dot(&b), expected);
+    }
+
+    #[test]
+    fn dot_special() {
+        let a = Block::from_parts(ElementType::mxfp8_e5m2(), Scale::from_exp(0), vec![0x7c, 0x3c]); // +inf, 1.0
+        let b = Block::from_parts(ElementType::mxfp8_e5m2(), Scale::from_exp(0), vec![0x3c, 0x3c]); // 1.0, 1.0
+
+        assert_eq!(a.dot(&b), f32::INFINITY);
+
+        let b = Block::from_parts(ElementType::mxfp8_e5m2(), Scale::from_exp(0), vec![0x00, 0x3c]); // 0.0, 1.0
+
+        assert!(a.dot(&b).is_nan());
+
+        let b = Block::from_parts(ElementType::mxfp8_e5m2(), Scale::nan(), vec![0x3c, 0x3c]);
+
+        assert!(a.dot(&b).is_nan());
+
+        // Scales are applied before rounding
+        let a = Block::from_parts(ElementType::mxfp8_e4m3(), Scale::from_exp(127), vec![0x7e]); // 448
+        let b = Block::from_parts(ElementType::mxfp8_e4m3(), Scale::from_exp(127), vec![0x7e]); // 448
+
+        assert_eq!(a.dot(&b), f32::INFINITY);
+
+        let a = Block::from_parts(ElementType::mxfp8_e4m3(), Scale::from_exp(-127), vec![0x38]); // 1.0
+        let b = Block::from_parts(ElementType::mxfp8_e4m3(), Scale::from_exp(-20), vec![0x38]); // 1.0
+
+        assert_eq!(a.dot(&b), f32::from_bits(1 << 2));
+    }
+
+    #[test]
+    fn blocks() {
+        let values = (0..80).map(|i| i as f32 * 0.25 - 10.0).collect::<Vec<_>>();
+
+        let blocks = quantize_blocks_f32(&values, &ElementType::mxint8());
+
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[2].elements().len(), 16);
+        assert_eq!(dequantize_blocks_f32(&blocks), values);
+
+        let ones = quantize_blocks_f32(&[1.0; 80], &ElementType::mxfp8_e4m3());
+
+        assert_eq!(dot_general(&blocks, &ones), values.iter().sum::<f32>());
+    }
+}
@@ -1,2 +1,203 @@
 pub mod addition;
+pub mod approximation;
+pub mod conversion;
+pub mod fused_multiply_add;
 pub mod multiplication;
+
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::context::*;
+use crate::flags::*;
+use crate::format::*;
+use crate::rounding::*;
+use crate::value::*;
+
+// Output of a pipelined op's first stage: either finite operands for the following stages, or a special result (eg. a
+// propagated NaN or infinity) that bypasses them
+#[derive(Debug)]
+pub enum Unpacked<T> {
+    Finite(T),
+    Special(Value),
+}
+
+pub(crate) fn flush_denormal_to_zero(value: Value) -> Value {
+    if value.exp == 0 {
+        Value::from_comps(value.sign, value.exp, 0, value.format)
+    } else {
+        value
+    }
+}
+
+// Treats a denormal input as zero, if the context asks for it
+pub(crate) fn denormal_input(value: Value, context: &Context) -> Value {
+    if context.denormals_are_zero {
+        flush_denormal_to_zero(value)
+    } else {
+        value
+    }
+}
+
+// Sign of an exactly zero sum: zeros of matching signs keep their sign, anything else cancels to +0 (-0 when rounding
+// toward negative). Without IEEE rounding, exact zero sums are always +0.
+pub(crate) fn exact_zero_sum_sign(lhs_sign: bool, rhs_sign: bool, context: &Context) -> bool {
+    if !context.ieee_rounding {
+        false
+    } else if lhs_sign == rhs_sign {
+        lhs_sign
+    } else {
+        context.rounding_mode == RoundingMode::TowardNegative
+    }
+}
+
+// Chooses the result of an op with NaN source(s) (given in operand order), raising invalid for signaling NaNs
+pub(crate) fn propagate_nan(sources: &[&Value], format: &Format, context: &mut Context) -> Value {
+    if sources.iter().any(|source| source.is_signaling_nan()) {
+        context.raise(Flags::INVALID);
+    }
+
+    let nan = match context.nan_mode {
+        NanMode::Canonical => None,
+        NanMode::PropagateFirst | NanMode::X86 => sources.iter().find(|source| source.is_nan()),
+        NanMode::Arm => sources
+            .iter()
+            .find(|source| source.is_signaling_nan())
+            .or_else(|| sources.iter().find(|source| source.is_nan())),
+    };
+
+    match nan {
+        Some(nan) => nan.quieted_nan(format),
+        None => default_nan(format, context),
+    }
+}
+
+// Result of an invalid operation (eg. inf - inf)
+pub(crate) fn invalid(format: &Format, context: &mut Context) -> Value {
+    context.raise(Flags::INVALID);
+    default_nan(format, context)
+}
+
+fn default_nan(format: &Format, context: &Context) -> Value {
+    let nan = Value::quiet_nan(format.clone());
+    match context.nan_mode {
+        NanMode::X86 => Value::from_comps(true, nan.exp, nan.sig, nan.format),
+        _ => nan,
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseOpError {
+    UnknownOp(String),
+    InvalidTargetFormat(FormatError),
+}
+
+impl fmt::Display for ParseOpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseOpError::UnknownOp(op) => write!(
+                f,
+                "Requested op \"{}\" is not valid; expected \"addition\", \"multiplication\", \"fused_multiply_add\" or \"conversion:<format>\".",
+                op
+            ),
+            ParseOpError::InvalidTargetFormat(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl error::Error for ParseOpError {}
+
+// An op on raw encodings, for tools that choose the op at runtime
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Op {
+    Addition,
+    Multiplication,
+    FusedMultiplyAdd,
+    // Into the given format
+    Conversion(Format),
+}
+
+impl Op {
+    pub fn num_sources(&self) -> usize {
+        match self {
+            Op::Addition | Op::Multiplication => 2,
+            Op::FusedMultiplyAdd => 3,
+            Op::Conversion(_) => 1,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Op::Addition => "addition",
+            Op::Multiplication => "multiplication",
+            Op::FusedMultiplyAdd => "fused_multiply_add",
+            Op::Conversion(_) => "conversion",
+        }
+    }
+
+    // Format of the result, given the sources' format
+    pub fn result_format(&self, format: &Format) -> Format {
+        match self {
+            Op::Conversion(target) => target.clone(),
+            _ => format.clone(),
+        }
+    }
+
+    // Applies the op to sources (as encodings in format)
+    pub fn evaluate(&self, sources: &[u32], format: &Format, context: &mut Context) -> Value {
+        if sources.len() != self.num_sources() {
+            panic!("Requested {} of {} sources, but it takes {} sources.", self.name(), sources.len(), self.num_sources());
+        }
+
+        let source = |i: usize| Value::from_bits(sources[i], format.clone());
+        match self {
+            Op::Addition => addition::addition_with_context(source(0), source(1), context),
+            Op::Multiplication => multiplication::multiplication_with_context(source(0), source(1), context),
+            Op::FusedMultiplyAdd => fused_multiply_add::fused_multiply_add_with_context(source(0), source(1), source(2), context),
+            Op::Conversion(target) => conversion::conversion_with_context(source(0), target.clone(), context),
+        }
+    }
+}
+
+// The op's name, with a conversion's target format after a colon, eg. "addition" or "conversion:e4m3fn"
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Op::Conversion(target) => write!(f, "{}:{}", self.name(), target),
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+impl FromStr for Op {
+    type Err = ParseOpError;
+
+    fn from_str(s: &str) -> Result<Op, ParseOpError> {
+        match s {
+            "addition" => Ok(Op::Addition),
+            "multiplication" => Ok(Op::Multiplication),
+            "fused_multiply_add" => Ok(Op::FusedMultiplyAdd),
+            _ => match s.strip_prefix("conversion:") {
+                Some(target) => Ok(Op::Conversion(target.parse().map_err(ParseOpError::InvalidTargetFormat)?)),
+                None => Err(ParseOpError::UnknownOp(s.to_string())),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn op_from_str() {
+        for op in &[Op::Addition, Op::Multiplication, Op::FusedMultiplyAdd, Op::Conversion(Format::ocp_fp8_e4m3())] {
+            assert_eq!(op.to_string().parse::<Op>(), Ok(op.clone()));
+        }
+
+        assert_eq!("conversion:e4m3fn".parse::<Op>(), Ok(Op::Conversion(Format::ocp_fp8_e4m3())));
+        assert_eq!("conversion".parse::<Op>(), Err(ParseOpError::UnknownOp("conversion".to_string())));
+        assert_eq!("division".parse::<Op>(), Err(ParseOpError::UnknownOp("division".to_string())));
+        assert_eq!("conversion:e1m2".parse::<Op>(), Err(ParseOpError::InvalidTargetFormat(FormatError::NotEnoughExpBits)));
+    }
+}
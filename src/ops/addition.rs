@@ -1,13 +1,83 @@
+use crate::context::*;
+use crate::format::*;
+use crate::rounding::*;
 use crate::value::*;
 
+use super::*;
+
 use std::mem;
 
+// Bits kept below the lsb while aligning with IEEE rounding; anything shifted out past them collapses into a sticky bit
+pub(crate) const NUM_GUARD_BITS: u32 = 64;
+
 pub fn addition(source1: Value, source2: Value) -> Value {
+    addition_with_context(source1, source2, &mut Context::new())
+}
+
+pub fn addition_with_context(source1: Value, source2: Value, context: &mut Context) -> Value {
+    let swapped = match swap(source1, source2, context) {
+        Unpacked::Finite(swapped) => swapped,
+        Unpacked::Special(result) => return result,
+    };
+    let aligned = align(&swapped, context);
+    let negated = negate(&aligned);
+    let sum = add(&negated, context);
+
+    // Normalize, round and pack sum
+    let normalized = normalize(sum.sign, sum.sig, sum.exp, &sum.format);
+    let rounded = round_normalized(&normalized, context);
+    pack(&rounded, context)
+}
+
+// Swap stage: finite sources' full sigs and exps, with the source of greater magnitude as lhs
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Swapped {
+    pub(crate) lhs_sign: bool,
+    pub(crate) lhs_sig: u32,
+    pub(crate) lhs_exp: i32,
+    pub(crate) rhs_sign: bool,
+    pub(crate) rhs_sig: u32,
+    pub(crate) rhs_exp: i32,
+    pub(crate) format: Format,
+}
+
+impl Swapped {
+    pub fn lhs_sign(&self) -> bool {
+        self.lhs_sign
+    }
+
+    pub fn lhs_sig(&self) -> u32 {
+        self.lhs_sig
+    }
+
+    pub fn lhs_exp(&self) -> i32 {
+        self.lhs_exp
+    }
+
+    pub fn rhs_sign(&self) -> bool {
+        self.rhs_sign
+    }
+
+    pub fn rhs_sig(&self) -> u32 {
+        self.rhs_sig
+    }
+
+    pub fn rhs_exp(&self) -> i32 {
+        self.rhs_exp
+    }
+}
+
+pub fn swap(source1: Value, source2: Value, context: &mut Context) -> Unpacked<Swapped> {
     assert_eq!(source1.format, source2.format);
 
-    // Treat denormal input(s) as zero
-    let mut source1 = flush_denormal_to_zero(source1);
-    let mut source2 = flush_denormal_to_zero(source2);
+    // Treat denormal input(s) as zero (if enabled)
+    let mut source1 = denormal_input(source1, context);
+    let mut source2 = denormal_input(source2, context);
+
+    // Propagate NaNs (before swapping, as operand order matters)
+    if source1.is_nan() || source2.is_nan() {
+        return Unpacked::Special(propagate_nan(&[&source1, &source2], &source1.format, context));
+    }
 
     // Ensure source with greater magnitude is lhs
     if source1.exp < source2.exp || (source1.exp == source2.exp && source1.sig < source2.sig) {
@@ -16,87 +86,251 @@ pub fn addition(source1: Value, source2: Value) -> Value {
 
     let format = &source1.format;
 
-    // Propagate (replace!) NaNs
-    let sig_quiet_bit = 1 << (format.num_sig_bits - 1);
-    let quiet_nan = Value::from_comps(false, format.exp_max(), sig_quiet_bit, format.clone());
+    if source1.is_inf() {
+        // TODO: Is this case really important?
+        if source2.is_inf() && source1.sign != source2.sign {
+            return Unpacked::Special(invalid(format, context));
+        }
 
-    if source1.is_nan() || source2.is_nan() {
-        return quiet_nan;
+        return Unpacked::Special(Value::infinity(source1.sign, format.clone()));
     }
 
-    // TODO: Is this case really important?
-    if source1.is_inf() && source2.is_inf() && source1.sign != source2.sign {
-        return quiet_nan;
+    // Decode full sigs
+    let (lhs_sig, lhs_exp) = source1.decode_finite();
+    let (rhs_sig, rhs_exp) = source2.decode_finite();
+    Unpacked::Finite(Swapped {
+        lhs_sign: source1.sign,
+        lhs_sig,
+        lhs_exp,
+        rhs_sign: source2.sign,
+        rhs_sig,
+        rhs_exp,
+        format: format.clone(),
+    })
+}
+
+// Align stage: both sigs extended by guard bits, with rhs shifted to lhs's point. With IEEE rounding, there are
+// NUM_GUARD_BITS and bits shifted out past them collapse into a sticky bit; otherwise, as in the hardware, there are none
+// and bits shifted out are truncated.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Aligned {
+    pub(crate) lhs_sign: bool,
+    pub(crate) lhs_sig: u128,
+    pub(crate) rhs_sign: bool,
+    pub(crate) rhs_sig: u128,
+    pub(crate) exp: i32,
+    pub(crate) shift: u32,
+    pub(crate) num_guard_bits: u32,
+    pub(crate) format: Format,
+}
+
+impl Aligned {
+    pub fn lhs_sign(&self) -> bool {
+        self.lhs_sign
     }
 
-    // Decode full sigs
-    let hidden_bit = 1 << format.num_sig_bits;
-    let source1_sig = hidden_bit | source1.sig;
-    let mut source2_sig = hidden_bit | source2.sig;
+    pub fn lhs_sig(&self) -> u128 {
+        self.lhs_sig
+    }
+
+    pub fn rhs_sign(&self) -> bool {
+        self.rhs_sign
+    }
+
+    pub fn rhs_sig(&self) -> u128 {
+        self.rhs_sig
+    }
+
+    // Unbiased exponent of both sigs' lsb
+    pub fn exp(&self) -> i32 {
+        self.exp
+    }
+
+    // Places rhs was shifted right by
+    pub fn shift(&self) -> u32 {
+        self.shift
+    }
+
+    pub fn num_guard_bits(&self) -> u32 {
+        self.num_guard_bits
+    }
+}
+
+pub fn align(swapped: &Swapped, context: &Context) -> Aligned {
+    let num_guard_bits = if context.ieee_rounding { NUM_GUARD_BITS } else { 0 };
+    let lhs_sig = (swapped.lhs_sig as u128) << num_guard_bits;
+    let mut rhs_sig = (swapped.rhs_sig as u128) << num_guard_bits;
 
     // Align rhs point (if applicable)
-    if source2.exp < source1.exp {
-        let shift_digits = source1.exp - source2.exp;
-        if shift_digits > format.num_sig_bits {
-            source2_sig = 0;
-        } else {
-            source2_sig >>= shift_digits;
-        }
+    let shift = (swapped.lhs_exp - swapped.rhs_exp) as u32;
+    if shift > 0 {
+        let is_sticky = context.ieee_rounding && if shift >= 128 { rhs_sig != 0 } else { (rhs_sig & ((1 << shift) - 1)) != 0 };
+        let shifted_sig = if shift >= 128 { 0 } else { rhs_sig >> shift };
+        rhs_sig = shifted_sig | (is_sticky as u128);
     }
 
-    // If sources' signs differ, negate rhs sig via two's complement
-    let sig_including_hidden_and_overflow_bits_mask = (1 << (format.num_sig_bits + 2)) - 1;
-    if source1.sign != source2.sign {
-        source2_sig = (!source2_sig).wrapping_add(1) & sig_including_hidden_and_overflow_bits_mask;
+    Aligned {
+        lhs_sign: swapped.lhs_sign,
+        lhs_sig,
+        rhs_sign: swapped.rhs_sign,
+        rhs_sig,
+        exp: swapped.lhs_exp - num_guard_bits as i32,
+        shift,
+        num_guard_bits,
+        format: swapped.format.clone(),
     }
+}
 
-    // Calculate sum
-    let sum_sign = source1.sign;
-    let mut sum_exp = source1.exp;
-    let mut sum_sig = (source1_sig + source2_sig) & sig_including_hidden_and_overflow_bits_mask;
-    let is_sum_zero = sum_exp == 0 || sum_sig == 0;
+// Negate stage: if sources' signs differ, rhs sig in two's complement (in the width of a sig with its hidden, overflow
+// and guard bits)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Negated {
+    pub(crate) lhs_sign: bool,
+    pub(crate) lhs_sig: u128,
+    pub(crate) rhs_sign: bool,
+    pub(crate) rhs_sig: u128,
+    pub(crate) exp: i32,
+    pub(crate) num_guard_bits: u32,
+    pub(crate) format: Format,
+}
 
-    // Normalize sum in case of hidden bit overflow
-    let sum_sig_overflow = ((sum_sig >> (format.num_sig_bits + 1)) & 1) != 0;
-    if sum_sig_overflow {
-        sum_exp += 1;
-        sum_sig >>= 1;
+impl Negated {
+    pub fn lhs_sign(&self) -> bool {
+        self.lhs_sign
     }
 
-    // Check for infinity (exp overflow)
-    let is_sum_inf = sum_exp >= format.exp_max();
+    pub fn lhs_sig(&self) -> u128 {
+        self.lhs_sig
+    }
 
-    // Normalize sum in case of cancellations from potentially-negative rhs
-    let sum_sig_leading_zeros = sum_sig.leading_zeros() - (32 - (format.num_sig_bits + 1));
-    sum_sig <<= sum_sig_leading_zeros;
-    let is_sum_zero = is_sum_zero || sum_sig_leading_zeros >= sum_exp;
-    sum_exp = sum_exp.wrapping_sub(sum_sig_leading_zeros);
+    pub fn rhs_sign(&self) -> bool {
+        self.rhs_sign
+    }
 
-    if is_sum_inf {
-        Value::from_comps(sum_sign, format.exp_max(), 0, format.clone())
-    } else if is_sum_zero {
-        // TODO: Handle sign properly (or not? :) )
-        Value::from_comps(false, 0, 0, format.clone())
-    } else {
-        // Remove hidden bit from sum
-        let sum_sig = sum_sig & ((1 << format.num_sig_bits) - 1);
-        Value::from_comps(sum_sign, sum_exp, sum_sig, format.clone())
+    pub fn rhs_sig(&self) -> u128 {
+        self.rhs_sig
+    }
+
+    pub fn exp(&self) -> i32 {
+        self.exp
+    }
+
+    pub fn is_subtraction(&self) -> bool {
+        self.lhs_sign != self.rhs_sign
     }
 }
 
-fn flush_denormal_to_zero(value: Value) -> Value {
-    if value.exp == 0 {
-        Value::from_comps(value.sign, value.exp, 0, value.format)
+pub(crate) fn sig_including_hidden_and_overflow_bits_mask(format: &Format, num_guard_bits: u32) -> u128 {
+    (1 << (format.num_sig_bits + 2 + num_guard_bits)) - 1
+}
+
+pub fn negate(aligned: &Aligned) -> Negated {
+    let rhs_sig = if aligned.lhs_sign != aligned.rhs_sign {
+        (!aligned.rhs_sig).wrapping_add(1) & sig_including_hidden_and_overflow_bits_mask(&aligned.format, aligned.num_guard_bits)
     } else {
-        value
+        aligned.rhs_sig
+    };
+
+    Negated {
+        lhs_sign: aligned.lhs_sign,
+        lhs_sig: aligned.lhs_sig,
+        rhs_sign: aligned.rhs_sign,
+        rhs_sig,
+        exp: aligned.exp,
+        num_guard_bits: aligned.num_guard_bits,
+        format: aligned.format.clone(),
+    }
+}
+
+// Add stage: the (unnormalized) sum, (-1)^sign * sig * 2^exp
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Sum {
+    pub(crate) sign: bool,
+    pub(crate) sig: u128,
+    pub(crate) exp: i32,
+    pub(crate) format: Format,
+}
+
+impl Sum {
+    pub fn sign(&self) -> bool {
+        self.sign
+    }
+
+    pub fn sig(&self) -> u128 {
+        self.sig
+    }
+
+    pub fn exp(&self) -> i32 {
+        self.exp
     }
 }
 
+pub fn add(negated: &Negated, context: &Context) -> Sum {
+    let sig = (negated.lhs_sig + negated.rhs_sig) & sig_including_hidden_and_overflow_bits_mask(&negated.format, negated.num_guard_bits);
+
+    // An exact zero takes its sign from both sources
+    let sign = if sig == 0 {
+        exact_zero_sum_sign(negated.lhs_sign, negated.rhs_sign, context)
+    } else {
+        negated.lhs_sign
+    };
+
+    Sum { sign, sig, exp: negated.exp, format: negated.format.clone() }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    use crate::format::*;
+    use crate::flags::*;
+    use crate::iter::*;
+
+    #[test]
+    fn addition_stages() {
+        let f = Format::ieee754_single();
+        let mut context = Context::new();
+
+        let swapped = match swap(Value::from_bits(0xbf400000, f.clone()), Value::from_bits(0x3f800000, f.clone()), &mut context) {
+            Unpacked::Finite(swapped) => swapped,
+            Unpacked::Special(result) => panic!("Unexpected special result {:?}", result),
+        }; // -0.75 + 1.0
+
+        assert_eq!((swapped.lhs_sign(), swapped.lhs_sig(), swapped.lhs_exp()), (false, 0x800000, -23));
+        assert_eq!((swapped.rhs_sign(), swapped.rhs_sig(), swapped.rhs_exp()), (true, 0xc00000, -24));
+
+        let aligned = align(&swapped, &context);
+
+        assert_eq!((aligned.lhs_sig(), aligned.rhs_sig()), (0x800000, 0x600000));
+        assert_eq!((aligned.exp(), aligned.shift(), aligned.num_guard_bits()), (-23, 1, 0));
+
+        let ieee_aligned = align(&swapped, &Context::new().with_ieee_rounding(true));
+
+        assert_eq!((ieee_aligned.lhs_sig(), ieee_aligned.rhs_sig()), (0x800000 << 64, 0x600000 << 64));
+        assert_eq!((ieee_aligned.exp(), ieee_aligned.shift(), ieee_aligned.num_guard_bits()), (-87, 1, 64));
+
+        let negated = negate(&aligned);
+
+        assert!(negated.is_subtraction());
+        assert_eq!(negated.rhs_sig(), 0x1a00000);
+
+        let sum = add(&negated, &context);
+
+        assert_eq!((sum.sign(), sum.sig(), sum.exp()), (false, 0x200000, -23));
+
+        let normalized = normalize(sum.sign(), sum.sig(), sum.exp(), &f);
+
+        assert_eq!((normalized.num_leading_zeros(), normalized.msb_exp(), normalized.lsb_exp()), (106, -2, -25));
+
+        let rounded = round_normalized(&normalized, &mut context);
+
+        assert_eq!((rounded.sig(), rounded.is_inexact()), (0x800000, false));
+        assert_eq!(pack(&rounded, &mut context).to_bits(), 0x3e800000); // 0.25
+
+        match swap(Value::from_bits(0x7f800000, f.clone()), Value::from_bits(0x3f800000, f.clone()), &mut context) {
+            Unpacked::Special(result) => assert_eq!(result.to_bits(), 0x7f800000),
+            Unpacked::Finite(swapped) => panic!("Unexpected finite operands {:?}", swapped),
+        }
+    }
 
     #[test]
     fn addition_basic() {
@@ -288,6 +522,283 @@ mod tests {
         assert_eq!(res.to_bits(), 0xbe800000); // -0.25
     }
 
+    #[test]
+    fn addition_truncation() {
+        let f = Format::ieee754_single();
+
+        let a = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let b = Value::from_comps(false, 103, 0, f.clone()); // 2^-24
+
+        let res = addition(a, b);
+
+        assert_eq!(res.to_bits(), 0x3f800000); // 1.0
+
+        let a = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let b = Value::from_comps(true, 97, 0, f.clone()); // -2^-30
+
+        let res = addition(a, b);
+
+        assert_eq!(res.to_bits(), 0x3f800000); // 1.0 (addend truncated while aligning)
+
+        let a = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let b = Value::from_comps(true, 97, 0, f.clone()); // -2^-30
+
+        let res = addition_with_context(a, b, &mut Context::new().with_ieee_rounding(true));
+
+        assert_eq!(res.to_bits(), 0x3f7fffff); // 0.99999994
+
+        let a = Value::from_comps(false, 127, 0x7fffff, f.clone()); // 1.99999988
+        let b = Value::from_comps(false, 127, 0x7fffff, f.clone()); // 1.99999988
+
+        let res = addition(a, b);
+
+        assert_eq!(res.to_bits(), 0x407fffff); // 3.99999976
+
+        let a = Value::from_comps(true, 254, 0x7fffff, f.clone()); // -max value
+        let b = Value::from_comps(true, 254, 0, f.clone()); // -2^127
+
+        let res = addition(a, b);
+
+        assert_eq!(res.to_bits(), 0xff800000); // -inf
+
+        let a = Value::from_comps(false, 2, 0, f.clone()); // 2^-125
+        let b = Value::from_comps(true, 1, 0x7fffff, f.clone()); // -(2^-125 - 2^-149)
+
+        let res = addition(a, b);
+
+        assert_eq!(res.to_bits(), 0x00000000); // denormal flushed to 0.0
+    }
+
+    #[test]
+    fn addition_stochastic() {
+        let f = Format::ieee754_single();
+
+        // 1.0 + 0.75 ulp
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 2 })
+            .with_ieee_rounding(true)
+            .with_random_source(|_| 0b00);
+
+        let a = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let b = Value::from_comps(false, 103, 1 << 22, f.clone()); // 1.5 * 2^-24
+
+        let res = addition_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0x3f800000); // 1.0
+
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 2 })
+            .with_ieee_rounding(true)
+            .with_random_source(|_| 0b01);
+
+        let a = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let b = Value::from_comps(false, 103, 1 << 22, f.clone()); // 1.5 * 2^-24
+
+        let res = addition_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0x3f800001); // 1.00000012
+
+        // Bits shifted out during alignment still contribute
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 16 })
+            .with_ieee_rounding(true)
+            .with_random_source(|_| 0x0000);
+
+        let a = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let b = Value::from_comps(true, 60, 0, f.clone()); // -2^-67
+
+        let res = addition_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0x3f7fffff); // 0.99999994
+
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 16 })
+            .with_ieee_rounding(true)
+            .with_random_source(|_| 0x0001);
+
+        let a = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let b = Value::from_comps(true, 60, 0, f.clone()); // -2^-67
+
+        let res = addition_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0x3f800000); // 1.0
+
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 16 })
+            .with_ieee_rounding(true)
+            .with_random_source(|_| 0xffff);
+
+        let a = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let b = Value::from_comps(false, 88, 0, f.clone()); // 2^-39
+
+        let res = addition_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0x3f800001); // 1.00000012
+    }
+
+    #[test]
+    fn addition_saturate() {
+        let f = Format::ieee754_single();
+
+        let mut context = Context::new().with_rounding_mode(RoundingMode::NearestEven);
+
+        let a = Value::from_comps(false, 254, 0x7fffff, f.clone()); // +max value
+        let b = Value::from_comps(false, 254, 0, f.clone()); // 2^127
+
+        let res = addition_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0x7f800000); // +inf
+        assert_eq!(context.flags(), Flags::OVERFLOW | Flags::INEXACT);
+
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::NearestEven)
+            .with_overflow_mode(OverflowMode::Saturate);
+
+        let a = Value::from_comps(false, 254, 0x7fffff, f.clone()); // +max value
+        let b = Value::from_comps(false, 254, 0, f.clone()); // 2^127
+
+        let res = addition_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0x7f7fffff); // +max value
+        assert_eq!(context.flags(), Flags::OVERFLOW | Flags::INEXACT);
+
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::NearestEven)
+            .with_overflow_mode(OverflowMode::Saturate)
+            .with_ieee_rounding(true);
+
+        let a = Value::from_comps(true, 254, 0x7fffff, f.clone()); // -max value
+        let b = Value::from_comps(true, 230, 0, f.clone()); // -2^103 (half an ulp)
+
+        let res = addition_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0xff7fffff); // -max value
+        assert_eq!(context.flags(), Flags::OVERFLOW | Flags::INEXACT);
+
+        // Infinite sources aren't overflow
+        let mut context = Context::new().with_overflow_mode(OverflowMode::Saturate);
+
+        let a = Value::from_comps(true, 255, 0, f.clone()); // -inf
+        let b = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+
+        let res = addition_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0xff800000); // -inf
+        assert!(context.flags().is_empty());
+
+        // Overflow is flagged when truncating as well
+        let mut context = Context::new();
+
+        let a = Value::from_comps(false, 254, 0, f.clone()); // 2^127
+        let b = Value::from_comps(false, 254, 0, f.clone()); // 2^127
+
+        let res = addition_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0x7f800000); // +inf
+        assert_eq!(context.flags(), Flags::OVERFLOW | Flags::INEXACT);
+    }
+
+    #[test]
+    fn addition_finite_only() {
+        let f = Format::ocp_fp4_e2m1();
+
+        let a = Value::from_comps(false, 3, 1, f.clone()); // 6.0
+        let b = Value::from_comps(false, 1, 0, f.clone()); // 1.0
+
+        let res = addition(a, b);
+
+        assert_eq!(res.to_bits(), 0x7); // 6.0 (truncated)
+
+        let a = Value::from_comps(true, 2, 1, f.clone()); // -3.0
+        let b = Value::from_comps(false, 1, 1, f.clone()); // 1.5
+
+        let res = addition(a, b);
+
+        assert_eq!(res.to_bits(), 0xc); // -2.0 (addend truncated while aligning)
+
+        let a = Value::from_comps(true, 2, 1, f.clone()); // -3.0
+        let b = Value::from_comps(false, 1, 1, f.clone()); // 1.5
+
+        let res = addition_with_context(a, b, &mut Context::new().with_ieee_rounding(true));
+
+        assert_eq!(res.to_bits(), 0xb); // -1.5
+    }
+
+    #[test]
+    fn addition_exact_zero() {
+        let f = Format::ieee754_single();
+
+        // By default, like the hardware, every exact zero sum is +0.0
+        let a = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let b = Value::from_comps(true, 127, 0, f.clone()); // -1.0
+
+        let res = addition(a, b);
+
+        assert_eq!(res.to_bits(), 0x00000000); // +0.0
+
+        let a = Value::from_comps(true, 0, 0, f.clone()); // -0.0
+        let b = Value::from_comps(true, 0, 0, f.clone()); // -0.0
+
+        let res = addition(a, b);
+
+        assert_eq!(res.to_bits(), 0x00000000); // +0.0
+
+        // With IEEE rounding, it's -0.0 when rounding toward negative or when both addends are -0.0
+        let a = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let b = Value::from_comps(true, 127, 0, f.clone()); // -1.0
+        let mut context = Context::new().with_ieee_rounding(true);
+
+        let res = addition_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0x00000000); // +0.0
+
+        let a = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let b = Value::from_comps(true, 127, 0, f.clone()); // -1.0
+        let mut context = Context::new().with_rounding_mode(RoundingMode::TowardNegative).with_ieee_rounding(true);
+
+        let res = addition_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0x80000000); // -0.0
+
+        let a = Value::from_comps(true, 0, 0, f.clone()); // -0.0
+        let b = Value::from_comps(true, 0, 0, f.clone()); // -0.0
+        let mut context = Context::new().with_ieee_rounding(true);
+
+        let res = addition_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0x80000000); // -0.0
+    }
+
+    #[test]
+    fn addition_denormal_inputs() {
+        let f = Format::ieee754_single();
+
+        let a = Value::from_comps(false, 0, 1, f.clone()); // min subnormal
+        let b = Value::from_comps(false, 0, 2, f.clone()); // 2 * min subnormal
+
+        let res = addition(a, b);
+
+        assert_eq!(res.to_bits(), 0x00000000); // 0.0 (inputs treated as zero)
+
+        let a = Value::from_comps(false, 0, 1, f.clone()); // min subnormal
+        let b = Value::from_comps(false, 0, 2, f.clone()); // 2 * min subnormal
+        let mut context = Context::new().with_denormals_are_zero(false).with_flush_to_zero(false);
+
+        let res = addition_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0x00000003); // 3 * min subnormal
+        assert!(context.flags().is_empty());
+
+        let a = Value::from_comps(false, 1, 0, f.clone()); // min normal
+        let b = Value::from_comps(true, 0, 1, f.clone()); // -min subnormal
+        let mut context = Context::new().with_denormals_are_zero(false);
+
+        let res = addition_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0x00000000); // 0.0 (result flushed to zero)
+        assert_eq!(context.flags(), Flags::UNDERFLOW | Flags::INEXACT);
+    }
+
     #[test]
     fn addition_nan() {
         let f = Format::ieee754_single();
@@ -382,4 +893,78 @@ mod tests {
 
         assert_eq!(res.to_bits(), 0x7fc00000); // NaN
     }
+
+    #[test]
+    fn addition_nan_modes() {
+        let f = Format::ieee754_single();
+
+        let qnan = 0xffc01234; // -qNaN
+        let snan = 0x7f800042; // +sNaN
+        let one = 0x3f800000; // 1.0
+        let inf = 0x7f800000; // +inf
+        let neg_inf = 0xff800000; // -inf
+
+        for &(nan_mode, qnan_one, one_qnan, qnan_snan, snan_one, inf_neg_inf) in &[
+            (NanMode::Canonical, 0x7fc00000, 0x7fc00000, 0x7fc00000, 0x7fc00000, 0x7fc00000),
+            (NanMode::PropagateFirst, 0xffc01234, 0xffc01234, 0xffc01234, 0x7fc00042, 0x7fc00000),
+            (NanMode::X86, 0xffc01234, 0xffc01234, 0xffc01234, 0x7fc00042, 0xffc00000),
+            (NanMode::Arm, 0xffc01234, 0xffc01234, 0x7fc00042, 0x7fc00042, 0x7fc00000),
+        ] {
+            let mut context = Context::new().with_nan_mode(nan_mode);
+
+            let res = addition_with_context(Value::from_bits(qnan, f.clone()), Value::from_bits(one, f.clone()), &mut context);
+
+            assert_eq!(res.to_bits(), qnan_one);
+            assert!(context.flags().is_empty());
+
+            let res = addition_with_context(Value::from_bits(one, f.clone()), Value::from_bits(qnan, f.clone()), &mut context);
+
+            assert_eq!(res.to_bits(), one_qnan);
+            assert!(context.flags().is_empty());
+
+            let res = addition_with_context(Value::from_bits(snan, f.clone()), Value::from_bits(one, f.clone()), &mut context);
+
+            assert_eq!(res.to_bits(), snan_one);
+            assert_eq!(context.flags(), Flags::INVALID);
+
+            context.clear_flags();
+
+            let res = addition_with_context(Value::from_bits(qnan, f.clone()), Value::from_bits(snan, f.clone()), &mut context);
+
+            assert_eq!(res.to_bits(), qnan_snan);
+            assert_eq!(context.flags(), Flags::INVALID);
+
+            context.clear_flags();
+
+            let res = addition_with_context(Value::from_bits(inf, f.clone()), Value::from_bits(neg_inf, f.clone()), &mut context);
+
+            assert_eq!(res.to_bits(), inf_neg_inf);
+            assert_eq!(context.flags(), Flags::INVALID);
+        }
+    }
+
+    #[test]
+    fn addition_exhaustive() {
+        for format in &[Format::ocp_fp8_e5m2(), Format::ocp_fp8_e4m3(), Format::ocp_fp6_e3m2(), Format::ocp_fp4_e2m1()] {
+            for &(rounding_mode, flush_to_zero) in &[(RoundingMode::TowardZero, true), (RoundingMode::NearestEven, false)] {
+                for (a, b) in format.value_pairs().with_filter(ValueFilter::Finite) {
+                    let mut expected_context = Context::new().with_rounding_mode(rounding_mode).with_ieee_rounding(true);
+                    expected_context.flush_to_zero = flush_to_zero;
+                    let mut context = Context::new().with_rounding_mode(rounding_mode).with_ieee_rounding(true);
+                    context.flush_to_zero = flush_to_zero;
+
+                    // Sums of these narrow formats are exact in f64; denormal inputs are always treated as zero
+                    let exact = to_f64(&flush_denormal_to_zero(Value::from_bits(a.to_bits(), format.clone())))
+                        + to_f64(&flush_denormal_to_zero(Value::from_bits(b.to_bits(), format.clone())));
+                    let expected = round_f64(exact, format, &mut expected_context);
+
+                    let (a_bits, b_bits) = (a.to_bits(), b.to_bits());
+                    let res = addition_with_context(a, b, &mut context);
+
+                    assert_eq!(res.to_bits(), expected.to_bits(), "{} + {} ({:?})", a_bits, b_bits, format);
+                    assert_eq!(context.flags(), expected_context.flags(), "{} + {} ({:?})", a_bits, b_bits, format);
+                }
+            }
+        }
+    }
 }
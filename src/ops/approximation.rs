@@ -0,0 +1,484 @@
+// Reciprocal and reciprocal square root as shader units approximate them: a seed from a ROM indexed by the source's
+// leading sig bits, refined by Newton-Raphson iterations in fixed point (truncating each step), then rounded into the
+// format. Results aren't correctly rounded; ulp_error_report measures how far off they get.
+
+use std::fmt;
+use std::io;
+use std::io::Write;
+
+use crate::context::*;
+use crate::flags::*;
+use crate::format::*;
+use crate::rounding::*;
+use crate::value::*;
+
+use super::*;
+
+// Fraction bits of the fixed-point sigs Newton-Raphson iterates on
+const NUM_FRACTION_BITS: u32 = 62;
+
+const MAX_NUM_INDEX_BITS: u32 = 16;
+const MAX_NUM_ENTRY_BITS: u32 = 32;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ApproximatedFunction {
+    Reciprocal,
+    ReciprocalSquareRoot,
+}
+
+impl ApproximatedFunction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ApproximatedFunction::Reciprocal => "reciprocal",
+            ApproximatedFunction::ReciprocalSquareRoot => "reciprocal_square_root",
+        }
+    }
+}
+
+// Parameters of an approximation unit: the seed ROM has 2^num_index_bits entries of num_entry_bits bits each.
+//
+// A reciprocal seed is indexed by the source sig's leading num_index_bits fraction bits, and holds 1/m at the midpoint m
+// of that interval of [1, 2). A reciprocal square root seed is indexed by the source exponent's parity, then the sig's
+// leading num_index_bits - 1 fraction bits, and holds 1/sqrt(m) at the midpoint m of that interval of [1, 2) (or [2, 4)
+// for odd exponents). Either way, entries are the seed's fraction bits, rounded to nearest (seeds lie in (0.5, 1]; one
+// that rounds up to 1 is clamped to the largest entry).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Approximation {
+    pub(crate) num_index_bits: u32,
+    pub(crate) num_entry_bits: u32,
+    pub(crate) num_iterations: u32,
+}
+
+impl Approximation {
+    pub fn new(num_index_bits: u32, num_entry_bits: u32, num_iterations: u32) -> Approximation {
+        if !(1..=MAX_NUM_INDEX_BITS).contains(&num_index_bits) {
+            panic!(
+                "Requested approximation with {} index bits, but it must have between 1 and {} index bits.",
+                num_index_bits, MAX_NUM_INDEX_BITS
+            );
+        }
+        if !(1..=MAX_NUM_ENTRY_BITS).contains(&num_entry_bits) {
+            panic!(
+                "Requested approximation with {} entry bits, but it must have between 1 and {} entry bits.",
+                num_entry_bits, MAX_NUM_ENTRY_BITS
+            );
+        }
+
+        Approximation {
+            num_index_bits,
+            num_entry_bits,
+            num_iterations,
+        }
+    }
+
+    pub fn num_index_bits(&self) -> u32 {
+        self.num_index_bits
+    }
+
+    pub fn num_entry_bits(&self) -> u32 {
+        self.num_entry_bits
+    }
+
+    pub fn num_iterations(&self) -> u32 {
+        self.num_iterations
+    }
+
+    // Seed ROM entry index for function
+    pub fn seed(&self, function: ApproximatedFunction, index: u32) -> u32 {
+        let num_index_bits = self.num_index_bits;
+        let num_entry_bits = self.num_entry_bits;
+        let seed = match function {
+            ApproximatedFunction::Reciprocal => {
+                // Midpoint n / 2^(num_index_bits + 1), so the seed is 2^(num_entry_bits + num_index_bits + 1) / n
+                let n = (1u128 << (num_index_bits + 1)) + 2 * index as u128 + 1;
+                ((1u128 << (num_entry_bits + num_index_bits + 2)) / n).div_ceil(2)
+            }
+            ApproximatedFunction::ReciprocalSquareRoot => {
+                // Midpoint (n / 2^num_index_bits) * 2^parity, so the seed is sqrt(2^(2 * num_entry_bits +
+                // num_index_bits - parity) / n)
+                let parity = index >> (num_index_bits - 1);
+                let n = (1u128 << num_index_bits) + 2 * (index & ((1 << (num_index_bits - 1)) - 1)) as u128 + 1;
+                isqrt((1u128 << (2 * num_entry_bits + num_index_bits - parity + 2)) / n).div_ceil(2)
+            }
+        };
+        seed.min((1 << num_entry_bits) - 1) as u32
+    }
+
+    // Every seed ROM entry for function, in index order
+    pub fn seed_table(&self, function: ApproximatedFunction) -> Vec<u32> {
+        (0..1 << self.num_index_bits).map(|index| self.seed(function, index)).collect()
+    }
+
+    // The seed ROM for function, one entry per line in hex (padded to whole hex digits) for $readmemh, after a comment
+    // naming the parameters
+    pub fn write_seed_table_readmemh<W: Write>(&self, writer: &mut W, function: ApproximatedFunction) -> io::Result<()> {
+        writeln!(
+            writer,
+            "// {} seeds: {} index bits, {} entry bits",
+            function.name(),
+            self.num_index_bits,
+            self.num_entry_bits
+        )?;
+        let num_digits = self.num_entry_bits.div_ceil(4) as usize;
+        for seed in self.seed_table(function) {
+            writeln!(writer, "{:0width$x}", seed, width = num_digits)?;
+        }
+        Ok(())
+    }
+}
+
+pub fn reciprocal_approximation(source: Value, approximation: &Approximation) -> Value {
+    reciprocal_approximation_with_context(source, approximation, &mut Context::new())
+}
+
+pub fn reciprocal_approximation_with_context(source: Value, approximation: &Approximation, context: &mut Context) -> Value {
+    approximate(ApproximatedFunction::Reciprocal, source, approximation, context)
+}
+
+pub fn reciprocal_square_root_approximation(source: Value, approximation: &Approximation) -> Value {
+    reciprocal_square_root_approximation_with_context(source, approximation, &mut Context::new())
+}
+
+pub fn reciprocal_square_root_approximation_with_context(source: Value, approximation: &Approximation, context: &mut Context) -> Value {
+    approximate(ApproximatedFunction::ReciprocalSquareRoot, source, approximation, context)
+}
+
+// Floor of sqrt(x), by Newton's method from an initial guess at or above the root
+fn isqrt(x: u128) -> u128 {
+    if x < 2 {
+        return x;
+    }
+    let mut root = 1u128 << (128 - x.leading_zeros()).div_ceil(2);
+    loop {
+        let next = (root + x / root) / 2;
+        if next >= root {
+            return root;
+        }
+        root = next;
+    }
+}
+
+// Infinity from finite sources, as conversion maps infinities into formats without them
+fn divide_by_zero(sign: bool, format: Format, context: &mut Context) -> Value {
+    context.raise(Flags::DIVIDE_BY_ZERO);
+    if context.overflow_mode == OverflowMode::Saturate && format.special_encoding != SpecialEncoding::Ieee {
+        return Value::max_finite(sign, format);
+    }
+
+    Value::infinity(sign, format)
+}
+
+fn approximate(function: ApproximatedFunction, source: Value, approximation: &Approximation, context: &mut Context) -> Value {
+    // Treat denormal input as zero (if enabled)
+    let source = denormal_input(source, context);
+    let format = source.format.clone();
+
+    // Propagate NaNs
+    if source.is_nan() {
+        return propagate_nan(&[&source], &format, context);
+    }
+
+    match function {
+        ApproximatedFunction::Reciprocal => {
+            if source.is_inf() {
+                return Value::zero(source.sign, format);
+            }
+            if source.is_zero() {
+                return divide_by_zero(source.sign, format, context);
+            }
+        }
+        ApproximatedFunction::ReciprocalSquareRoot => {
+            if source.is_zero() {
+                return divide_by_zero(source.sign, format, context);
+            }
+            if source.sign {
+                // Formats without NaN have nothing better to offer than zero
+                if format.special_encoding == SpecialEncoding::FiniteOnly {
+                    context.raise(Flags::INVALID);
+                    return Value::zero(false, format);
+                }
+
+                return invalid(&format, context);
+            }
+            if source.is_inf() {
+                return Value::zero(false, format);
+            }
+        }
+    }
+
+    // Normalize source to m * 2^exp, with m in [1, 2)
+    let (sig, exp) = source.decode_finite();
+    let msb = 31 - sig.leading_zeros();
+    let m = (sig as u128) << (NUM_FRACTION_BITS - msb);
+    let exp = exp + msb as i32;
+
+    let num_index_bits = approximation.num_index_bits;
+    let seed = |index: u128| (approximation.seed(function, index as u32) as u128) << (NUM_FRACTION_BITS - approximation.num_entry_bits);
+    let one = 1u128 << NUM_FRACTION_BITS;
+    match function {
+        ApproximatedFunction::Reciprocal => {
+            // y' = y * (2 - m * y)
+            let mut y = seed((m >> (NUM_FRACTION_BITS - num_index_bits)) & ((1 << num_index_bits) - 1));
+            for _ in 0..approximation.num_iterations {
+                y = (y * (2 * one - ((m * y) >> NUM_FRACTION_BITS))) >> NUM_FRACTION_BITS;
+            }
+
+            round(source.sign, y, -(NUM_FRACTION_BITS as i32) - exp, &format, context)
+        }
+        ApproximatedFunction::ReciprocalSquareRoot => {
+            // Fold an odd exponent into m, so m is in [1, 4), then y' = y * (3 - m * y^2) / 2
+            let parity = exp.rem_euclid(2) as u32;
+            let fraction_index = (m >> (NUM_FRACTION_BITS + 1 - num_index_bits)) & ((1 << (num_index_bits - 1)) - 1);
+            let mut y = seed(((parity as u128) << (num_index_bits - 1)) | fraction_index);
+            let m = m << parity;
+            for _ in 0..approximation.num_iterations {
+                let m_y_squared = (((y * y) >> NUM_FRACTION_BITS) * m) >> NUM_FRACTION_BITS;
+                y = (y * (3 * one - m_y_squared)) >> (NUM_FRACTION_BITS + 1);
+            }
+
+            round(false, y, -(NUM_FRACTION_BITS as i32) - (exp - parity as i32) / 2, &format, context)
+        }
+    }
+}
+
+// Worst error of an approximation over a format's sources
+#[derive(Clone, Debug, PartialEq)]
+pub struct UlpErrorReport {
+    pub(crate) function: ApproximatedFunction,
+    pub(crate) format: Format,
+    pub(crate) max_ulp_error: f64,
+    pub(crate) worst_source: u32,
+    pub(crate) num_sources: u64,
+}
+
+impl UlpErrorReport {
+    // Largest distance from an exact result, in ulps of the format at that exact result (infinite if some source
+    // gave an infinite or NaN result)
+    pub fn max_ulp_error(&self) -> f64 {
+        self.max_ulp_error
+    }
+
+    // The (first) source with the largest error
+    pub fn worst_source(&self) -> u32 {
+        self.worst_source
+    }
+
+    pub fn num_sources(&self) -> u64 {
+        self.num_sources
+    }
+}
+
+impl fmt::Display for UlpErrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}: max {} ulp at ", self.function.name(), self.format, self.max_ulp_error)?;
+        write!(f, "{}", Value::from_bits(self.worst_source, self.format.clone()).to_fields_string())?;
+        write!(f, ", over {} sources", self.num_sources)
+    }
+}
+
+// Measures an approximation over every positive finite source that context's modes read as nonzero, and whose exact
+// result is a normal value (so overflow and underflow don't count against the approximation). Evaluates every
+// positive encoding, which takes a while for 32-bit formats.
+pub fn ulp_error_report(function: ApproximatedFunction, format: &Format, approximation: &Approximation, context: &mut Context) -> UlpErrorReport {
+    let (max_exp, max_sig) = format.max_finite_comps();
+    let max_finite_m = ((1u32 << format.num_sig_bits) | max_sig) as f64 / (1u32 << format.num_sig_bits) as f64;
+    let max_finite_bits = (max_exp << format.num_sig_bits) | max_sig;
+
+    let mut report = UlpErrorReport {
+        function,
+        format: format.clone(),
+        max_ulp_error: 0.0,
+        worst_source: 0,
+        num_sources: 0,
+    };
+    for bits in 1..=max_finite_bits {
+        let source = Value::from_bits(bits, format.clone());
+        if context.denormals_are_zero && source.is_subnormal() {
+            continue;
+        }
+
+        // The exact result is r * 2^exp, with r in (0.5, 1]
+        let (sig, exp) = source.decode_finite();
+        let msb = 31 - sig.leading_zeros();
+        let m = sig as f64 / (1u32 << msb) as f64;
+        let exp = exp + msb as i32;
+        let (r, exp) = match function {
+            ApproximatedFunction::Reciprocal => (1.0 / m, -exp),
+            ApproximatedFunction::ReciprocalSquareRoot => {
+                let parity = exp.rem_euclid(2);
+                (1.0 / (m * (1 << parity) as f64).sqrt(), -(exp - parity) / 2)
+            }
+        };
+        let (normalized_r, msb_exp) = if r == 1.0 { (r, exp) } else { (2.0 * r, exp - 1) };
+        if msb_exp < format.min_normal_exp() || msb_exp > format.max_normal_exp() || (msb_exp == format.max_normal_exp() && normalized_r > max_finite_m) {
+            continue;
+        }
+        report.num_sources += 1;
+
+        let result = match function {
+            ApproximatedFunction::Reciprocal => reciprocal_approximation_with_context(source, approximation, context),
+            ApproximatedFunction::ReciprocalSquareRoot => reciprocal_square_root_approximation_with_context(source, approximation, context),
+        };
+        let ulp_error = if result.is_finite() {
+            let (result_sig, result_exp) = result.decode_finite();
+            let result_r = result_sig as f64 * 2f64.powi(result_exp - exp);
+            (result_r - r).abs() * 2f64.powi(exp - (msb_exp - format.num_sig_bits as i32))
+        } else {
+            f64::INFINITY
+        };
+        if ulp_error > report.max_ulp_error {
+            report.max_ulp_error = ulp_error;
+            report.worst_source = bits;
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nearest_even() -> Context {
+        Context::new()
+            .with_rounding_mode(RoundingMode::NearestEven)
+            .with_flush_to_zero(false)
+            .with_denormals_are_zero(false)
+    }
+
+    #[test]
+    fn isqrt_floors() {
+        for x in (0..100000).chain([u128::MAX, u128::MAX - 1, 1 << 126, (1 << 126) - 1]) {
+            let root = isqrt(x);
+            assert!(root * root <= x && root + 1 > x / (root + 1), "{}", x);
+        }
+    }
+
+    #[test]
+    fn seed_tables() {
+        let approximation = Approximation::new(3, 6, 0);
+
+        assert_eq!(approximation.seed_table(ApproximatedFunction::Reciprocal), vec![0x3c, 0x36, 0x31, 0x2d, 0x29, 0x26, 0x23, 0x21]);
+        assert_eq!(approximation.seed_table(ApproximatedFunction::ReciprocalSquareRoot), vec![0x3c, 0x37, 0x32, 0x2f, 0x2b, 0x27, 0x24, 0x21]);
+
+        // 1 / 1.0078125 rounds up to 1, which is clamped
+        assert_eq!(Approximation::new(6, 6, 0).seed(ApproximatedFunction::Reciprocal, 0), 0x3f);
+
+        let mut readmemh = Vec::new();
+        approximation.write_seed_table_readmemh(&mut readmemh, ApproximatedFunction::Reciprocal).unwrap();
+
+        assert_eq!(
+            String::from_utf8(readmemh).unwrap(),
+            "// reciprocal seeds: 3 index bits, 6 entry bits\n3c\n36\n31\n2d\n29\n26\n23\n21\n"
+        );
+    }
+
+    #[test]
+    fn approximations() {
+        let f = Format::new(5, 10);
+        let approximation = Approximation::new(7, 9, 2);
+
+        // Results converge on the exact values from below, so round to nearest lands on them
+        let res = reciprocal_approximation_with_context(Value::from_bits(0x4000, f.clone()), &approximation, &mut nearest_even()); // 1 / 2
+        assert_eq!(res.to_bits(), 0x3800);
+        let res = reciprocal_approximation_with_context(Value::from_bits(0xc200, f.clone()), &approximation, &mut nearest_even()); // 1 / -3
+        assert_eq!(res.to_bits(), 0xb555);
+        let res = reciprocal_square_root_approximation_with_context(Value::from_bits(0x4400, f.clone()), &approximation, &mut nearest_even()); // 1 / sqrt(4)
+        assert_eq!(res.to_bits(), 0x3800);
+        let res = reciprocal_square_root_approximation_with_context(Value::from_bits(0x4000, f.clone()), &approximation, &mut nearest_even()); // 1 / sqrt(2)
+        assert_eq!(res.to_bits(), 0x39a8);
+
+        // Truncating lands just below them
+        let res = reciprocal_approximation(Value::from_bits(0x4000, f.clone()), &approximation);
+        assert_eq!(res.to_bits(), 0x37ff);
+
+        // The seed alone is only as good as the table: 1 / 1.00390625 to 9 bits is 510 / 512
+        let res = reciprocal_approximation_with_context(Value::from_bits(0x4000, f), &Approximation::new(7, 9, 0), &mut nearest_even());
+        assert_eq!(res.to_bits(), 0x37f8);
+    }
+
+    #[test]
+    fn special_sources() {
+        let f = Format::new(5, 10);
+        let approximation = Approximation::new(7, 9, 1);
+
+        let mut context = nearest_even();
+        let res = reciprocal_approximation_with_context(Value::zero(true, f.clone()), &approximation, &mut context);
+        assert_eq!(res.to_bits(), 0xfc00);
+        assert_eq!(context.flags(), Flags::DIVIDE_BY_ZERO);
+
+        let mut context = nearest_even();
+        let res = reciprocal_approximation_with_context(Value::infinity(true, f.clone()), &approximation, &mut context);
+        assert_eq!(res.to_bits(), 0x8000);
+        assert!(context.flags().is_empty());
+
+        let mut context = nearest_even();
+        let res = reciprocal_square_root_approximation_with_context(Value::from_bits(0xbc00, f.clone()), &approximation, &mut context);
+        assert_eq!(res.to_bits(), 0x7e00);
+        assert_eq!(context.flags(), Flags::INVALID);
+
+        let mut context = nearest_even();
+        let res = reciprocal_square_root_approximation_with_context(Value::zero(true, f.clone()), &approximation, &mut context);
+        assert_eq!(res.to_bits(), 0xfc00);
+        assert_eq!(context.flags(), Flags::DIVIDE_BY_ZERO);
+
+        let res = reciprocal_square_root_approximation_with_context(Value::infinity(false, f.clone()), &approximation, &mut nearest_even());
+        assert_eq!(res.to_bits(), 0x0000);
+
+        let mut context = nearest_even();
+        let res = reciprocal_approximation_with_context(Value::signaling_nan(f.clone()), &approximation, &mut context);
+        assert_eq!(res.to_bits(), 0x7e00);
+        assert_eq!(context.flags(), Flags::INVALID);
+
+        // Denormals are zero
+        let res = reciprocal_approximation(Value::min_subnormal(false, f), &approximation);
+        assert_eq!(res.to_bits(), 0x7c00);
+
+        // Formats without infinity or NaN
+        let e4m3 = Format::ocp_fp8_e4m3();
+        let res = reciprocal_approximation_with_context(Value::zero(false, e4m3.clone()), &approximation, &mut nearest_even());
+        assert_eq!(res.to_bits(), 0x7f);
+        let res = reciprocal_approximation_with_context(Value::zero(false, e4m3), &approximation, &mut nearest_even().with_overflow_mode(OverflowMode::Saturate));
+        assert_eq!(res.to_bits(), 0x7e);
+        let mut context = nearest_even();
+        let res = reciprocal_square_root_approximation_with_context(Value::from_bits(0xa, Format::ocp_fp4_e2m1()), &approximation, &mut context);
+        assert_eq!(res.to_bits(), 0x0);
+        assert_eq!(context.flags(), Flags::INVALID);
+    }
+
+    #[test]
+    fn ulp_error_reports() {
+        let f = Format::new(5, 10);
+
+        for function in [ApproximatedFunction::Reciprocal, ApproximatedFunction::ReciprocalSquareRoot] {
+            let report = |num_iterations| ulp_error_report(function, &f, &Approximation::new(7, 9, num_iterations), &mut nearest_even());
+            let seed_report = report(0);
+            let refined_report = report(1);
+
+            assert!(seed_report.max_ulp_error() > 8.0);
+            assert!(refined_report.max_ulp_error() > 0.5 && refined_report.max_ulp_error() < 0.53);
+            // Close enough to round correctly
+            assert!(report(2).max_ulp_error() < 0.5);
+        }
+
+        let report = ulp_error_report(ApproximatedFunction::Reciprocal, &f, &Approximation::new(7, 9, 1), &mut nearest_even());
+
+        // Sources whose reciprocals overflow or are subnormal don't count
+        assert_eq!(report.num_sources(), 0x7bff - 0x100 - (0x7bff - 0x7400));
+        assert_eq!(report.worst_source(), 0x0124);
+        assert_eq!(
+            report.to_string(),
+            format!("reciprocal e5m10: max {} ulp at 0x0124 (sign 0, exp 0x00, sig 0x124), over 29440 sources", report.max_ulp_error())
+        );
+
+        // Nor do sources read as zero
+        let report = ulp_error_report(ApproximatedFunction::ReciprocalSquareRoot, &f, &Approximation::new(7, 9, 1), &mut Context::new());
+
+        assert_eq!(report.num_sources(), 0x7bff - 0x3ff);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested approximation with 0 index bits, but it must have between 1 and 16 index bits.")]
+    fn no_index_bits() {
+        let _ = Approximation::new(0, 8, 1);
+    }
+}
@@ -0,0 +1,320 @@
+use crate::context::*;
+use crate::flags::*;
+use crate::format::*;
+use crate::rounding::*;
+use crate::value::*;
+
+use super::*;
+
+pub fn conversion(source: Value, format: Format) -> Value {
+    conversion_with_context(source, format, &mut Context::new())
+}
+
+pub fn conversion_with_context(source: Value, format: Format, context: &mut Context) -> Value {
+    // Treat denormal input as zero (if enabled)
+    let source = denormal_input(source, context);
+
+    // Propagate NaNs; formats without NaN have nothing better to offer than zero
+    if source.is_nan() {
+        if format.special_encoding == SpecialEncoding::FiniteOnly {
+            if source.is_signaling_nan() {
+                context.raise(Flags::INVALID);
+            }
+
+            return Value::zero(false, format);
+        }
+
+        return propagate_nan(&[&source], &format, context);
+    }
+
+    // Infinities map to infinities, or to NaN/max value in formats without them (always max value when saturating)
+    if source.is_inf() {
+        if context.overflow_mode == OverflowMode::Saturate && format.special_encoding != SpecialEncoding::Ieee {
+            return Value::max_finite(source.sign, format);
+        }
+
+        return Value::infinity(source.sign, format);
+    }
+
+    // Round into target format
+    let (sig, exp) = source.decode_finite();
+    round(source.sign, sig as u128, exp, &format, context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversion_basic() {
+        let single = Format::ieee754_single();
+        let half = Format::new(5, 10);
+
+        let a = Value::from_comps(false, 127, 0, single.clone()); // 1.0
+
+        let res = conversion(a, half.clone());
+
+        assert_eq!(res.to_bits(), 0x3c00); // 1.0
+
+        let a = Value::from_comps(true, 128, 0x260000, single.clone()); // -2.59375
+
+        let res = conversion(a, half.clone());
+
+        assert_eq!(res.to_bits(), 0xc130); // -2.59375
+
+        let a = Value::from_comps(false, 127, 0x7fffff, single.clone()); // 1.99999988
+
+        let res = conversion(a, half.clone());
+
+        assert_eq!(res.to_bits(), 0x3fff); // 1.9990234 (truncated)
+
+        let a = Value::from_comps(false, 15, 0x100, half.clone()); // 1.25
+
+        let res = conversion(a, single.clone());
+
+        assert_eq!(res.to_bits(), 0x3fa00000); // 1.25
+
+        let a = Value::from_comps(true, 0, 0, single.clone()); // -0.0
+
+        let res = conversion(a, half.clone());
+
+        assert_eq!(res.to_bits(), 0x8000); // -0.0
+    }
+
+    #[test]
+    fn conversion_custom_exp_bias() {
+        let single = Format::ieee754_single();
+        let e4m3_bias_11 = Format::new(4, 3).with_exp_bias(11);
+
+        let a = Value::from_comps(false, 127, 0, single.clone()); // 1.0
+
+        let res = conversion(a, e4m3_bias_11.clone());
+
+        assert_eq!(res.to_bits(), 0x58); // 1.0
+
+        let a = Value::from_comps(true, 117, 0, single.clone()); // -2^-10
+
+        let res = conversion(a, e4m3_bias_11.clone());
+
+        assert_eq!(res.to_bits(), 0x88); // -2^-10
+
+        let a = Value::from_comps(false, 130, 0, single.clone()); // 8.0
+
+        let res = conversion(a, e4m3_bias_11.clone());
+
+        assert_eq!(res.to_bits(), 0x70); // 8.0
+
+        let a = Value::from_comps(false, 131, 0, single.clone()); // 16.0
+
+        let res = conversion(a, e4m3_bias_11.clone());
+
+        assert_eq!(res.to_bits(), 0x78); // +inf
+
+        let a = Value::from_comps(false, 14, 0b100, e4m3_bias_11.clone()); // 12.0
+
+        let res = conversion(a, single.clone());
+
+        assert_eq!(res.to_bits(), 0x41400000); // 12.0
+    }
+
+    #[test]
+    fn conversion_daz_ftz() {
+        let single = Format::ieee754_single();
+        let half = Format::new(5, 10);
+
+        let a = Value::from_comps(false, 0, 1337, single.clone()); // any denormalized number
+
+        let res = conversion(a, half.clone());
+
+        assert_eq!(res.to_bits(), 0x0000); // 0.0
+
+        let a = Value::from_comps(true, 103, 0, single.clone()); // -2^-24 (denormal in half)
+
+        let res = conversion(a, half.clone());
+
+        assert_eq!(res.to_bits(), 0x0000); // 0.0
+
+        let a = Value::from_comps(true, 103, 0, single.clone()); // -2^-24 (denormal in half)
+
+        let res = conversion_with_context(a, half.clone(), &mut Context::new().with_ieee_rounding(true));
+
+        assert_eq!(res.to_bits(), 0x8000); // -0.0
+    }
+
+    #[test]
+    fn conversion_stochastic() {
+        let single = Format::ieee754_single();
+        let bfloat16 = Format::new(8, 7);
+
+        // 1.0 + 0.25 ulp
+        for &(random_bits, expected) in &[(0x00, 0x3f80), (0xbf, 0x3f80), (0xc0, 0x3f81), (0xff, 0x3f81)] {
+            let mut context = Context::new()
+                .with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 8 })
+                .with_random_source(move |_| random_bits);
+
+            let a = Value::from_comps(false, 127, 1 << 14, single.clone()); // 1.0 + 2^-9
+
+            let res = conversion_with_context(a, bfloat16.clone(), &mut context);
+
+            assert_eq!(res.to_bits(), expected);
+        }
+
+        // Exactly representable values are unaffected
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 8 })
+            .with_random_source(|_| 0xff);
+
+        let a = Value::from_comps(true, 128, 0x260000, single.clone()); // -2.59375
+
+        let res = conversion_with_context(a, bfloat16.clone(), &mut context);
+
+        assert_eq!(res.to_bits(), 0xc026); // -2.59375
+    }
+
+    #[test]
+    fn conversion_overflow() {
+        let single = Format::ieee754_single();
+        let half = Format::new(5, 10);
+
+        let a = Value::from_comps(false, 143, 0, single.clone()); // 65536.0
+
+        let res = conversion(a, half.clone());
+
+        assert_eq!(res.to_bits(), 0x7c00); // +inf
+
+        let a = Value::from_comps(false, 143, 0, single.clone()); // 65536.0
+
+        let res = conversion_with_context(a, half.clone(), &mut Context::new().with_ieee_rounding(true));
+
+        assert_eq!(res.to_bits(), 0x7bff); // 65504.0 (truncated)
+
+        let a = Value::from_comps(false, 143, 0, single.clone()); // 65536.0
+
+        let res = conversion(a, Format::ocp_fp6_e2m3());
+
+        assert_eq!(res.to_bits(), 0x1f); // 7.5
+    }
+
+    #[test]
+    fn conversion_saturate() {
+        let single = Format::ieee754_single();
+        let half = Format::new(5, 10);
+
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::NearestEven)
+            .with_overflow_mode(OverflowMode::Saturate);
+
+        let a = Value::from_comps(true, 143, 0, single.clone()); // -65536.0
+
+        let res = conversion_with_context(a, half.clone(), &mut context);
+
+        assert_eq!(res.to_bits(), 0xfbff); // -65504.0
+        assert_eq!(context.flags(), Flags::OVERFLOW | Flags::INEXACT);
+
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::NearestEven)
+            .with_overflow_mode(OverflowMode::Saturate);
+
+        let a = Value::from_comps(false, 135, 0, single.clone()); // 256.0
+
+        let res = conversion_with_context(a, half.clone(), &mut context);
+
+        assert_eq!(res.to_bits(), 0x5c00); // 256.0
+        assert!(context.flags().is_empty());
+
+        let a = Value::from_comps(false, 135, 0x700000, single.clone()); // 480.0
+
+        let res = conversion_with_context(a, Format::ocp_fp8_e4m3(), &mut context);
+
+        assert_eq!(res.to_bits(), 0x7e); // 448.0
+        assert_eq!(context.flags(), Flags::OVERFLOW | Flags::INEXACT);
+
+        // Infinities are kept when the target can represent them, but are clamped otherwise
+        let mut context = Context::new().with_overflow_mode(OverflowMode::Saturate);
+
+        let a = Value::from_comps(true, 255, 0, single.clone()); // -inf
+
+        let res = conversion_with_context(a, half.clone(), &mut context);
+
+        assert_eq!(res.to_bits(), 0xfc00); // -inf
+
+        let a = Value::from_comps(true, 255, 0, single.clone()); // -inf
+
+        let res = conversion_with_context(a, Format::ocp_fp8_e4m3(), &mut context);
+
+        assert_eq!(res.to_bits(), 0xfe); // -448.0
+        assert!(context.flags().is_empty());
+    }
+
+    #[test]
+    fn conversion_nan_inf() {
+        let single = Format::ieee754_single();
+        let half = Format::new(5, 10);
+
+        let a = Value::from_comps(true, 255, 1337, single.clone()); // any NaN
+
+        let res = conversion(a, half.clone());
+
+        assert_eq!(res.to_bits(), 0x7e00); // NaN
+
+        let a = Value::from_comps(true, 255, 0, single.clone()); // -inf
+
+        let res = conversion(a, half.clone());
+
+        assert_eq!(res.to_bits(), 0xfc00); // -inf
+
+        let a = Value::from_comps(true, 255, 0, single.clone()); // -inf
+
+        let res = conversion(a, Format::ocp_fp8_e4m3());
+
+        assert_eq!(res.to_bits(), 0xff); // NaN
+
+        let a = Value::from_comps(false, 255, 0, single.clone()); // +inf
+
+        let res = conversion(a, Format::ocp_fp4_e2m1());
+
+        assert_eq!(res.to_bits(), 0x7); // 6.0
+
+        let a = Value::from_comps(false, 255, 1, single.clone()); // any NaN
+
+        let res = conversion(a, Format::ocp_fp4_e2m1());
+
+        assert_eq!(res.to_bits(), 0x0); // 0.0
+    }
+
+    #[test]
+    fn conversion_nan_modes() {
+        let single = Format::ieee754_single();
+        let half = Format::new(5, 10);
+
+        let snan = 0xff812345; // -sNaN
+
+        for &(nan_mode, expected) in &[
+            (NanMode::Canonical, 0x7e00),
+            (NanMode::PropagateFirst, 0xfe09),
+            (NanMode::X86, 0xfe09),
+            (NanMode::Arm, 0xfe09),
+        ] {
+            let mut context = Context::new().with_nan_mode(nan_mode);
+
+            let res = conversion_with_context(Value::from_bits(snan, single.clone()), half.clone(), &mut context);
+
+            assert_eq!(res.to_bits(), expected);
+            assert_eq!(context.flags(), Flags::INVALID);
+
+            let mut context = Context::new().with_nan_mode(nan_mode);
+
+            let res = conversion_with_context(Value::from_bits(snan, single.clone()), Format::ocp_fp8_e4m3(), &mut context);
+
+            assert_eq!(res.to_bits(), if nan_mode == NanMode::Canonical { 0x7f } else { 0xff });
+            assert_eq!(context.flags(), Flags::INVALID);
+
+            let mut context = Context::new().with_nan_mode(nan_mode);
+
+            let res = conversion_with_context(Value::from_bits(snan, single.clone()), Format::ocp_fp4_e2m1(), &mut context);
+
+            assert_eq!(res.to_bits(), 0x0); // 0.0
+            assert_eq!(context.flags(), Flags::INVALID);
+        }
+    }
+}
@@ -0,0 +1,372 @@
+use crate::context::*;
+use crate::rounding::*;
+use crate::value::*;
+
+use super::*;
+
+use std::mem;
+
+// Position of the leading one of both terms before alignment, leaving room for the sum to carry out
+const NORMALIZED_MSB: u32 = 125;
+
+// Computes source1 * source2 + source3 with a single rounding
+pub fn fused_multiply_add(source1: Value, source2: Value, source3: Value) -> Value {
+    fused_multiply_add_with_context(source1, source2, source3, &mut Context::new())
+}
+
+pub fn fused_multiply_add_with_context(source1: Value, source2: Value, source3: Value, context: &mut Context) -> Value {
+    assert_eq!(source1.format, source2.format);
+    assert_eq!(source1.format, source3.format);
+
+    // Treat denormal input(s) as zero (if enabled)
+    let source1 = denormal_input(source1, context);
+    let source2 = denormal_input(source2, context);
+    let source3 = denormal_input(source3, context);
+
+    let format = &source1.format;
+
+    let product_sign = source1.sign ^ source2.sign;
+    let is_product_zero = source1.is_zero() || source2.is_zero();
+    let is_product_invalid = (source1.is_inf() && source2.is_zero()) || (source1.is_zero() && source2.is_inf());
+
+    // Propagate NaNs; inf * 0 is invalid even with a quiet NaN addend, and ARM answers it with the default NaN
+    if source1.is_nan() || source2.is_nan() || source3.is_nan() {
+        if is_product_invalid {
+            context.raise(Flags::INVALID);
+            if context.nan_mode == NanMode::Arm && !source3.is_signaling_nan() {
+                return Value::quiet_nan(format.clone());
+            }
+        }
+
+        let sources = match context.nan_mode {
+            NanMode::Arm => [&source3, &source1, &source2],
+            _ => [&source1, &source2, &source3],
+        };
+        return propagate_nan(&sources, format, context);
+    }
+
+    if source1.is_inf() || source2.is_inf() {
+        // inf * 0, or inf - inf
+        if is_product_invalid || (source3.is_inf() && source3.sign != product_sign) {
+            return invalid(format, context);
+        }
+
+        return Value::infinity(product_sign, format.clone());
+    }
+
+    if source3.is_inf() {
+        return Value::infinity(source3.sign, format.clone());
+    }
+
+    // Zeros of matching signs keep their sign; a zero product otherwise leaves the addend to be rounded on its own (which
+    // flushes it, if it's tiny)
+    if is_product_zero {
+        if source3.is_zero() {
+            return Value::zero(exact_zero_sum_sign(product_sign, source3.sign, context), format.clone());
+        }
+
+        let (source3_sig, source3_exp) = source3.decode_finite();
+        return round(source3.sign, source3_sig as u128, source3_exp, format, context);
+    }
+
+    // Decode full sigs
+    let (source1_sig, source1_exp) = source1.decode_finite();
+    let (source2_sig, source2_exp) = source2.decode_finite();
+    let (source3_sig, source3_exp) = source3.decode_finite();
+
+    // Calculate product (exactly)
+    let product = align_msb(product_sign, (source1_sig as u128) * (source2_sig as u128), source1_exp + source2_exp);
+
+    // A zero addend leaves the product to be rounded on its own
+    if source3.is_zero() {
+        let (sign, sig, exp) = product;
+        return round(sign, sig, exp, format, context);
+    }
+
+    let addend = align_msb(source3.sign, source3_sig as u128, source3_exp);
+
+    // Ensure term with greater magnitude is lhs
+    let (mut lhs, mut rhs) = (product, addend);
+    if lhs.2 < rhs.2 || (lhs.2 == rhs.2 && lhs.1 < rhs.1) {
+        mem::swap(&mut lhs, &mut rhs);
+    }
+    let (lhs_sign, lhs_sig, lhs_exp) = lhs;
+    let (rhs_sign, mut rhs_sig, rhs_exp) = rhs;
+
+    // Align rhs point (if applicable), collapsing bits shifted out of the datapath into a sticky bit
+    let shift_digits = (lhs_exp - rhs_exp) as u32;
+    if shift_digits > 0 {
+        let is_sticky = shift_digits >= 128 || (rhs_sig & ((1 << shift_digits) - 1)) != 0;
+        let shifted_sig = if shift_digits >= 128 { 0 } else { rhs_sig >> shift_digits };
+        rhs_sig = shifted_sig | (is_sticky as u128);
+    }
+
+    // Calculate sum
+    let sum_sig = if lhs_sign == rhs_sign { lhs_sig + rhs_sig } else { lhs_sig - rhs_sig };
+
+    // Exact cancellation
+    if sum_sig == 0 {
+        return Value::zero(exact_zero_sum_sign(lhs_sign, rhs_sign, context), format.clone());
+    }
+
+    // Normalize, round and pack sum
+    round(lhs_sign, sum_sig, lhs_exp, format, context)
+}
+
+// Shifts a nonzero term so its leading one sits at NORMALIZED_MSB, adjusting its exp to match
+fn align_msb(sign: bool, sig: u128, exp: i32) -> (bool, u128, i32) {
+    let shift = NORMALIZED_MSB - (127 - sig.leading_zeros());
+    (sign, sig << shift, exp - shift as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::flags::*;
+    use crate::format::*;
+
+    #[test]
+    fn fused_multiply_add_basic() {
+        let f = Format::ieee754_single();
+
+        let a = Value::from_comps(false, 128, 0, f.clone()); // 2.0
+        let b = Value::from_comps(false, 128, 1 << 22, f.clone()); // 3.0
+        let c = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+
+        let res = fused_multiply_add(a, b, c);
+
+        assert_eq!(res.to_bits(), 0x40e00000); // 7.0
+
+        let a = Value::from_comps(false, 128, 0, f.clone()); // 2.0
+        let b = Value::from_comps(false, 128, 1 << 22, f.clone()); // 3.0
+        let c = Value::from_comps(true, 130, 1 << 22, f.clone()); // -12.0
+
+        let res = fused_multiply_add(a, b, c);
+
+        assert_eq!(res.to_bits(), 0xc0c00000); // -6.0
+
+        let a = Value::from_comps(true, 126, 0, f.clone()); // -0.5
+        let b = Value::from_comps(false, 124, 0, f.clone()); // 0.125
+        let c = Value::from_comps(false, 0, 0, f.clone()); // 0.0
+
+        let res = fused_multiply_add(a, b, c);
+
+        assert_eq!(res.to_bits(), 0xbd800000); // -0.0625
+
+        let a = Value::from_comps(false, 0, 0, f.clone()); // 0.0
+        let b = Value::from_comps(false, 124, 0, f.clone()); // 0.125
+        let c = Value::from_comps(true, 128, 0x260000, f.clone()); // -2.59375
+
+        let res = fused_multiply_add(a, b, c);
+
+        assert_eq!(res.to_bits(), 0xc0260000); // -2.59375
+
+        let a = Value::from_comps(false, 128, 0, f.clone()); // 2.0
+        let b = Value::from_comps(true, 128, 1 << 22, f.clone()); // -3.0
+        let c = Value::from_comps(false, 129, 1 << 22, f.clone()); // 6.0
+
+        let res = fused_multiply_add(a, b, c);
+
+        assert_eq!(res.to_bits(), 0x00000000); // 0.0
+
+        // A denormal addend to a zero product is flushed, as in addition
+        let mut context = Context::new().with_denormals_are_zero(false);
+
+        let a = Value::from_comps(false, 0, 0, f.clone()); // 0.0
+        let b = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let c = Value::from_comps(false, 0, 1, f.clone()); // min subnormal
+
+        let res = fused_multiply_add_with_context(a, b, c, &mut context);
+
+        assert_eq!(res.to_bits(), 0x00000000); // 0.0 (result flushed to zero)
+        assert_eq!(context.flags(), Flags::UNDERFLOW | Flags::INEXACT);
+    }
+
+    #[test]
+    fn fused_multiply_add_single_rounding() {
+        let f = Format::ieee754_single();
+
+        // (1 + 2^-12)^2 - 1 = 2^-11 + 2^-24, which a separate multiply would truncate to 2^-11
+        let a = Value::from_comps(false, 127, 1 << 11, f.clone()); // 1 + 2^-12
+        let b = Value::from_comps(false, 127, 1 << 11, f.clone()); // 1 + 2^-12
+        let c = Value::from_comps(true, 127, 0, f.clone()); // -1.0
+
+        let res = fused_multiply_add(a, b, c);
+
+        assert_eq!(res.to_bits(), 0x3a000400); // 2^-11 + 2^-24
+
+        // Tiny addends still affect truncation
+        let a = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let b = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let c = Value::from_comps(true, 1, 0, f.clone()); // -2^-126
+
+        let res = fused_multiply_add(a, b, c);
+
+        assert_eq!(res.to_bits(), 0x3f7fffff); // 0.99999994
+    }
+
+    #[test]
+    fn fused_multiply_add_stochastic() {
+        let f = Format::ieee754_single();
+
+        // 1.0 * 1.0 + 2^-25 discards 0b01 followed by zeros
+        for &(random_bits, expected) in &[(0b00, 0x3f800000), (0b10, 0x3f800000), (0b11, 0x3f800001)] {
+            let mut context = Context::new()
+                .with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 2 })
+                .with_random_source(move |_| random_bits);
+
+            let a = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+            let b = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+            let c = Value::from_comps(false, 102, 0, f.clone()); // 2^-25
+
+            let res = fused_multiply_add_with_context(a, b, c, &mut context);
+
+            assert_eq!(res.to_bits(), expected);
+        }
+    }
+
+    #[test]
+    fn fused_multiply_add_saturate() {
+        let f = Format::ieee754_single();
+
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::NearestEven)
+            .with_overflow_mode(OverflowMode::Saturate);
+
+        let a = Value::from_comps(false, 254, 0, f.clone()); // 2^127
+        let b = Value::from_comps(false, 128, 0, f.clone()); // 2.0
+        let c = Value::from_comps(true, 254, 0, f.clone()); // -2^127
+
+        let res = fused_multiply_add_with_context(a, b, c, &mut context);
+
+        assert_eq!(res.to_bits(), 0x7f000000); // 2^127 (the product's overflow is only intermediate)
+        assert!(context.flags().is_empty());
+
+        let a = Value::from_comps(false, 254, 0, f.clone()); // 2^127
+        let b = Value::from_comps(false, 128, 0, f.clone()); // 2.0
+        let c = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+
+        let res = fused_multiply_add_with_context(a, b, c, &mut context);
+
+        assert_eq!(res.to_bits(), 0x7f7fffff); // +max value
+        assert_eq!(context.flags(), Flags::OVERFLOW | Flags::INEXACT);
+    }
+
+    #[test]
+    fn fused_multiply_add_daz_ftz() {
+        let f = Format::ieee754_single();
+
+        let a = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let b = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let c = Value::from_comps(true, 0, 1337, f.clone()); // any negative denormalized number
+
+        let res = fused_multiply_add(a, b, c);
+
+        assert_eq!(res.to_bits(), 0x3f800000); // 1.0
+
+        let a = Value::from_comps(false, 27, 0, f.clone()); // 2^-100
+        let b = Value::from_comps(false, 27, 0, f.clone()); // 2^-100
+        let c = Value::from_comps(false, 0, 0, f.clone()); // 0.0
+
+        let res = fused_multiply_add(a, b, c);
+
+        assert_eq!(res.to_bits(), 0x00000000); // 0.0
+
+        // A denormal addend to a zero product is flushed, as in addition
+        let mut context = Context::new().with_denormals_are_zero(false);
+
+        let a = Value::from_comps(false, 0, 0, f.clone()); // 0.0
+        let b = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let c = Value::from_comps(false, 0, 1, f.clone()); // min subnormal
+
+        let res = fused_multiply_add_with_context(a, b, c, &mut context);
+
+        assert_eq!(res.to_bits(), 0x00000000); // 0.0 (result flushed to zero)
+        assert_eq!(context.flags(), Flags::UNDERFLOW | Flags::INEXACT);
+    }
+
+    #[test]
+    fn fused_multiply_add_nan_inf() {
+        let f = Format::ieee754_single();
+
+        let a = Value::from_comps(false, 255, 1337, f.clone()); // any NaN
+        let b = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+        let c = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+
+        let res = fused_multiply_add(a, b, c);
+
+        assert_eq!(res.to_bits(), 0x7fc00000); // NaN
+
+        let a = Value::from_comps(false, 255, 0, f.clone()); // +inf
+        let b = Value::from_comps(false, 0, 0, f.clone()); // 0.0
+        let c = Value::from_comps(false, 127, 0, f.clone()); // 1.0
+
+        let res = fused_multiply_add(a, b, c);
+
+        assert_eq!(res.to_bits(), 0x7fc00000); // NaN
+
+        let a = Value::from_comps(false, 255, 0, f.clone()); // +inf
+        let b = Value::from_comps(true, 127, 0, f.clone()); // -1.0
+        let c = Value::from_comps(false, 255, 0, f.clone()); // +inf
+
+        let res = fused_multiply_add(a, b, c);
+
+        assert_eq!(res.to_bits(), 0x7fc00000); // NaN
+
+        let a = Value::from_comps(false, 255, 0, f.clone()); // +inf
+        let b = Value::from_comps(true, 127, 0, f.clone()); // -1.0
+        let c = Value::from_comps(true, 127, 0, f.clone()); // -1.0
+
+        let res = fused_multiply_add(a, b, c);
+
+        assert_eq!(res.to_bits(), 0xff800000); // -inf
+
+        let a = Value::from_comps(false, 254, 0, f.clone()); // 2^127
+        let b = Value::from_comps(false, 254, 0, f.clone()); // 2^127
+        let c = Value::from_comps(true, 255, 0, f.clone()); // -inf
+
+        let res = fused_multiply_add(a, b, c);
+
+        assert_eq!(res.to_bits(), 0xff800000); // -inf
+    }
+
+    #[test]
+    fn fused_multiply_add_nan_modes() {
+        let f = Format::ieee754_single();
+
+        let qnan1 = 0x7fc00001; // +qNaN
+        let qnan3 = 0xffc00003; // -qNaN
+        let snan2 = 0x7f800002; // +sNaN
+        let one = 0x3f800000; // 1.0
+        let zero = 0x00000000; // 0.0
+        let inf = 0x7f800000; // +inf
+
+        for &(nan_mode, q1_1_q3, q1_s2_q3, inf_0_q3) in &[
+            (NanMode::Canonical, 0x7fc00000, 0x7fc00000, 0x7fc00000),
+            (NanMode::PropagateFirst, 0x7fc00001, 0x7fc00001, 0xffc00003),
+            (NanMode::X86, 0x7fc00001, 0x7fc00001, 0xffc00003),
+            (NanMode::Arm, 0xffc00003, 0x7fc00002, 0x7fc00000),
+        ] {
+            let mut context = Context::new().with_nan_mode(nan_mode);
+
+            let res = fused_multiply_add_with_context(Value::from_bits(qnan1, f.clone()), Value::from_bits(one, f.clone()), Value::from_bits(qnan3, f.clone()), &mut context);
+
+            assert_eq!(res.to_bits(), q1_1_q3);
+            assert!(context.flags().is_empty());
+
+            let res = fused_multiply_add_with_context(Value::from_bits(qnan1, f.clone()), Value::from_bits(snan2, f.clone()), Value::from_bits(qnan3, f.clone()), &mut context);
+
+            assert_eq!(res.to_bits(), q1_s2_q3);
+            assert_eq!(context.flags(), Flags::INVALID);
+
+            context.clear_flags();
+
+            // inf * 0 is invalid even when the addend is a quiet NaN
+            let res = fused_multiply_add_with_context(Value::from_bits(inf, f.clone()), Value::from_bits(zero, f.clone()), Value::from_bits(qnan3, f.clone()), &mut context);
+
+            assert_eq!(res.to_bits(), inf_0_q3);
+            assert_eq!(context.flags(), Flags::INVALID);
+        }
+    }
+}
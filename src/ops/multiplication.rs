@@ -1,61 +1,125 @@
+use crate::context::*;
+use crate::format::*;
+use crate::rounding::*;
 use crate::value::*;
 
+use super::*;
+
 pub fn multiplication(source1: Value, source2: Value) -> Value {
+    multiplication_with_context(source1, source2, &mut Context::new())
+}
+
+pub fn multiplication_with_context(source1: Value, source2: Value, context: &mut Context) -> Value {
+    let decoded = match decode(source1, source2, context) {
+        Unpacked::Finite(decoded) => decoded,
+        Unpacked::Special(result) => return result,
+    };
+    let product = multiply(&decoded);
+
+    // Normalize, round and pack product
+    let normalized = normalize(product.sign, product.sig, product.exp, &product.format);
+    let rounded = round_normalized(&normalized, context);
+    pack(&rounded, context)
+}
+
+// Decode stage: finite, nonzero sources' full sigs and exps
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Decoded {
+    pub(crate) sign: bool,
+    pub(crate) lhs_sig: u32,
+    pub(crate) lhs_exp: i32,
+    pub(crate) rhs_sig: u32,
+    pub(crate) rhs_exp: i32,
+    pub(crate) format: Format,
+}
+
+impl Decoded {
+    // Sign of the product
+    pub fn sign(&self) -> bool {
+        self.sign
+    }
+
+    pub fn lhs_sig(&self) -> u32 {
+        self.lhs_sig
+    }
+
+    pub fn lhs_exp(&self) -> i32 {
+        self.lhs_exp
+    }
+
+    pub fn rhs_sig(&self) -> u32 {
+        self.rhs_sig
+    }
+
+    pub fn rhs_exp(&self) -> i32 {
+        self.rhs_exp
+    }
+}
+
+pub fn decode(source1: Value, source2: Value, context: &mut Context) -> Unpacked<Decoded> {
     assert_eq!(source1.format, source2.format);
 
-    // Treat denormal input(s) as zero
-    let source1 = flush_denormal_to_zero(source1);
-    let source2 = flush_denormal_to_zero(source2);
+    // Treat denormal input(s) as zero (if enabled)
+    let source1 = denormal_input(source1, context);
+    let source2 = denormal_input(source2, context);
 
     let format = &source1.format;
 
-    // Propagate (replace!) NaNs
-    let sig_quiet_bit = 1 << (format.num_sig_bits - 1);
-    let quiet_nan = Value::from_comps(false, format.exp_max(), sig_quiet_bit, format.clone());
-
+    // Propagate NaNs
     if source1.is_nan() || source2.is_nan() {
-        return quiet_nan;
+        return Unpacked::Special(propagate_nan(&[&source1, &source2], format, context));
     }
 
-    let product_sign = source1.sign ^ source2.sign;
+    let sign = source1.sign ^ source2.sign;
+
+    if source1.is_inf() || source2.is_inf() {
+        // inf * 0
+        if source1.is_zero() || source2.is_zero() {
+            return Unpacked::Special(invalid(format, context));
+        }
 
-    if source1.exp == 0 || source2.exp == 0 {
-        return Value::from_comps(product_sign, 0, 0, format.clone())
+        return Unpacked::Special(Value::infinity(sign, format.clone()));
     }
 
-    // TODO: Check for additional special cases/conditioning
+    if source1.is_zero() || source2.is_zero() {
+        return Unpacked::Special(Value::zero(sign, format.clone()));
+    }
 
     // Decode full sigs
-    let hidden_bit = 1 << format.num_sig_bits;
-    let source1_sig = hidden_bit | source1.sig;
-    let source2_sig = hidden_bit | source2.sig;
+    let (lhs_sig, lhs_exp) = source1.decode_finite();
+    let (rhs_sig, rhs_exp) = source2.decode_finite();
+    Unpacked::Finite(Decoded { sign, lhs_sig, lhs_exp, rhs_sig, rhs_exp, format: format.clone() })
+}
 
-    // Calculate product
-    let exp_bias = (1 << (format.num_exp_bits - 1)) - 1;
-    let mut product_exp = (source1.exp + source2.exp).wrapping_sub(exp_bias); // TODO: Handle overflow/underflow
-    let product_sig = (source1_sig as u64) * (source2_sig as u64);
+// Multiply stage: the exact (unnormalized) product, (-1)^sign * sig * 2^exp
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Product {
+    pub(crate) sign: bool,
+    pub(crate) sig: u128,
+    pub(crate) exp: i32,
+    pub(crate) format: Format,
+}
 
-    // Normalize product
-    let mut product_sig = (product_sig >> format.num_sig_bits) as u32;
-    let product_sig_leading_zeros = product_sig.leading_zeros() - (32 - (format.num_sig_bits + 2));
+impl Product {
+    pub fn sign(&self) -> bool {
+        self.sign
+    }
 
-    if product_sig_leading_zeros < 1 {
-        let product_sig_shift_right = 1 - product_sig_leading_zeros;
-        product_exp += product_sig_shift_right;
-        product_sig >>= product_sig_shift_right;
+    pub fn sig(&self) -> u128 {
+        self.sig
     }
 
-    // Remove hidden bit from product
-    let product_sig = (product_sig as u32) & ((1 << format.num_sig_bits) - 1);
-    Value::from_comps(product_sign, product_exp, product_sig, format.clone())
+    pub fn exp(&self) -> i32 {
+        self.exp
+    }
 }
 
-// TODO: dedupe
-fn flush_denormal_to_zero(value: Value) -> Value {
-    if value.exp == 0 {
-        Value::from_comps(value.sign, value.exp, 0, value.format)
-    } else {
-        value
+pub fn multiply(decoded: &Decoded) -> Product {
+    Product {
+        sign: decoded.sign,
+        sig: (decoded.lhs_sig as u128) * (decoded.rhs_sig as u128),
+        exp: decoded.lhs_exp + decoded.rhs_exp,
+        format: decoded.format.clone(),
     }
 }
 
@@ -63,7 +127,38 @@ fn flush_denormal_to_zero(value: Value) -> Value {
 mod tests {
     use super::*;
 
-    use crate::format::*;
+    use crate::flags::*;
+    use crate::iter::*;
+
+    #[test]
+    fn multiplication_stages() {
+        let f = Format::ieee754_single();
+        let mut context = Context::new();
+
+        let decoded = match decode(Value::from_bits(0x3fc00000, f.clone()), Value::from_bits(0xbfc00000, f.clone()), &mut context) {
+            Unpacked::Finite(decoded) => decoded,
+            Unpacked::Special(result) => panic!("Unexpected special result {:?}", result),
+        }; // 1.5 * -1.5
+
+        assert_eq!((decoded.sign(), decoded.lhs_sig(), decoded.lhs_exp(), decoded.rhs_sig(), decoded.rhs_exp()), (true, 0xc00000, -23, 0xc00000, -23));
+
+        let product = multiply(&decoded);
+
+        assert_eq!((product.sign(), product.sig(), product.exp()), (true, 0x900000000000, -46));
+
+        let normalized = normalize(product.sign(), product.sig(), product.exp(), &f);
+
+        assert_eq!((normalized.num_leading_zeros(), normalized.msb_exp(), normalized.lsb_exp()), (80, 1, -22));
+
+        let rounded = round_normalized(&normalized, &mut context);
+
+        assert_eq!(pack(&rounded, &mut context).to_bits(), 0xc0100000); // -2.25
+
+        match decode(Value::from_bits(0x7f800000, f.clone()), Value::from_bits(0x00000000, f.clone()), &mut context) {
+            Unpacked::Special(result) => assert!(result.is_nan()),
+            Unpacked::Finite(decoded) => panic!("Unexpected finite operands {:?}", decoded),
+        }
+    }
 
     #[test]
     fn multiplication_basic() {
@@ -133,6 +228,160 @@ mod tests {
         assert_eq!(res.to_bits(), 0x00000000); // 0.0
     }
 
+    #[test]
+    fn multiplication_custom_exp_bias() {
+        let f = Format::ieee754_single().with_exp_bias(100);
+
+        let a = Value::from_comps(false, 100, 0, f.clone()); // 1.0
+        let b = Value::from_comps(false, 101, 0, f.clone()); // 2.0
+
+        let res = multiplication(a, b);
+
+        assert_eq!(res.to_bits(), 0x32800000); // 2.0
+
+        let a = Value::from_comps(false, 101, 1 << 22, f.clone()); // 3.0
+        let b = Value::from_comps(true, 99, 0, f.clone()); // -0.5
+
+        let res = multiplication(a, b);
+
+        assert_eq!(res.to_bits(), 0xb2400000); // -1.5
+
+        let f = Format::new(4, 3).with_exp_bias(11);
+
+        let a = Value::from_comps(false, 11, 0, f.clone()); // 1.0
+        let b = Value::from_comps(false, 13, 0b100, f.clone()); // 6.0
+
+        let res = multiplication(a, b);
+
+        assert_eq!(res.to_bits(), 0x6c); // 6.0
+
+        let f = Format::new(4, 3).with_exp_bias(-2);
+
+        let a = Value::from_comps(false, 1, 0, f.clone()); // 8.0
+        let b = Value::from_comps(false, 2, 0, f.clone()); // 16.0
+
+        let res = multiplication(a, b);
+
+        assert_eq!(res.to_bits(), 0x28); // 128.0
+    }
+
+    #[test]
+    fn multiplication_truncation() {
+        let f = Format::ieee754_single();
+
+        let a = Value::from_comps(false, 127, 0x7fffff, f.clone()); // 1.99999988
+        let b = Value::from_comps(false, 127, 0x7fffff, f.clone()); // 1.99999988
+
+        let res = multiplication(a, b);
+
+        assert_eq!(res.to_bits(), 0x407ffffe); // 3.99999952
+
+        let a = Value::from_comps(false, 254, 0, f.clone()); // 2^127
+        let b = Value::from_comps(true, 128, 0, f.clone()); // -2.0
+
+        let res = multiplication(a, b);
+
+        assert_eq!(res.to_bits(), 0xff800000); // -inf
+
+        let a = Value::from_comps(false, 254, 0, f.clone()); // 2^127
+        let b = Value::from_comps(true, 128, 0, f.clone()); // -2.0
+
+        let res = multiplication_with_context(a, b, &mut Context::new().with_ieee_rounding(true));
+
+        assert_eq!(res.to_bits(), 0xff7fffff); // -max value (truncated)
+
+        let a = Value::from_comps(false, 27, 0, f.clone()); // 2^-100
+        let b = Value::from_comps(false, 27, 0, f.clone()); // 2^-100
+
+        let res = multiplication(a, b);
+
+        assert_eq!(res.to_bits(), 0x00000000); // denormal flushed to 0.0
+    }
+
+    #[test]
+    fn multiplication_stochastic() {
+        let f = Format::ieee754_single();
+
+        // 1.99999988^2 = 4 - 2^-21 + 2^-46, which lies 2^-24 ulp above 3.99999952
+        for &(random_bits, expected) in &[(0x000000, 0x407ffffe), (0xfffffe, 0x407ffffe), (0xffffff, 0x407fffff)] {
+            let mut context = Context::new()
+                .with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 24 })
+                .with_random_source(move |_| random_bits);
+
+            let a = Value::from_comps(false, 127, 0x7fffff, f.clone()); // 1.99999988
+            let b = Value::from_comps(false, 127, 0x7fffff, f.clone()); // 1.99999988
+
+            let res = multiplication_with_context(a, b, &mut context);
+
+            assert_eq!(res.to_bits(), expected);
+        }
+    }
+
+    #[test]
+    fn multiplication_saturate() {
+        let f = Format::ieee754_single();
+
+        let mut context = Context::new().with_rounding_mode(RoundingMode::NearestEven);
+
+        let a = Value::from_comps(false, 200, 0, f.clone()); // 2^73
+        let b = Value::from_comps(true, 200, 0, f.clone()); // -2^73
+
+        let res = multiplication_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0xff800000); // -inf
+        assert_eq!(context.flags(), Flags::OVERFLOW | Flags::INEXACT);
+
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::NearestEven)
+            .with_overflow_mode(OverflowMode::Saturate);
+
+        let a = Value::from_comps(false, 200, 0, f.clone()); // 2^73
+        let b = Value::from_comps(true, 200, 0, f.clone()); // -2^73
+
+        let res = multiplication_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0xff7fffff); // -max value
+        assert_eq!(context.flags(), Flags::OVERFLOW | Flags::INEXACT);
+
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::NearestEven)
+            .with_overflow_mode(OverflowMode::Saturate);
+
+        let a = Value::from_comps(false, 8, 0, Format::ocp_fp8_e4m3()); // 2.0
+        let b = Value::from_comps(false, 15, 0, Format::ocp_fp8_e4m3()); // 256.0
+
+        let res = multiplication_with_context(a, b, &mut context);
+
+        assert_eq!(res.to_bits(), 0x7e); // 448.0
+        assert_eq!(context.flags(), Flags::OVERFLOW | Flags::INEXACT);
+    }
+
+    #[test]
+    fn multiplication_inf() {
+        let f = Format::ieee754_single();
+
+        let a = Value::from_comps(false, 255, 0, f.clone()); // +inf
+        let b = Value::from_comps(true, 128, 0, f.clone()); // -2.0
+
+        let res = multiplication(a, b);
+
+        assert_eq!(res.to_bits(), 0xff800000); // -inf
+
+        let a = Value::from_comps(true, 255, 0, f.clone()); // -inf
+        let b = Value::from_comps(true, 255, 0, f.clone()); // -inf
+
+        let res = multiplication(a, b);
+
+        assert_eq!(res.to_bits(), 0x7f800000); // +inf
+
+        let a = Value::from_comps(false, 0, 0, f.clone()); // 0.0
+        let b = Value::from_comps(true, 255, 0, f.clone()); // -inf
+
+        let res = multiplication(a, b);
+
+        assert_eq!(res.to_bits(), 0x7fc00000); // NaN
+    }
+
     #[test]
     fn multiplication_daz_ftz() {
         let f = Format::ieee754_single();
@@ -240,4 +489,64 @@ mod tests {
 
         assert_eq!(res.to_bits(), 0x7fc00000); // NaN
     }
+
+    #[test]
+    fn multiplication_nan_modes() {
+        let f = Format::ieee754_single();
+
+        let qnan = 0x7fc00001; // +qNaN
+        let snan = 0xff800002; // -sNaN
+        let zero = 0x00000000; // 0.0
+        let inf = 0x7f800000; // +inf
+
+        for &(nan_mode, qnan_snan, snan_qnan, inf_zero) in &[
+            (NanMode::Canonical, 0x7fc00000, 0x7fc00000, 0x7fc00000),
+            (NanMode::PropagateFirst, 0x7fc00001, 0xffc00002, 0x7fc00000),
+            (NanMode::X86, 0x7fc00001, 0xffc00002, 0xffc00000),
+            (NanMode::Arm, 0xffc00002, 0xffc00002, 0x7fc00000),
+        ] {
+            let mut context = Context::new().with_nan_mode(nan_mode);
+
+            let res = multiplication_with_context(Value::from_bits(qnan, f.clone()), Value::from_bits(snan, f.clone()), &mut context);
+
+            assert_eq!(res.to_bits(), qnan_snan);
+            assert_eq!(context.flags(), Flags::INVALID);
+
+            let res = multiplication_with_context(Value::from_bits(snan, f.clone()), Value::from_bits(qnan, f.clone()), &mut context);
+
+            assert_eq!(res.to_bits(), snan_qnan);
+
+            context.clear_flags();
+
+            let res = multiplication_with_context(Value::from_bits(inf, f.clone()), Value::from_bits(zero, f.clone()), &mut context);
+
+            assert_eq!(res.to_bits(), inf_zero);
+            assert_eq!(context.flags(), Flags::INVALID);
+        }
+    }
+
+    #[test]
+    fn multiplication_exhaustive() {
+        for format in &[Format::ocp_fp8_e5m2(), Format::ocp_fp8_e4m3(), Format::ocp_fp6_e3m2(), Format::ocp_fp4_e2m1()] {
+            for &(rounding_mode, flush_to_zero) in &[(RoundingMode::TowardZero, true), (RoundingMode::NearestEven, false)] {
+                for (a, b) in format.value_pairs().with_filter(ValueFilter::Finite) {
+                    let mut expected_context = Context::new().with_rounding_mode(rounding_mode);
+                    expected_context.flush_to_zero = flush_to_zero;
+                    let mut context = Context::new().with_rounding_mode(rounding_mode);
+                    context.flush_to_zero = flush_to_zero;
+
+                    // Products of these narrow formats are exact in f64; denormal inputs are always treated as zero
+                    let exact = to_f64(&flush_denormal_to_zero(Value::from_bits(a.to_bits(), format.clone())))
+                        * to_f64(&flush_denormal_to_zero(Value::from_bits(b.to_bits(), format.clone())));
+                    let expected = round_f64(exact, format, &mut expected_context);
+
+                    let (a_bits, b_bits) = (a.to_bits(), b.to_bits());
+                    let res = multiplication_with_context(a, b, &mut context);
+
+                    assert_eq!(res.to_bits(), expected.to_bits(), "{} * {} ({:?})", a_bits, b_bits, format);
+                    assert_eq!(context.flags(), expected_context.flags(), "{} * {} ({:?})", a_bits, b_bits, format);
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,518 @@
+// Reference implementations of the ops, written for obviousness rather than speed: each finite result is computed
+// exactly as a big integer times a power of two, then rounded by a straightforward, separate rounding routine. Special
+// values follow the same rules as the ops, so every op can be checked against its oracle bit for bit (flags included).
+
+use crate::bignum::*;
+use crate::context::*;
+use crate::flags::*;
+use crate::format::*;
+use crate::ops::*;
+use crate::rounding::*;
+use crate::value::*;
+
+// (-1)^sign * mag * 2^exp
+pub(crate) struct Exact {
+    pub(crate) sign: bool,
+    pub(crate) mag: BigUint,
+    pub(crate) exp: i64,
+}
+
+impl Exact {
+    pub(crate) fn from_value(value: &Value) -> Exact {
+        let (sig, exp) = value.decode_finite();
+        Exact {
+            sign: value.sign,
+            mag: BigUint::from_u64(sig as u64),
+            exp: exp as i64,
+        }
+    }
+
+    pub(crate) fn mul(&self, other: &Exact) -> Exact {
+        Exact {
+            sign: self.sign ^ other.sign,
+            mag: self.mag.mul(&other.mag),
+            exp: self.exp + other.exp,
+        }
+    }
+
+    pub(crate) fn add(&self, other: &Exact) -> Exact {
+        // Bring both to the smaller exponent
+        let exp = self.exp.min(other.exp);
+        let mut lhs = self.mag.clone();
+        lhs.mul_pow2((self.exp - exp) as u32);
+        let mut rhs = other.mag.clone();
+        rhs.mul_pow2((other.exp - exp) as u32);
+
+        if self.sign == other.sign {
+            return Exact { sign: self.sign, mag: lhs.add(&rhs), exp };
+        }
+        if lhs >= rhs {
+            lhs.sub_assign(&rhs);
+            Exact { sign: self.sign, mag: lhs, exp }
+        } else {
+            rhs.sub_assign(&lhs);
+            Exact { sign: other.sign, mag: rhs, exp }
+        }
+    }
+}
+
+pub fn addition(source1: &Value, source2: &Value, context: &mut Context) -> Value {
+    assert_eq!(source1.format, source2.format);
+
+    let source1 = denormal_as_zero(source1, context);
+    let source2 = denormal_as_zero(source2, context);
+    let format = &source1.format;
+
+    if source1.is_nan() || source2.is_nan() {
+        return propagate_nan(&[&source1, &source2], format, context);
+    }
+    if source1.is_inf() && source2.is_inf() && source1.sign != source2.sign {
+        return invalid(format, context);
+    }
+    if source1.is_inf() || source2.is_inf() {
+        let sign = if source1.is_inf() { source1.sign } else { source2.sign };
+        return Value::infinity(sign, format.clone());
+    }
+
+    let sum = Exact::from_value(&source1).add(&Exact::from_value(&source2));
+    if sum.mag.is_zero() {
+        return Value::zero(zero_sum_sign(source1.sign, source2.sign, context), format.clone());
+    }
+    round_exact(&sum, format, context)
+}
+
+pub fn multiplication(source1: &Value, source2: &Value, context: &mut Context) -> Value {
+    assert_eq!(source1.format, source2.format);
+
+    let source1 = denormal_as_zero(source1, context);
+    let source2 = denormal_as_zero(source2, context);
+    let format = &source1.format;
+    let sign = source1.sign ^ source2.sign;
+
+    if source1.is_nan() || source2.is_nan() {
+        return propagate_nan(&[&source1, &source2], format, context);
+    }
+    if (source1.is_inf() && source2.is_zero()) || (source1.is_zero() && source2.is_inf()) {
+        return invalid(format, context);
+    }
+    if source1.is_inf() || source2.is_inf() {
+        return Value::infinity(sign, format.clone());
+    }
+
+    let product = Exact::from_value(&source1).mul(&Exact::from_value(&source2));
+    if product.mag.is_zero() {
+        return Value::zero(sign, format.clone());
+    }
+    round_exact(&product, format, context)
+}
+
+pub fn fused_multiply_add(source1: &Value, source2: &Value, source3: &Value, context: &mut Context) -> Value {
+    assert_eq!(source1.format, source2.format);
+    assert_eq!(source1.format, source3.format);
+
+    let source1 = denormal_as_zero(source1, context);
+    let source2 = denormal_as_zero(source2, context);
+    let source3 = denormal_as_zero(source3, context);
+    let format = &source1.format;
+    let product_sign = source1.sign ^ source2.sign;
+    let is_product_invalid = (source1.is_inf() && source2.is_zero()) || (source1.is_zero() && source2.is_inf());
+    let is_product_inf = source1.is_inf() || source2.is_inf();
+
+    if source1.is_nan() || source2.is_nan() || source3.is_nan() {
+        if is_product_invalid {
+            context.raise(Flags::INVALID);
+            if context.nan_mode == NanMode::Arm && !source3.is_signaling_nan() {
+                return Value::quiet_nan(format.clone());
+            }
+        }
+        return match context.nan_mode {
+            NanMode::Arm => propagate_nan(&[&source3, &source1, &source2], format, context),
+            _ => propagate_nan(&[&source1, &source2, &source3], format, context),
+        };
+    }
+    if is_product_invalid || (is_product_inf && source3.is_inf() && source3.sign != product_sign) {
+        return invalid(format, context);
+    }
+    if is_product_inf {
+        return Value::infinity(product_sign, format.clone());
+    }
+    if source3.is_inf() {
+        return Value::infinity(source3.sign, format.clone());
+    }
+
+    let product = Exact::from_value(&source1).mul(&Exact::from_value(&source2));
+    let sum = product.add(&Exact::from_value(&source3));
+    if sum.mag.is_zero() {
+        return Value::zero(zero_sum_sign(product_sign, source3.sign, context), format.clone());
+    }
+    round_exact(&sum, format, context)
+}
+
+pub fn conversion(source: &Value, format: &Format, context: &mut Context) -> Value {
+    let source = denormal_as_zero(source, context);
+
+    if source.is_nan() {
+        if format.special_encoding == SpecialEncoding::FiniteOnly {
+            if source.is_signaling_nan() {
+                context.raise(Flags::INVALID);
+            }
+            return Value::zero(false, format.clone());
+        }
+        return propagate_nan(&[&source], format, context);
+    }
+    if source.is_inf() {
+        if context.overflow_mode == OverflowMode::Saturate && format.special_encoding != SpecialEncoding::Ieee {
+            return Value::max_finite(source.sign, format.clone());
+        }
+        return Value::infinity(source.sign, format.clone());
+    }
+    if source.is_zero() {
+        return Value::zero(source.sign, format.clone());
+    }
+
+    round_exact(&Exact::from_value(&source), format, context)
+}
+
+// Only zeros of matching signs keep their sign; rounding toward negative cancels to -0, and everything else to +0
+fn zero_sum_sign(lhs_sign: bool, rhs_sign: bool, context: &Context) -> bool {
+    if lhs_sign == rhs_sign {
+        lhs_sign
+    } else {
+        context.rounding_mode == RoundingMode::TowardNegative
+    }
+}
+
+fn denormal_as_zero(value: &Value, context: &Context) -> Value {
+    if value.is_subnormal() && context.denormals_are_zero {
+        Value::zero(value.sign, value.format.clone())
+    } else {
+        Value::from_comps(value.sign, value.exp, value.sig, value.format.clone())
+    }
+}
+
+// Rounds a nonzero exact value into format
+fn round_exact(x: &Exact, format: &Format, context: &mut Context) -> Value {
+    let num_sig_bits = format.num_sig_bits as i64;
+
+    let msb_exp = x.exp + x.mag.bit_len() as i64 - 1;
+    let min_normal_exp = format.min_normal_exp() as i64;
+    let is_tiny = msb_exp < min_normal_exp;
+    if is_tiny && context.flush_to_zero && context.tininess_mode == TininessMode::BeforeRounding {
+        context.raise(Flags::UNDERFLOW | Flags::INEXACT);
+        return Value::zero(x.sign, format.clone());
+    }
+
+    // Exact results still draw, to stay in lockstep with the ops
+    let random_bits = match context.rounding_mode {
+        RoundingMode::Stochastic { num_random_bits } => context.next_random_bits(num_random_bits) as u64,
+        _ => 0,
+    };
+
+    // Rounds to an integer multiple of 2^lsb_exp, also returning whether that was inexact
+    let round_to = |lsb_exp: i64| -> (u64, bool) {
+        if lsb_exp <= x.exp {
+            let mut kept = x.mag.clone();
+            kept.mul_pow2((x.exp - lsb_exp) as u32);
+            return (kept.to_u64(), false);
+        }
+
+        // Split into the part that's kept and the part that's discarded (as a fraction of the lsb,
+        // discarded / 2^num_discarded_bits). Fractions of an lsb below 2^-64 can't be distinguished by any rounding
+        // mode (stochastic rounding looks at most 32 bits deep), so cap the shift.
+        let num_discarded_bits = (lsb_exp - x.exp).min(x.mag.bit_len() as i64 + 64) as u32;
+        let kept = x.mag.shr(num_discarded_bits).to_u64();
+        let discarded = x.mag.low_bits(num_discarded_bits);
+        if discarded.is_zero() {
+            return (kept, false);
+        }
+
+        let mut half = BigUint::from_u64(1);
+        half.mul_pow2(num_discarded_bits - 1);
+        let round_up = match context.rounding_mode {
+            RoundingMode::NearestEven => discarded > half || (discarded == half && kept % 2 == 1),
+            RoundingMode::NearestAway => discarded >= half,
+            RoundingMode::TowardZero => false,
+            RoundingMode::TowardPositive => !x.sign,
+            RoundingMode::TowardNegative => x.sign,
+            RoundingMode::Stochastic { num_random_bits } => {
+                // Round up iff the top num_random_bits bits of the discarded fraction plus the random bits carry
+                let mut top = discarded.clone();
+                top.mul_pow2(num_random_bits);
+                let top = top.shr(num_discarded_bits).to_u64();
+                top + random_bits >= 1 << num_random_bits
+            }
+        };
+        (kept + round_up as u64, true)
+    };
+
+    // Tiny after rounding means still below the smallest normal when rounded to full precision at any exponent
+    let is_tiny = is_tiny
+        && match context.tininess_mode {
+            TininessMode::BeforeRounding => true,
+            TininessMode::AfterRounding => {
+                let (kept, _) = round_to(msb_exp - num_sig_bits);
+                msb_exp + ((kept >> (num_sig_bits + 1)) as i64) < min_normal_exp
+            }
+        };
+    if is_tiny && context.flush_to_zero {
+        context.raise(Flags::UNDERFLOW | Flags::INEXACT);
+        return Value::zero(x.sign, format.clone());
+    }
+
+    // Denormals share the smallest normal's lsb
+    let mut lsb_exp = msb_exp.max(min_normal_exp) - num_sig_bits;
+    let (mut kept, is_inexact) = round_to(lsb_exp);
+
+    // A carry out of the sig bumps the exponent
+    if kept == 1 << (num_sig_bits + 1) {
+        kept >>= 1;
+        lsb_exp += 1;
+    }
+
+    if is_inexact {
+        context.raise(Flags::INEXACT);
+        if is_tiny {
+            context.raise(Flags::UNDERFLOW);
+        }
+    }
+
+    // Subnormals (no hidden bit) have a zero exponent field
+    let biased_exp = if kept >> num_sig_bits == 0 { 0 } else { lsb_exp + num_sig_bits + format.exp_bias as i64 };
+    let sig = (kept as u32) & format.sig_mask();
+
+    let (max_exp, max_sig) = format.max_finite_comps();
+    if biased_exp > max_exp as i64 || (biased_exp == max_exp as i64 && sig > max_sig) {
+        context.raise(Flags::OVERFLOW | Flags::INEXACT);
+
+        // Max finite when saturating, or when rounding toward zero in the direction of the result's sign
+        let is_max_finite = context.overflow_mode == OverflowMode::Saturate
+            || context.rounding_mode == RoundingMode::TowardZero
+            || (context.rounding_mode == RoundingMode::TowardPositive && x.sign)
+            || (context.rounding_mode == RoundingMode::TowardNegative && !x.sign);
+        return if is_max_finite { Value::max_finite(x.sign, format.clone()) } else { Value::infinity(x.sign, format.clone()) };
+    }
+
+    Value::from_comps(x.sign, biased_exp as u32, sig, format.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ops;
+
+    const NAN_MODES: [NanMode; 4] = [NanMode::Canonical, NanMode::PropagateFirst, NanMode::X86, NanMode::Arm];
+
+    fn contexts(nan_mode: NanMode) -> Vec<Context> {
+        let rounding_modes = [
+            RoundingMode::NearestEven,
+            RoundingMode::NearestAway,
+            RoundingMode::TowardZero,
+            RoundingMode::TowardPositive,
+            RoundingMode::TowardNegative,
+            RoundingMode::Stochastic { num_random_bits: 5 },
+        ];
+        // (overflow mode, flush to zero, denormals are zero, tininess mode), from the hardware default to IEEE
+        let configurations = [
+            (OverflowMode::Ieee, true, true, TininessMode::BeforeRounding),
+            (OverflowMode::Ieee, false, false, TininessMode::AfterRounding),
+            (OverflowMode::Saturate, true, false, TininessMode::AfterRounding),
+            (OverflowMode::Saturate, false, true, TininessMode::BeforeRounding),
+        ];
+
+        let mut contexts = Vec::new();
+        for &rounding_mode in &rounding_modes {
+            for &(overflow_mode, flush_to_zero, denormals_are_zero, tininess_mode) in &configurations {
+                // Same sequence of random bits for both the op and its oracle
+                let mut state = 0x9e3779b9u32;
+                let context = Context::new()
+                    .with_rounding_mode(rounding_mode)
+                    .with_random_source(move |_| {
+                        state ^= state << 13;
+                        state ^= state >> 17;
+                        state ^= state << 5;
+                        state
+                    })
+                    .with_overflow_mode(overflow_mode)
+                    .with_flush_to_zero(flush_to_zero)
+                    .with_denormals_are_zero(denormals_are_zero)
+                    .with_tininess_mode(tininess_mode)
+                    .with_nan_mode(nan_mode)
+                    .with_ieee_rounding(true);
+                contexts.push(context);
+            }
+        }
+        contexts
+    }
+
+    fn copy(value: &Value) -> Value {
+        Value::from_bits(value.to_bits(), value.format.clone())
+    }
+
+    fn check_binary_op(
+        format: &Format,
+        pairs: impl Iterator<Item = (Value, Value)>,
+        op: fn(Value, Value, &mut Context) -> Value,
+        oracle: fn(&Value, &Value, &mut Context) -> Value,
+        nan_mode: NanMode,
+    ) {
+        let mut op_contexts = contexts(nan_mode);
+        let mut oracle_contexts = contexts(nan_mode);
+        for (a, b) in pairs {
+            for (op_context, oracle_context) in op_contexts.iter_mut().zip(oracle_contexts.iter_mut()) {
+                op_context.clear_flags();
+                oracle_context.clear_flags();
+
+                let expected = oracle(&a, &b, oracle_context);
+                let res = op(copy(&a), copy(&b), op_context);
+
+                assert_eq!(res.to_bits(), expected.to_bits(), "{:?}, {:?} in {} ({:?})", a, b, format, op_context.rounding_mode());
+                assert_eq!(op_context.flags(), oracle_context.flags(), "{:?}, {:?} in {} ({:?})", a, b, format, op_context.rounding_mode());
+            }
+        }
+    }
+
+    #[test]
+    fn addition_exhaustive() {
+        for format in &[Format::ocp_fp8_e4m3(), Format::ocp_fp6_e3m2(), Format::new(3, 3).with_exp_bias(-2)] {
+            check_binary_op(format, format.value_pairs(), ops::addition::addition_with_context, addition, NanMode::Canonical);
+
+            // NaN modes only matter for special values
+            for &nan_mode in &NAN_MODES[1..] {
+                let pairs = format.value_pairs().filter(|(a, b)| !a.is_finite() || !b.is_finite());
+                check_binary_op(format, pairs, ops::addition::addition_with_context, addition, nan_mode);
+            }
+        }
+    }
+
+    #[test]
+    fn multiplication_exhaustive() {
+        for format in &[Format::ocp_fp8_e4m3(), Format::ocp_fp6_e2m3(), Format::new(3, 3).with_exp_bias(6)] {
+            check_binary_op(format, format.value_pairs(), ops::multiplication::multiplication_with_context, multiplication, NanMode::Canonical);
+
+            for &nan_mode in &NAN_MODES[1..] {
+                let pairs = format.value_pairs().filter(|(a, b)| !a.is_finite() || !b.is_finite());
+                check_binary_op(format, pairs, ops::multiplication::multiplication_with_context, multiplication, nan_mode);
+            }
+        }
+    }
+
+    #[test]
+    fn addition_multiplication_sampled() {
+        // Shards of the wider formats' pair space, spread across it
+        for format in &[Format::new(5, 10), Format::new(8, 7), Format::new(4, 11).with_exp_bias(3)] {
+            let num_shards = 1 << 16;
+            for shard_index in (0..num_shards).step_by(4099) {
+                let pairs = || format.value_pairs().with_shard(shard_index, num_shards).step_by(257);
+                for &nan_mode in &[NanMode::Canonical, NanMode::Arm] {
+                    let mut op_contexts = contexts(nan_mode);
+                    let mut oracle_contexts = contexts(nan_mode);
+                    for (a, b) in pairs() {
+                        for (op_context, oracle_context) in op_contexts.iter_mut().zip(oracle_contexts.iter_mut()) {
+                            op_context.clear_flags();
+                            oracle_context.clear_flags();
+
+                            let expected = addition(&a, &b, oracle_context);
+                            let res = ops::addition::addition_with_context(copy(&a), copy(&b), op_context);
+
+                            assert_eq!(res.to_bits(), expected.to_bits(), "{:?} + {:?}", a, b);
+                            assert_eq!(op_context.flags(), oracle_context.flags(), "{:?} + {:?}", a, b);
+
+                            let expected = multiplication(&a, &b, oracle_context);
+                            let res = ops::multiplication::multiplication_with_context(copy(&a), copy(&b), op_context);
+
+                            assert_eq!(res.to_bits(), expected.to_bits(), "{:?} * {:?}", a, b);
+                            assert_eq!(op_context.flags(), oracle_context.flags(), "{:?} * {:?}", a, b);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn fused_multiply_add_sampled() {
+        for format in &[Format::ocp_fp8_e5m2(), Format::ocp_fp8_e4m3(), Format::ocp_fp6_e3m2(), Format::new(5, 10)] {
+            let values = format.values().collect::<Vec<_>>();
+            for &nan_mode in &NAN_MODES {
+                let mut op_contexts = contexts(nan_mode);
+                let mut oracle_contexts = contexts(nan_mode);
+                let mut state = 0x2545f491u32;
+                for _ in 0..2000 {
+                    let mut pick = || {
+                        state ^= state << 13;
+                        state ^= state >> 17;
+                        state ^= state << 5;
+                        &values[state as usize % values.len()]
+                    };
+                    let (a, b, c) = (pick(), pick(), pick());
+                    for (op_context, oracle_context) in op_contexts.iter_mut().zip(oracle_contexts.iter_mut()) {
+                        op_context.clear_flags();
+                        oracle_context.clear_flags();
+
+                        let expected = fused_multiply_add(a, b, c, oracle_context);
+                        let res = ops::fused_multiply_add::fused_multiply_add_with_context(copy(a), copy(b), copy(c), op_context);
+
+                        assert_eq!(res.to_bits(), expected.to_bits(), "{:?} * {:?} + {:?}", a, b, c);
+                        assert_eq!(op_context.flags(), oracle_context.flags(), "{:?} * {:?} + {:?}", a, b, c);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn conversion_sampled() {
+        let targets = [
+            Format::ieee754_single(),
+            Format::new(5, 10),
+            Format::ocp_fp8_e5m2(),
+            Format::ocp_fp8_e4m3(),
+            Format::ocp_fp6_e3m2(),
+            Format::ocp_fp4_e2m1(),
+            Format::new(8, 7).with_exp_bias(100),
+        ];
+        for source_format in &[Format::new(5, 10), Format::new(8, 7)] {
+            for target in &targets {
+                for &nan_mode in &NAN_MODES {
+                    let mut op_contexts = contexts(nan_mode);
+                    let mut oracle_contexts = contexts(nan_mode);
+                    for a in source_format.values().step_by(if nan_mode == NanMode::Canonical { 11 } else { 127 }) {
+                        for (op_context, oracle_context) in op_contexts.iter_mut().zip(oracle_contexts.iter_mut()) {
+                            op_context.clear_flags();
+                            oracle_context.clear_flags();
+
+                            let expected = conversion(&a, target, oracle_context);
+                            let res = ops::conversion::conversion_with_context(copy(&a), target.clone(), op_context);
+
+                            assert_eq!(res.to_bits(), expected.to_bits(), "{:?} to {}", a, target);
+                            assert_eq!(op_context.flags(), oracle_context.flags(), "{:?} to {}", a, target);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn round_exact() {
+        let f = Format::ieee754_single();
+        let mut context = Context::new().with_rounding_mode(RoundingMode::NearestEven);
+        context.flush_to_zero = false;
+
+        // 1 + 2^-24 ties to even, 1 + 3 * 2^-24 ties up
+        let x = Exact { sign: false, mag: BigUint::from_u64((1 << 24) + 1), exp: -24 };
+
+        assert_eq!(super::round_exact(&x, &f, &mut context).to_bits(), 0x3f800000);
+
+        let x = Exact { sign: false, mag: BigUint::from_u64((1 << 24) + 3), exp: -24 };
+
+        assert_eq!(super::round_exact(&x, &f, &mut context).to_bits(), 0x3f800002);
+
+        // Far below the smallest subnormal
+        let x = Exact { sign: true, mag: BigUint::from_u64(1), exp: -100000 };
+
+        assert_eq!(super::round_exact(&x, &f, &mut context).to_bits(), 0x80000000);
+        assert_eq!(context.flags(), Flags::INEXACT | Flags::UNDERFLOW);
+    }
+}
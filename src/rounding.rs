@@ -0,0 +1,779 @@
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::context::*;
+use crate::flags::*;
+use crate::format::*;
+use crate::value::*;
+
+pub const MAX_NUM_RANDOM_BITS: u32 = 32;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RoundingMode {
+    NearestEven,
+    // Ties round away from zero
+    NearestAway,
+    TowardZero,
+    TowardPositive,
+    TowardNegative,
+    // Rounds up with probability proportional to the top num_random_bits discarded bits
+    Stochastic { num_random_bits: u32 },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum OverflowMode {
+    // Overflow goes to infinity, or to the max value when rounding toward zero
+    Ieee,
+    // Overflow always clamps to the max value
+    Saturate,
+}
+
+// When a result counts as tiny, for the underflow flag and flush to zero
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TininessMode {
+    // The exact result lies below the smallest normal (as on ARM and RISC-V)
+    BeforeRounding,
+    // The result rounded to the format's precision, but with an unbounded exponent range, lies below the smallest
+    // normal (as on x86)
+    AfterRounding,
+}
+
+// A name that doesn't name a mode of the kind being parsed
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseModeError {
+    InvalidRoundingMode(String),
+    InvalidTininessMode(String),
+    InvalidOverflowMode(String),
+    InvalidNanMode(String),
+}
+
+impl fmt::Display for ParseModeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseModeError::InvalidRoundingMode(name) => write!(
+                f,
+                "Requested rounding mode \"{}\" is not valid; expected eg. \"nearest_even\", \"toward_zero\" or \"stochastic8\".",
+                name
+            ),
+            ParseModeError::InvalidTininessMode(name) => {
+                write!(f, "Requested tininess mode \"{}\" is not valid; expected \"before_rounding\" or \"after_rounding\".", name)
+            }
+            ParseModeError::InvalidOverflowMode(name) => {
+                write!(f, "Requested overflow mode \"{}\" is not valid; expected \"ieee\" or \"saturate\".", name)
+            }
+            ParseModeError::InvalidNanMode(name) => write!(
+                f,
+                "Requested NaN mode \"{}\" is not valid; expected \"canonical\", \"propagate_first\", \"x86\" or \"arm\".",
+                name
+            ),
+        }
+    }
+}
+
+impl error::Error for ParseModeError {}
+
+// Mode names, as in traces and the command-line calculator's options, eg. "nearest_even" or "stochastic8"
+impl fmt::Display for RoundingMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RoundingMode::NearestEven => write!(f, "nearest_even"),
+            RoundingMode::NearestAway => write!(f, "nearest_away"),
+            RoundingMode::TowardZero => write!(f, "toward_zero"),
+            RoundingMode::TowardPositive => write!(f, "toward_positive"),
+            RoundingMode::TowardNegative => write!(f, "toward_negative"),
+            RoundingMode::Stochastic { num_random_bits } => write!(f, "stochastic{}", num_random_bits),
+        }
+    }
+}
+
+impl FromStr for RoundingMode {
+    type Err = ParseModeError;
+
+    fn from_str(s: &str) -> Result<RoundingMode, ParseModeError> {
+        match s {
+            "nearest_even" => Ok(RoundingMode::NearestEven),
+            "nearest_away" => Ok(RoundingMode::NearestAway),
+            "toward_zero" => Ok(RoundingMode::TowardZero),
+            "toward_positive" => Ok(RoundingMode::TowardPositive),
+            "toward_negative" => Ok(RoundingMode::TowardNegative),
+            _ => match s.strip_prefix("stochastic").and_then(|digits| digits.parse().ok()) {
+                Some(num_random_bits) if num_random_bits != 0 && num_random_bits <= MAX_NUM_RANDOM_BITS => {
+                    Ok(RoundingMode::Stochastic { num_random_bits })
+                }
+                _ => Err(ParseModeError::InvalidRoundingMode(s.to_string())),
+            },
+        }
+    }
+}
+
+impl fmt::Display for OverflowMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OverflowMode::Ieee => write!(f, "ieee"),
+            OverflowMode::Saturate => write!(f, "saturate"),
+        }
+    }
+}
+
+impl FromStr for OverflowMode {
+    type Err = ParseModeError;
+
+    fn from_str(s: &str) -> Result<OverflowMode, ParseModeError> {
+        match s {
+            "ieee" => Ok(OverflowMode::Ieee),
+            "saturate" => Ok(OverflowMode::Saturate),
+            _ => Err(ParseModeError::InvalidOverflowMode(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for TininessMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TininessMode::BeforeRounding => write!(f, "before_rounding"),
+            TininessMode::AfterRounding => write!(f, "after_rounding"),
+        }
+    }
+}
+
+impl FromStr for TininessMode {
+    type Err = ParseModeError;
+
+    fn from_str(s: &str) -> Result<TininessMode, ParseModeError> {
+        match s {
+            "before_rounding" => Ok(TininessMode::BeforeRounding),
+            "after_rounding" => Ok(TininessMode::AfterRounding),
+            _ => Err(ParseModeError::InvalidTininessMode(s.to_string())),
+        }
+    }
+}
+
+// Supplies random bits for stochastic rounding; one draw is made per rounded result
+pub trait RandomSource {
+    // Returns num_bits random bits in the low bits of the result
+    fn random_bits(&mut self, num_bits: u32) -> u32;
+}
+
+impl<F: FnMut(u32) -> u32> RandomSource for F {
+    fn random_bits(&mut self, num_bits: u32) -> u32 {
+        self(num_bits)
+    }
+}
+
+// Shifts the magnitude sig (of a value with the given sign) right by shift bits, rounding the discarded bits according to
+// mode
+pub(crate) fn shift_right_rounded(sign: bool, sig: u128, shift: u32, mode: RoundingMode, random_bits: u32) -> u128 {
+    if shift == 0 {
+        return sig;
+    }
+
+    let truncated = if shift >= 128 { 0 } else { sig >> shift };
+    let round_bit = shift <= 128 && ((sig >> (shift - 1)) & 1) != 0;
+    let sticky = shift > 128 || (sig & ((1 << (shift - 1)) - 1)) != 0;
+
+    let increment = match mode {
+        RoundingMode::NearestEven => round_bit && (sticky || (truncated & 1) != 0),
+        RoundingMode::NearestAway => round_bit,
+        RoundingMode::TowardZero => false,
+        RoundingMode::TowardPositive => !sign && (round_bit || sticky),
+        RoundingMode::TowardNegative => sign && (round_bit || sticky),
+        RoundingMode::Stochastic { num_random_bits } => {
+            // Bits below the top num_random_bits discarded bits don't contribute
+            let discarded = if shift >= num_random_bits {
+                let discarded_shift = shift - num_random_bits;
+                if discarded_shift >= 128 { 0 } else { (sig >> discarded_shift) & ((1 << num_random_bits) - 1) }
+            } else {
+                (sig & ((1 << shift) - 1)) << (num_random_bits - shift)
+            };
+            discarded + random_bits as u128 >= 1 << num_random_bits
+        }
+    };
+
+    if increment {
+        truncated + 1
+    } else {
+        truncated
+    }
+}
+
+// Rounds the exact value (-1)^sign * sig * 2^exp into format, as the normalize, round and pack stages
+pub(crate) fn round(sign: bool, sig: u128, exp: i32, format: &Format, context: &mut Context) -> Value {
+    let normalized = normalize(sign, sig, exp, format);
+    let rounded = round_normalized(&normalized, context);
+    pack(&rounded, context)
+}
+
+// Normalize stage: locates the exact value's leading one, and the result's lsb
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Normalized {
+    pub(crate) sign: bool,
+    pub(crate) sig: u128,
+    pub(crate) exp: i32,
+    pub(crate) num_leading_zeros: u32,
+    pub(crate) lsb_exp: i32,
+    pub(crate) is_tiny: bool,
+    pub(crate) format: Format,
+}
+
+impl Normalized {
+    pub fn sign(&self) -> bool {
+        self.sign
+    }
+
+    // The exact value is (-1)^sign * sig * 2^exp
+    pub fn sig(&self) -> u128 {
+        self.sig
+    }
+
+    pub fn exp(&self) -> i32 {
+        self.exp
+    }
+
+    // Leading zeros of sig, as a 128-bit value
+    pub fn num_leading_zeros(&self) -> u32 {
+        self.num_leading_zeros
+    }
+
+    // Unbiased exponent of the leading one
+    pub fn msb_exp(&self) -> i32 {
+        self.exp + (127 - self.num_leading_zeros as i32)
+    }
+
+    // Unbiased exponent of the result's lsb (denormals share the smallest normal's lsb)
+    pub fn lsb_exp(&self) -> i32 {
+        self.lsb_exp
+    }
+
+    // Whether the exact value lies below the smallest normal (ie. tininess before rounding)
+    pub fn is_tiny(&self) -> bool {
+        self.is_tiny
+    }
+}
+
+pub fn normalize(sign: bool, sig: u128, exp: i32, format: &Format) -> Normalized {
+    let num_leading_zeros = sig.leading_zeros();
+    let msb_exp = exp + (127 - num_leading_zeros as i32);
+    let min_normal_exp = format.min_normal_exp();
+
+    Normalized {
+        sign,
+        sig,
+        exp,
+        num_leading_zeros,
+        lsb_exp: msb_exp.max(min_normal_exp) - format.num_sig_bits as i32,
+        is_tiny: sig != 0 && msb_exp < min_normal_exp,
+        format: format.clone(),
+    }
+}
+
+// Round stage: the exact value rounded at the result's lsb, or flushed to zero
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Rounded {
+    pub(crate) sign: bool,
+    pub(crate) sig: u128,
+    pub(crate) lsb_exp: i32,
+    pub(crate) is_tiny: bool,
+    pub(crate) is_inexact: bool,
+    pub(crate) is_flushed: bool,
+    pub(crate) format: Format,
+}
+
+impl Rounded {
+    pub fn sign(&self) -> bool {
+        self.sign
+    }
+
+    // In units of the result's lsb; may have carried out into the next binade
+    pub fn sig(&self) -> u128 {
+        self.sig
+    }
+
+    pub fn lsb_exp(&self) -> i32 {
+        self.lsb_exp
+    }
+
+    // Tininess, as detected by the context's tininess mode
+    pub fn is_tiny(&self) -> bool {
+        self.is_tiny
+    }
+
+    pub fn is_inexact(&self) -> bool {
+        self.is_inexact
+    }
+
+    pub fn is_flushed(&self) -> bool {
+        self.is_flushed
+    }
+}
+
+pub fn round_normalized(normalized: &Normalized, context: &mut Context) -> Rounded {
+    let Normalized { sign, sig, exp, lsb_exp, format, .. } = normalized;
+    let (sign, sig, exp, lsb_exp) = (*sign, *sig, *exp, *lsb_exp);
+    let num_sig_bits = format.num_sig_bits as i32;
+    let msb_exp = normalized.msb_exp();
+
+    if sig == 0 {
+        return Rounded { sign, sig: 0, lsb_exp, is_tiny: false, is_inexact: false, is_flushed: false, format: format.clone() };
+    }
+
+    // Without IEEE rounding, results are flushed to +0
+    let flushed_sign = sign && context.ieee_rounding;
+    let flushed = || Rounded { sign: flushed_sign, sig: 0, lsb_exp, is_tiny: true, is_inexact: true, is_flushed: true, format: format.clone() };
+
+    if context.flush_to_zero && normalized.is_tiny && context.tininess_mode == TininessMode::BeforeRounding {
+        context.raise(Flags::UNDERFLOW | Flags::INEXACT);
+        return flushed();
+    }
+
+    let random_bits = match context.rounding_mode {
+        RoundingMode::Stochastic { num_random_bits } => context.next_random_bits(num_random_bits),
+        _ => 0,
+    };
+
+    // Tininess after rounding only differs when rounding to the format's precision carries up to the smallest normal
+    let is_tiny = normalized.is_tiny
+        && match context.tininess_mode {
+            TininessMode::BeforeRounding => true,
+            TininessMode::AfterRounding => {
+                let unbounded_lsb_exp = msb_exp - num_sig_bits;
+                let is_carry = unbounded_lsb_exp > exp
+                    && shift_right_rounded(sign, sig, (unbounded_lsb_exp - exp) as u32, context.rounding_mode, random_bits)
+                        >> (num_sig_bits + 1)
+                        != 0;
+                msb_exp + (is_carry as i32) < format.min_normal_exp()
+            }
+        };
+    if context.flush_to_zero && is_tiny {
+        context.raise(Flags::UNDERFLOW | Flags::INEXACT);
+        return flushed();
+    }
+
+    // Align sig so that its lsb has the weight of the result's lsb
+    let result_sig = if lsb_exp <= exp {
+        sig << (exp - lsb_exp)
+    } else {
+        shift_right_rounded(sign, sig, (lsb_exp - exp) as u32, context.rounding_mode, random_bits)
+    };
+    let is_inexact = lsb_exp > exp && (lsb_exp - exp >= 128 || (sig & ((1 << (lsb_exp - exp)) - 1)) != 0);
+    if is_inexact {
+        context.raise(if is_tiny { Flags::UNDERFLOW | Flags::INEXACT } else { Flags::INEXACT });
+    }
+
+    Rounded { sign, sig: result_sig, lsb_exp, is_tiny, is_inexact, is_flushed: false, format: format.clone() }
+}
+
+// Pack stage: encodes the rounded value, handling overflow
+pub fn pack(rounded: &Rounded, context: &mut Context) -> Value {
+    let Rounded { sign, lsb_exp, format, .. } = rounded;
+    let (sign, lsb_exp) = (*sign, *lsb_exp);
+    if rounded.is_flushed {
+        return Value::zero(sign, format.clone());
+    }
+
+    let num_sig_bits = format.num_sig_bits as i32;
+
+    // Encode exp, renormalizing in case rounding carried out of the sig
+    let hidden_bit = 1 << num_sig_bits;
+    let mut result_sig = rounded.sig;
+    let mut result_exp = if result_sig >= hidden_bit { lsb_exp + num_sig_bits + format.exp_bias() } else { 0 };
+    if result_sig >= hidden_bit << 1 {
+        result_sig >>= 1;
+        result_exp += 1;
+    }
+    let result_sig = (result_sig as u32) & format.sig_mask();
+
+    // Check for exp overflow
+    let (max_exp, max_sig) = format.max_finite_comps();
+    let is_overflow = result_exp > max_exp as i32 || (result_exp == max_exp as i32 && result_sig > max_sig);
+    if is_overflow {
+        context.raise(Flags::OVERFLOW | Flags::INEXACT);
+
+        return if is_overflow_max_finite(sign, context) {
+            Value::max_finite(sign, format.clone())
+        } else {
+            Value::infinity(sign, format.clone())
+        };
+    }
+
+    Value::from_comps(sign, result_exp as u32, result_sig, format.clone())
+}
+
+// Whether an overflowing result of the given sign goes to the max finite value rather than infinity, ie. when
+// saturating or, with IEEE rounding, when rounding toward zero (in either direction)
+pub(crate) fn is_overflow_max_finite(sign: bool, context: &Context) -> bool {
+    context.overflow_mode == OverflowMode::Saturate
+        || context.ieee_rounding
+            && match context.rounding_mode {
+                RoundingMode::TowardZero => true,
+                RoundingMode::TowardPositive => sign,
+                RoundingMode::TowardNegative => !sign,
+                _ => false,
+            }
+}
+
+// Exact host arithmetic as a reference for ops in formats whose values (and op results) f64 represents exactly
+#[cfg(test)]
+pub(crate) fn to_f64(value: &Value) -> f64 {
+    let (sig, exp) = value.decode_finite();
+    let magnitude = sig as f64 * 2f64.powi(exp);
+    if value.sign { -magnitude } else { magnitude }
+}
+
+#[cfg(test)]
+pub(crate) fn round_f64(x: f64, format: &Format, context: &mut Context) -> Value {
+    let bits = x.to_bits();
+    let sign = (bits >> 63) != 0;
+    let exp = ((bits >> 52) & 0x7ff) as i32;
+    let sig = bits & ((1 << 52) - 1);
+    if exp == 0 {
+        round(sign, sig as u128, -1074, format, context)
+    } else {
+        round(sign, (sig | (1 << 52)) as u128, exp - 1075, format, context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    fn nearest_even() -> Context {
+        Context {
+            flush_to_zero: false,
+            ieee_rounding: true,
+            ..Context::new().with_rounding_mode(RoundingMode::NearestEven)
+        }
+    }
+
+    fn stochastic(num_random_bits: u32, random_bits: u32) -> Context {
+        Context {
+            flush_to_zero: false,
+            ieee_rounding: true,
+            ..Context::new()
+                .with_rounding_mode(RoundingMode::Stochastic { num_random_bits })
+                .with_random_source(move |_| random_bits)
+        }
+    }
+
+    #[test]
+    fn mode_names() {
+        for &mode in &[
+            RoundingMode::NearestEven,
+            RoundingMode::NearestAway,
+            RoundingMode::TowardZero,
+            RoundingMode::TowardPositive,
+            RoundingMode::TowardNegative,
+            RoundingMode::Stochastic { num_random_bits: 8 },
+        ] {
+            assert_eq!(mode.to_string().parse::<RoundingMode>(), Ok(mode));
+        }
+        for &mode in &[TininessMode::BeforeRounding, TininessMode::AfterRounding] {
+            assert_eq!(mode.to_string().parse::<TininessMode>(), Ok(mode));
+        }
+        for &mode in &[OverflowMode::Ieee, OverflowMode::Saturate] {
+            assert_eq!(mode.to_string().parse::<OverflowMode>(), Ok(mode));
+        }
+
+        assert_eq!(RoundingMode::Stochastic { num_random_bits: 8 }.to_string(), "stochastic8");
+        for name in &["stochastic0", "stochastic33", "stochastic", "up"] {
+            assert_eq!(name.parse::<RoundingMode>(), Err(ParseModeError::InvalidRoundingMode(name.to_string())));
+        }
+        assert_eq!("during".parse::<TininessMode>(), Err(ParseModeError::InvalidTininessMode("during".to_string())));
+        assert_eq!("wrap".parse::<OverflowMode>(), Err(ParseModeError::InvalidOverflowMode("wrap".to_string())));
+    }
+
+    #[test]
+    fn shift_right_rounded_nearest_even() {
+        assert_eq!(shift_right_rounded(false, 0b1011, 2, RoundingMode::NearestEven, 0), 0b11); // 2.75 -> 3
+        assert_eq!(shift_right_rounded(false, 0b1001, 2, RoundingMode::NearestEven, 0), 0b10); // 2.25 -> 2
+        assert_eq!(shift_right_rounded(false, 0b1010, 2, RoundingMode::NearestEven, 0), 0b10); // 2.5 -> 2
+        assert_eq!(shift_right_rounded(false, 0b1110, 2, RoundingMode::NearestEven, 0), 0b100); // 3.5 -> 4
+        assert_eq!(shift_right_rounded(false, u128::MAX, 128, RoundingMode::NearestEven, 0), 1);
+        assert_eq!(shift_right_rounded(false, u128::MAX, 129, RoundingMode::NearestEven, 0), 0);
+    }
+
+    #[test]
+    fn shift_right_rounded_toward_zero() {
+        assert_eq!(shift_right_rounded(false, 0b1011, 2, RoundingMode::TowardZero, 0), 0b10);
+        assert_eq!(shift_right_rounded(false, 0b1110, 2, RoundingMode::TowardZero, 0), 0b11);
+        assert_eq!(shift_right_rounded(false, u128::MAX, 128, RoundingMode::TowardZero, 0), 0);
+    }
+
+    #[test]
+    fn shift_right_rounded_nearest_away() {
+        assert_eq!(shift_right_rounded(false, 0b1010, 2, RoundingMode::NearestAway, 0), 0b11); // 2.5 -> 3
+        assert_eq!(shift_right_rounded(true, 0b1010, 2, RoundingMode::NearestAway, 0), 0b11); // -2.5 -> -3
+        assert_eq!(shift_right_rounded(false, 0b1001, 2, RoundingMode::NearestAway, 0), 0b10); // 2.25 -> 2
+    }
+
+    #[test]
+    fn shift_right_rounded_directed() {
+        assert_eq!(shift_right_rounded(false, 0b1001, 2, RoundingMode::TowardPositive, 0), 0b11); // 2.25 -> 3
+        assert_eq!(shift_right_rounded(true, 0b1001, 2, RoundingMode::TowardPositive, 0), 0b10); // -2.25 -> -2
+        assert_eq!(shift_right_rounded(false, 0b1001, 2, RoundingMode::TowardNegative, 0), 0b10); // 2.25 -> 2
+        assert_eq!(shift_right_rounded(true, 0b1001, 2, RoundingMode::TowardNegative, 0), 0b11); // -2.25 -> -3
+        assert_eq!(shift_right_rounded(true, 0b1000, 2, RoundingMode::TowardNegative, 0), 0b10); // Exact
+        assert_eq!(shift_right_rounded(false, 1, 200, RoundingMode::TowardPositive, 0), 1);
+    }
+
+    #[test]
+    fn shift_right_rounded_stochastic() {
+        let mode = RoundingMode::Stochastic { num_random_bits: 4 };
+
+        // Discarded bits 0b1100 round up for random bits >= 0b0100
+        assert_eq!(shift_right_rounded(false, 0b101100, 4, mode, 0b0011), 0b10);
+        assert_eq!(shift_right_rounded(false, 0b101100, 4, mode, 0b0100), 0b11);
+
+        // Discarded bits below the top num_random_bits are ignored
+        assert_eq!(shift_right_rounded(false, 0b10000011, 6, mode, 0b1111), 0b10);
+        assert_eq!(shift_right_rounded(false, 0b10001000, 6, mode, 0b1110), 0b11);
+
+        // Fewer discarded bits than random bits
+        assert_eq!(shift_right_rounded(false, 0b101, 1, mode, 0b0111), 0b10);
+        assert_eq!(shift_right_rounded(false, 0b101, 1, mode, 0b1000), 0b11);
+
+        // Exact values never round up
+        assert_eq!(shift_right_rounded(false, 0b1000, 3, mode, 0b1111), 0b1);
+        assert_eq!(shift_right_rounded(false, u128::MAX, 200, mode, 0b1111), 0);
+
+        let mode = RoundingMode::Stochastic { num_random_bits: 32 };
+
+        assert_eq!(shift_right_rounded(false, 0x1_8000_0000, 32, mode, 0x7fff_ffff), 0x1);
+        assert_eq!(shift_right_rounded(false, 0x1_8000_0000, 32, mode, 0x8000_0000), 0x2);
+    }
+
+    #[test]
+    fn round_normal() {
+        let f = Format::ieee754_single();
+
+        assert_eq!(round(false, 3, -1, &f, &mut nearest_even()).to_bits(), 0x3fc00000); // 1.5
+        assert_eq!(round(true, 0x1000001, 0, &f, &mut nearest_even()).to_bits(), 0xcb800000); // -16777217 -> -16777216
+        assert_eq!(round(false, 0x1000003, 0, &f, &mut nearest_even()).to_bits(), 0x4b800002); // 16777219 -> 16777220
+        assert_eq!(round(false, 0xffffff8, -4, &f, &mut nearest_even()).to_bits(), 0x4b800000); // carry out of sig
+    }
+
+    #[test]
+    fn round_denormal() {
+        let f = Format::ieee754_single();
+
+        assert_eq!(round(false, 1, -149, &f, &mut nearest_even()).to_bits(), 0x00000001);
+        assert_eq!(round(false, 1, -150, &f, &mut nearest_even()).to_bits(), 0x00000000); // tie to even (zero)
+        assert_eq!(round(false, 3, -150, &f, &mut nearest_even()).to_bits(), 0x00000002);
+        assert_eq!(round(false, 0xffffff, -150, &f, &mut nearest_even()).to_bits(), 0x00800000); // rounds up to smallest normal
+
+        let mut ftz = nearest_even().with_flush_to_zero(true);
+        assert_eq!(round(true, 1, -149, &f, &mut ftz).to_bits(), 0x80000000);
+
+        // Without IEEE rounding, flushed results are +0
+        assert_eq!(round(true, 1, -149, &f, &mut Context::new()).to_bits(), 0x00000000);
+    }
+
+    #[test]
+    fn round_overflow() {
+        let f = Format::ieee754_single();
+
+        assert_eq!(round(false, 1, 128, &f, &mut nearest_even()).to_bits(), 0x7f800000); // +inf
+        assert_eq!(round(true, 0xffffff, 104, &f, &mut nearest_even()).to_bits(), 0xff7fffff); // -max value
+        assert_eq!(round(true, 0x1ffffff, 103, &f, &mut nearest_even()).to_bits(), 0xff800000); // -inf
+
+        let mut toward_zero = nearest_even().with_rounding_mode(RoundingMode::TowardZero);
+        assert_eq!(round(false, 1, 128, &f, &mut toward_zero).to_bits(), 0x7f7fffff); // +max value
+
+        // Without IEEE rounding, overflow goes to infinity even when rounding toward zero
+        assert_eq!(round(false, 1, 128, &f, &mut Context::new()).to_bits(), 0x7f800000); // +inf
+
+        let mut saturate = nearest_even().with_overflow_mode(OverflowMode::Saturate);
+        assert_eq!(round(false, 1, 128, &f, &mut saturate).to_bits(), 0x7f7fffff); // +max value
+        assert_eq!(round(true, 1, 300, &f, &mut saturate).to_bits(), 0xff7fffff); // -max value
+
+        let e4m3 = Format::ocp_fp8_e4m3();
+        assert_eq!(round(false, 29, 4, &e4m3, &mut nearest_even()).to_bits(), 0x7e); // 464 -> 448 (tie to even)
+        assert_eq!(round(false, 15, 5, &e4m3, &mut nearest_even()).to_bits(), 0x7f); // 480 -> NaN
+        assert_eq!(round(false, 1, 9, &e4m3, &mut nearest_even()).to_bits(), 0x7f); // 512 -> NaN
+        assert_eq!(round(false, 1, 9, &e4m3, &mut saturate).to_bits(), 0x7e); // 512 -> 448
+
+        let e2m1 = Format::ocp_fp4_e2m1();
+        assert_eq!(round(true, 1, 3, &e2m1, &mut nearest_even()).to_bits(), 0xf); // -8 -> -6
+    }
+
+    #[test]
+    fn round_overflow_flag() {
+        let f = Format::ieee754_single();
+
+        let mut context = nearest_even();
+        let _ = round(false, 0xffffff, 104, &f, &mut context); // max value
+        assert!(context.flags().is_empty());
+        let _ = round(false, 0x1ffffff, 103, &f, &mut context); // rounds up to 2^128
+        assert_eq!(context.flags(), Flags::OVERFLOW | Flags::INEXACT);
+
+        for &overflow_mode in &[OverflowMode::Ieee, OverflowMode::Saturate] {
+            let mut context = Context::new().with_overflow_mode(overflow_mode);
+            let _ = round(false, 1, 128, &f, &mut context);
+            assert_eq!(context.flags(), Flags::OVERFLOW | Flags::INEXACT);
+        }
+    }
+
+    #[test]
+    fn round_inexact_underflow_flags() {
+        let f = Format::ieee754_single();
+
+        let mut context = nearest_even();
+        let _ = round(false, 0x800001, 0, &f, &mut context); // exact
+        assert!(context.flags().is_empty());
+        let _ = round(false, 0x1000001, 0, &f, &mut context); // 2^24 + 1
+        assert_eq!(context.flags(), Flags::INEXACT);
+
+        let mut context = nearest_even();
+        let _ = round(false, 6, -150, &f, &mut context); // exact subnormal
+        assert!(context.flags().is_empty());
+        let _ = round(false, 3, -151, &f, &mut context); // 0.75 * min subnormal
+        assert_eq!(context.flags(), Flags::UNDERFLOW | Flags::INEXACT);
+
+        // Tiny results are flushed with underflow
+        let mut context = Context::new();
+        let _ = round(false, 1, -149, &f, &mut context);
+        assert_eq!(context.flags(), Flags::UNDERFLOW | Flags::INEXACT);
+    }
+
+    #[test]
+    fn round_tininess_after_rounding() {
+        let f = Format::ieee754_single();
+        let after_rounding = || nearest_even().with_tininess_mode(TininessMode::AfterRounding);
+
+        // 2^-126 * (1 - 2^-25) rounds up to the smallest normal, even with an unbounded exponent range
+        let mut context = nearest_even();
+        assert_eq!(round(false, 0x1ffffff, -151, &f, &mut context).to_bits(), 0x00800000);
+        assert_eq!(context.flags(), Flags::UNDERFLOW | Flags::INEXACT);
+
+        let mut context = after_rounding();
+        assert_eq!(round(false, 0x1ffffff, -151, &f, &mut context).to_bits(), 0x00800000);
+        assert_eq!(context.flags(), Flags::INEXACT);
+
+        let mut context = after_rounding().with_flush_to_zero(true);
+        assert_eq!(round(true, 0x1ffffff, -151, &f, &mut context).to_bits(), 0x80800000);
+        assert_eq!(context.flags(), Flags::INEXACT);
+        assert_eq!(round(true, 0x1ffffff, -151, &f, &mut nearest_even().with_flush_to_zero(true)).to_bits(), 0x80000000);
+
+        // Rounding toward zero doesn't carry, so the result is still tiny
+        let mut context = after_rounding().with_rounding_mode(RoundingMode::TowardZero).with_flush_to_zero(true);
+        assert_eq!(round(false, 0x1ffffff, -151, &f, &mut context).to_bits(), 0x00000000);
+        assert_eq!(context.flags(), Flags::UNDERFLOW | Flags::INEXACT);
+
+        // 2^-126 * (1 - 2^-24) is exact at full precision, so tiny either way
+        let mut context = after_rounding();
+        assert_eq!(round(false, 0xffffff, -150, &f, &mut context).to_bits(), 0x00800000);
+        assert_eq!(context.flags(), Flags::UNDERFLOW | Flags::INEXACT);
+    }
+
+    #[test]
+    fn round_directed() {
+        let f = Format::ieee754_single();
+        let context = |rounding_mode| nearest_even().with_rounding_mode(rounding_mode);
+
+        // 1 + 2^-24 is a tie
+        let sig = (1 << 24) + 1;
+        assert_eq!(round(false, sig, -24, &f, &mut context(RoundingMode::NearestEven)).to_bits(), 0x3f800000);
+        assert_eq!(round(false, sig, -24, &f, &mut context(RoundingMode::NearestAway)).to_bits(), 0x3f800001);
+        assert_eq!(round(true, sig, -24, &f, &mut context(RoundingMode::NearestAway)).to_bits(), 0xbf800001);
+        assert_eq!(round(false, sig, -24, &f, &mut context(RoundingMode::TowardPositive)).to_bits(), 0x3f800001);
+        assert_eq!(round(true, sig, -24, &f, &mut context(RoundingMode::TowardPositive)).to_bits(), 0xbf800000);
+        assert_eq!(round(false, sig, -24, &f, &mut context(RoundingMode::TowardNegative)).to_bits(), 0x3f800000);
+        assert_eq!(round(true, sig, -24, &f, &mut context(RoundingMode::TowardNegative)).to_bits(), 0xbf800001);
+
+        // Tiny values round away from zero in their direction
+        assert_eq!(round(false, 1, -200, &f, &mut context(RoundingMode::TowardPositive)).to_bits(), 0x00000001);
+        assert_eq!(round(true, 1, -200, &f, &mut context(RoundingMode::TowardPositive)).to_bits(), 0x80000000);
+
+        // Overflow goes to the max finite value when rounding toward zero
+        assert_eq!(round(false, 1, 128, &f, &mut context(RoundingMode::TowardPositive)).to_bits(), 0x7f800000);
+        assert_eq!(round(true, 1, 128, &f, &mut context(RoundingMode::TowardPositive)).to_bits(), 0xff7fffff);
+        assert_eq!(round(false, 1, 128, &f, &mut context(RoundingMode::TowardNegative)).to_bits(), 0x7f7fffff);
+        assert_eq!(round(true, 1, 128, &f, &mut context(RoundingMode::TowardNegative)).to_bits(), 0xff800000);
+        assert_eq!(round(true, 1, 128, &f, &mut context(RoundingMode::NearestAway)).to_bits(), 0xff800000);
+    }
+
+    #[test]
+    fn round_stochastic() {
+        let f = Format::ieee754_single();
+
+        // 1 + 2^-24 + 2^-25 discards 0b11 followed by zeros
+        let sig = (1 << 25) | 0b11;
+        assert_eq!(round(false, sig, -25, &f, &mut stochastic(2, 0b00)).to_bits(), 0x3f800000);
+        assert_eq!(round(false, sig, -25, &f, &mut stochastic(2, 0b01)).to_bits(), 0x3f800001);
+        assert_eq!(round(true, sig, -25, &f, &mut stochastic(2, 0b11)).to_bits(), 0xbf800001);
+
+        // Denormal results
+        assert_eq!(round(false, 1, -151, &f, &mut stochastic(8, 0xbf)).to_bits(), 0x00000000);
+        assert_eq!(round(false, 1, -151, &f, &mut stochastic(8, 0xc0)).to_bits(), 0x00000001);
+
+        // Rounding up past the max value overflows
+        assert_eq!(round(false, 0x1ffffff, 103, &f, &mut stochastic(1, 0)).to_bits(), 0x7f7fffff);
+        assert_eq!(round(false, 0x1ffffff, 103, &f, &mut stochastic(1, 1)).to_bits(), 0x7f800000);
+    }
+
+    #[test]
+    fn round_stochastic_draws() {
+        let f = Format::ieee754_single();
+
+        let num_draws = Rc::new(Cell::new(0));
+        let draws = num_draws.clone();
+        let mut context = Context::new()
+            .with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 8 })
+            .with_random_source(move |num_bits| {
+                assert_eq!(num_bits, 8);
+                draws.set(draws.get() + 1);
+                0
+            });
+
+        // One draw per rounded result, even if exact
+        let _ = round(false, 1, 0, &f, &mut context);
+        assert_eq!(num_draws.get(), 1);
+        let _ = round(false, 3, -30, &f, &mut context);
+        assert_eq!(num_draws.get(), 2);
+
+        // Zeros and flushed denormals aren't rounded
+        let _ = round(false, 0, 0, &f, &mut context);
+        assert_eq!(num_draws.get(), 2);
+        let _ = round(false, 1, -140, &f, &mut context);
+        assert_eq!(num_draws.get(), 2);
+    }
+
+    #[test]
+    fn round_custom_exp_bias() {
+        let f = Format::new(4, 3).with_exp_bias(11);
+
+        assert_eq!(round(false, 1, 0, &f, &mut nearest_even()).to_bits(), 0x58); // 1.0
+        assert_eq!(round(false, 15, 0, &f, &mut nearest_even()).to_bits(), 0x77); // 15.0 (max value)
+        assert_eq!(round(false, 1, 4, &f, &mut nearest_even()).to_bits(), 0x78); // 16.0 -> +inf
+        assert_eq!(round(false, 1, -13, &f, &mut nearest_even()).to_bits(), 0x01); // smallest denormal
+    }
+
+    #[test]
+    fn stages() {
+        let f = Format::new(5, 10);
+        let mut context = nearest_even();
+
+        // 4095 rounds to 4096, carrying out of the sig
+        let normalized = normalize(false, 0xfff, 0, &f);
+
+        assert_eq!((normalized.num_leading_zeros(), normalized.msb_exp(), normalized.lsb_exp(), normalized.is_tiny()), (116, 11, 1, false));
+
+        let rounded = round_normalized(&normalized, &mut context);
+
+        assert_eq!((rounded.sig(), rounded.lsb_exp(), rounded.is_inexact(), rounded.is_flushed()), (0x800, 1, true, false));
+        assert_eq!(pack(&rounded, &mut context).to_bits(), 0x6c00);
+
+        // Tiny values are flushed (with flush to zero)
+        let normalized = normalize(false, 1, -30, &f);
+
+        assert!(normalized.is_tiny());
+
+        let rounded = round_normalized(&normalized, &mut Context::new().with_flush_to_zero(true));
+
+        assert!(rounded.is_flushed() && rounded.is_tiny());
+        assert_eq!(pack(&rounded, &mut context).to_bits(), 0x0000);
+    }
+}
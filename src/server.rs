@@ -0,0 +1,151 @@
+// Line protocol for softy-server, a golden-model process for simulators that can spawn a process but can't load
+// libraries. Each request is one line of whitespace-separated fields:
+//
+//   <op> <format> <modes> <random bits> <source>...
+//
+// where op is "addition", "multiplication", "fused_multiply_add" or "conversion:<target format>", formats are
+// specifications such as "e8m23" or "e4m3fn", modes is the ffi modes word (see ffi::MODE_*) in hex, random bits (used by
+// stochastic rounding) are in hex, and sources are raw bits in hex, eg. "addition e8m23 0 0 3f800000 3f800000". Each
+// reply is one line, "<result> <flags>" in hex (eg. "40000000 00"), or "error <message>" for a malformed request.
+// Blank lines and lines starting with '#' are ignored, and get no reply.
+//
+// Replies come back in request order. They're only flushed once no further requests are buffered, so clients get
+// throughput by writing a batch of requests before reading the batch of replies.
+
+use std::any::Any;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Read;
+use std::io::Write;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+
+use crate::ffi;
+use crate::format::*;
+use crate::ops::*;
+
+fn parse_hex(token: &str, num_bits: u32, name: &str) -> Result<u32, String> {
+    match u32::from_str_radix(token, 16) {
+        Ok(bits) if (bits as u64) >> num_bits == 0 => Ok(bits),
+        _ => Err(format!("Invalid {} \"{}\".", name, token)),
+    }
+}
+
+// The message a panic was raised with, if it has one
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else {
+        "Evaluation panicked.".to_string()
+    }
+}
+
+fn parse_format(token: &str) -> Result<Format, String> {
+    token.parse().map_err(|error: FormatError| error.to_string())
+}
+
+// Evaluates one request line, returning its reply (without a newline)
+pub fn handle_request(line: &str) -> Result<String, String> {
+    let tokens = line.split_whitespace().collect::<Vec<_>>();
+    if tokens.len() < 4 {
+        return Err("Expected <op> <format> <modes> <random bits> <source>...".to_string());
+    }
+
+    let op = tokens[0].parse::<Op>().map_err(|error| error.to_string())?;
+    let format = parse_format(tokens[1])?;
+    let modes = parse_hex(tokens[2], 32, "modes")?;
+    let random_bits = parse_hex(tokens[3], 32, "random bits")?;
+    let mut context = ffi::context(modes, random_bits).ok_or_else(|| format!("Invalid modes \"{}\".", tokens[2]))?;
+
+    let sources = &tokens[4..];
+    if sources.len() != op.num_sources() {
+        return Err(format!("{} takes {} sources, but got {}.", op.name(), op.num_sources(), sources.len()));
+    }
+    let sources = sources
+        .iter()
+        .map(|source| parse_hex(source, format.num_storage_bits(), "source"))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // A panic becomes an error reply, rather than taking down the server along with every pending request
+    let result = panic::catch_unwind(AssertUnwindSafe(|| op.evaluate(&sources, &format, &mut context)))
+        .map_err(|payload| panic_message(&*payload))?;
+    let num_result_digits = op.result_format(&format).num_storage_bits().div_ceil(4) as usize;
+    Ok(format!("{:0width$x} {:02x}", result.to_bits(), context.flags().bits(), width = num_result_digits))
+}
+
+// Serves requests from reader until it ends
+pub fn serve<R: Read, W: Write>(reader: R, writer: W) -> io::Result<()> {
+    let mut reader = BufReader::new(reader);
+    let mut writer = BufWriter::new(writer);
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return writer.flush();
+        }
+
+        let request = line.trim();
+        if !request.is_empty() && !request.starts_with('#') {
+            match handle_request(request) {
+                Ok(reply) => writeln!(writer, "{}", reply)?,
+                Err(message) => writeln!(writer, "error {}", message)?,
+            }
+        }
+
+        if reader.buffer().is_empty() {
+            writer.flush()?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests() {
+        assert_eq!(handle_request("addition e8m23 0 0 3f800000 3f800000"), Ok("40000000 00".to_string()));
+        assert_eq!(handle_request("multiplication e5m10 0 0 7bff 7bff"), Ok("7c00 05".to_string()));
+        assert_eq!(handle_request("multiplication e5m10 40 0 7bff 7bff"), Ok("7bff 05".to_string())); // Saturating
+        assert_eq!(handle_request("fused_multiply_add e4m3fn 0 0 38 38 38"), Ok("40 00".to_string()));
+        assert_eq!(handle_request("conversion:e2m1+finite e8m23 0 0 3f800000"), Ok("2 00".to_string()));
+
+        // Stochastic rounding of 1 + 2^-11 with 1 random bit (and IEEE rounding, so the addend isn't truncated)
+        assert_eq!(handle_request("addition e5m10 10205 0 3c00 1000"), Ok("3c00 01".to_string()));
+        assert_eq!(handle_request("addition e5m10 10205 1 3c00 1000"), Ok("3c01 01".to_string()));
+    }
+
+    #[test]
+    fn malformed_requests() {
+        assert_eq!(handle_request("addition e8m23 0"), Err("Expected <op> <format> <modes> <random bits> <source>...".to_string()));
+        assert!(handle_request("division e8m23 0 0 0 0").unwrap_err().starts_with("Requested op \"division\" is not valid;"));
+        assert!(handle_request("addition e1m2 0 0 0 0").unwrap_err().starts_with("Requested format must have at least 2 exponent bits."));
+        assert_eq!(handle_request("addition e5m10 7 0 0 0"), Err("Invalid modes \"7\".".to_string()));
+        assert_eq!(handle_request("addition e5m10 0 0 0"), Err("addition takes 2 sources, but got 1.".to_string()));
+        assert_eq!(handle_request("addition e5m10 0 0 0 10000"), Err("Invalid source \"10000\".".to_string()));
+        assert_eq!(
+            handle_request("conversion:e8m0 e8m23 0 0 7fc00000"),
+            Err("Requested format must have at least 1 significand bit to encode NaN alongside infinity.".to_string())
+        );
+    }
+
+    #[test]
+    fn serve_batch() {
+        let requests = "addition e5m10 0 0 3c00 3c00\n\n# comment\ndivision e5m10 0 0 0 0\n  multiplication e5m10 0 0 3c00 4000  \nconversion:e8m0 e8m23 0 0 7fc00000\nconversion:e8m0+finite e8m23 0 0 3f800000\n";
+        let mut replies = Vec::new();
+
+        serve(requests.as_bytes(), &mut replies).unwrap();
+
+        let replies = String::from_utf8(replies).unwrap();
+        let replies = replies.lines().collect::<Vec<_>>();
+        assert_eq!(replies.len(), 5);
+        assert_eq!((replies[0], replies[2]), ("4000 00", "4000 00"));
+        assert!(replies[1].starts_with("error Requested op \"division\" is not valid;"));
+        assert!(replies[3].starts_with("error Requested format must have at least 1 significand bit"));
+        assert_eq!(replies[4], "07f 00");
+    }
+}
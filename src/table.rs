@@ -0,0 +1,362 @@
+// Complete result and flags tables for ops over small formats (every binary op over an 8-bit format fits in 2^16
+// entries), so sweeps can look results up instead of evaluating them. A table is fixed to one op, format and set of
+// modes; TableCache generates each table on first use.
+//
+// Entry i holds the result for the sources packed into i, source1 in the high bits (eg. (source1 << 8) | source2 for
+// an 8-bit format). Exporters write each entry as one word, (result << 5) | flags, for Rust, $readmemh ROMs or raw
+// binary.
+
+use std::io;
+use std::io::Write;
+
+use crate::context::*;
+use crate::flags::*;
+use crate::format::*;
+use crate::ops::*;
+use crate::rounding::*;
+
+// Tables are limited to 2^16 entries
+const MAX_TABLE_SOURCE_BITS: u32 = 16;
+
+// Flags take the low 5 bits of an exported word
+const NUM_FLAG_BITS: u32 = 5;
+
+pub struct Table {
+    pub(crate) op: Op,
+    pub(crate) format: Format,
+    pub(crate) modes: Modes,
+    pub(crate) results: Vec<u32>,
+    pub(crate) flags: Vec<Flags>,
+}
+
+impl Table {
+    // Evaluates op over every combination of sources in format, under context's modes (context's flags are untouched)
+    pub fn new(op: &Op, format: &Format, context: &Context) -> Table {
+        let num_source_bits = op.num_sources() as u32 * format.num_storage_bits();
+        if num_source_bits > MAX_TABLE_SOURCE_BITS {
+            panic!(
+                "Requested table over {} source bits, which exceeds the maximum of {} bits.",
+                num_source_bits, MAX_TABLE_SOURCE_BITS
+            );
+        }
+        if let RoundingMode::Stochastic { .. } = context.rounding_mode {
+            panic!("Requested table with stochastic rounding, whose results depend on random bits as well as sources.");
+        }
+
+        let modes = Modes::new(context);
+        let mut context = modes.context();
+        let mut table = Table {
+            op: op.clone(),
+            format: format.clone(),
+            modes,
+            results: Vec::with_capacity(1 << num_source_bits),
+            flags: Vec::with_capacity(1 << num_source_bits),
+        };
+        let mut sources = vec![0; op.num_sources()];
+        for index in 0..1u32 << num_source_bits {
+            for (i, source) in sources.iter_mut().rev().enumerate() {
+                *source = (index >> (i as u32 * format.num_storage_bits())) & format.storage_mask();
+            }
+            context.clear_flags();
+            table.results.push(op.evaluate(&sources, format, &mut context).to_bits());
+            table.flags.push(context.flags());
+        }
+        table
+    }
+
+    pub fn op(&self) -> &Op {
+        &self.op
+    }
+
+    pub fn format(&self) -> &Format {
+        &self.format
+    }
+
+    pub fn num_entries(&self) -> usize {
+        self.results.len()
+    }
+
+    // Index of the entry for sources (bits above the format's are ignored, as in Value::from_bits)
+    pub fn index(&self, sources: &[u32]) -> usize {
+        if sources.len() != self.op.num_sources() {
+            panic!("Requested {} of {} sources, but it takes {} sources.", self.op.name(), sources.len(), self.op.num_sources());
+        }
+
+        let num_storage_bits = self.format.num_storage_bits();
+        let mask = self.format.storage_mask();
+        sources.iter().fold(0, |index, &source| (index << num_storage_bits) | (source & mask) as usize)
+    }
+
+    // Result and flags for sources
+    pub fn lookup(&self, sources: &[u32]) -> (u32, Flags) {
+        let index = self.index(sources);
+        (self.results[index], self.flags[index])
+    }
+
+    // results[i] (and flags[i], if flags are wanted) for sources[0][i], sources[1][i], ...
+    pub fn evaluate(&self, sources: &[&[u32]], results: &mut [u32], mut flags: Option<&mut [Flags]>) {
+        if sources.len() != self.op.num_sources() {
+            panic!("Requested {} of {} sources, but it takes {} sources.", self.op.name(), sources.len(), self.op.num_sources());
+        }
+        let num_results = results.len();
+        if sources.iter().any(|sources| sources.len() != num_results) || flags.as_ref().is_some_and(|flags| flags.len() != num_results) {
+            panic!("Requested table {} into {} results, but every slice must have the same length.", self.op.name(), num_results);
+        }
+
+        let mut entry_sources = vec![0; sources.len()];
+        for i in 0..num_results {
+            for (entry_source, sources) in entry_sources.iter_mut().zip(sources) {
+                *entry_source = sources[i];
+            }
+            let index = self.index(&entry_sources);
+            results[i] = self.results[index];
+            if let Some(flags) = flags.as_mut() {
+                flags[i] = self.flags[index];
+            }
+        }
+    }
+
+    // Bits in an exported word: the result, then the flags
+    pub fn num_word_bits(&self) -> u32 {
+        self.op.result_format(&self.format).num_storage_bits() + NUM_FLAG_BITS
+    }
+
+    // Exported word for entry index: (result << 5) | flags
+    pub fn word(&self, index: usize) -> u64 {
+        ((self.results[index] as u64) << NUM_FLAG_BITS) | self.flags[index].bits() as u64
+    }
+
+    // Bytes per word in Rust arrays and raw binary: the smallest of 1, 2, 4 or 8 that fits
+    fn num_word_bytes(&self) -> usize {
+        (self.num_word_bits().div_ceil(8) as usize).next_power_of_two()
+    }
+
+    // What the table holds and how it's laid out, for exported comments
+    fn describe(&self) -> Vec<String> {
+        let modes = self.modes.context();
+        let num_sources = self.op.num_sources();
+        let index = (1..=num_sources)
+            .map(|i| match (num_sources - i) as u32 * self.format.num_storage_bits() {
+                0 => format!("source{}", i),
+                shift => format!("(source{} << {})", i, shift),
+            })
+            .collect::<Vec<_>>()
+            .join(" | ");
+        vec![
+            format!(
+                "{} {}: rounding {}, flush to zero {}, denormals are zero {}, tininess {}, overflow {}, nan {}, ieee rounding {}",
+                self.op.name(),
+                self.format,
+                modes.rounding_mode,
+                modes.flush_to_zero,
+                modes.denormals_are_zero,
+                modes.tininess_mode,
+                modes.overflow_mode,
+                modes.nan_mode,
+                modes.ieee_rounding
+            ),
+            format!("Entry {} holds (result << {}) | flags", index, NUM_FLAG_BITS),
+        ]
+    }
+
+    // A Rust static array named name, of the smallest unsigned type that fits a word
+    pub fn write_rust<W: Write>(&self, writer: &mut W, name: &str) -> io::Result<()> {
+        const WORDS_PER_LINE: usize = 8;
+
+        for line in self.describe() {
+            writeln!(writer, "// {}", line)?;
+        }
+        let num_word_bytes = self.num_word_bytes();
+        writeln!(writer, "pub static {}: [u{}; {}] = [", name, num_word_bytes * 8, self.num_entries())?;
+        for start in (0..self.num_entries()).step_by(WORDS_PER_LINE) {
+            let end = (start + WORDS_PER_LINE).min(self.num_entries());
+            let words = (start..end).map(|index| format!("0x{:0width$x},", self.word(index), width = num_word_bytes * 2)).collect::<Vec<_>>();
+            writeln!(writer, "    {}", words.join(" "))?;
+        }
+        writeln!(writer, "];")
+    }
+
+    // One word per line in hex (padded to whole hex digits), for $readmemh into a ROM of num_entries words
+    pub fn write_readmemh<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        for line in self.describe() {
+            writeln!(writer, "// {}", line)?;
+        }
+        let num_digits = self.num_word_bits().div_ceil(4) as usize;
+        for index in 0..self.num_entries() {
+            writeln!(writer, "{:0width$x}", self.word(index), width = num_digits)?;
+        }
+        Ok(())
+    }
+
+    // Words back to back, little-endian, each as wide as in write_rust, with no header
+    pub fn write_binary<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let num_word_bytes = self.num_word_bytes();
+        let mut bytes = Vec::with_capacity(self.num_entries() * num_word_bytes);
+        for index in 0..self.num_entries() {
+            bytes.extend_from_slice(&self.word(index).to_le_bytes()[..num_word_bytes]);
+        }
+        writer.write_all(&bytes)
+    }
+}
+
+// Tables generated so far, by op, format and modes
+#[derive(Default)]
+pub struct TableCache {
+    tables: Vec<Table>,
+}
+
+impl TableCache {
+    pub fn new() -> TableCache {
+        TableCache { tables: Vec::new() }
+    }
+
+    // The table for op over format under context's modes, generating it if it isn't cached
+    pub fn table(&mut self, op: &Op, format: &Format, context: &Context) -> &Table {
+        let modes = Modes::new(context);
+        let index = match self.tables.iter().position(|table| table.op == *op && table.format == *format && table.modes == modes) {
+            Some(index) => index,
+            None => {
+                self.tables.push(Table::new(op, format, context));
+                self.tables.len() - 1
+            }
+        };
+        &self.tables[index]
+    }
+
+    pub fn num_tables(&self) -> usize {
+        self.tables.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nearest_even() -> Context {
+        Context::new()
+            .with_rounding_mode(RoundingMode::NearestEven)
+            .with_flush_to_zero(false)
+            .with_denormals_are_zero(false)
+    }
+
+    #[test]
+    fn matches_ops() {
+        let e4m3 = Format::ocp_fp8_e4m3();
+        let e2m1 = Format::ocp_fp4_e2m1();
+        let cases = [
+            (Op::Addition, e4m3.clone(), Context::new()),
+            (Op::Multiplication, Format::ocp_fp8_e5m2(), nearest_even().with_overflow_mode(OverflowMode::Saturate)),
+            (Op::FusedMultiplyAdd, e2m1.clone(), nearest_even()),
+            (Op::Conversion(Format::ieee754_single()), e4m3.clone(), Context::new()),
+            (Op::Conversion(e2m1), e4m3, nearest_even().with_nan_mode(NanMode::X86)),
+        ];
+
+        for (op, format, mut context) in cases {
+            let table = Table::new(&op, &format, &context);
+
+            assert_eq!(table.num_entries(), 1 << (op.num_sources() as u32 * format.num_storage_bits()));
+            for index in 0..table.num_entries() {
+                let sources = (0..op.num_sources())
+                    .rev()
+                    .map(|i| (index as u32 >> (i as u32 * format.num_storage_bits())) & format.storage_mask())
+                    .collect::<Vec<_>>();
+                context.clear_flags();
+                let result = op.evaluate(&sources, &format, &mut context).to_bits();
+
+                assert_eq!(table.index(&sources), index);
+                assert_eq!(table.lookup(&sources), (result, context.flags()), "{} {} {:x?}", op.name(), format, sources);
+            }
+        }
+    }
+
+    #[test]
+    fn evaluate() {
+        let table = Table::new(&Op::Addition, &Format::ocp_fp8_e4m3(), &nearest_even());
+        let sources1 = [0x38, 0x7e, 0x7f];
+        let sources2 = [0x38, 0x7e, 0x38];
+        let mut results = [0; 3];
+        let mut flags = [Flags::empty(); 3];
+
+        table.evaluate(&[&sources1, &sources2], &mut results, Some(&mut flags));
+
+        // 1 + 1 = 2, 448 + 448 overflows to NaN (e4m3 has no infinity), NaN + 1 = NaN
+        assert_eq!(results, [0x40, 0x7f, 0x7f]);
+        assert_eq!(flags, [Flags::empty(), Flags::OVERFLOW | Flags::INEXACT, Flags::empty()]);
+        // Bits above the format's are ignored
+        assert_eq!(table.lookup(&[0x138, 0x38]), (0x40, Flags::empty()));
+    }
+
+    #[test]
+    fn cache() {
+        let mut cache = TableCache::new();
+        let f = Format::ocp_fp8_e5m2();
+
+        let result = cache.table(&Op::Multiplication, &f, &Context::new()).lookup(&[0x3c, 0x3d]);
+        let mut context = Context::new();
+        context.raise(Flags::INEXACT);
+        let _ = cache.table(&Op::Multiplication, &f, &context);
+
+        // Flags aren't part of the key, but modes are
+        assert_eq!(cache.num_tables(), 1);
+        let _ = cache.table(&Op::Multiplication, &f, &nearest_even());
+        let _ = cache.table(&Op::Addition, &f, &Context::new());
+        assert_eq!(cache.num_tables(), 3);
+        assert_eq!(result, (0x3d, Flags::empty()));
+    }
+
+    #[test]
+    fn export() {
+        let table = Table::new(&Op::Multiplication, &Format::ocp_fp4_e2m1(), &nearest_even());
+
+        assert_eq!(table.num_word_bits(), 9);
+        // 1.5 * 1.5 = 2.25, rounded to 2
+        assert_eq!(table.lookup(&[0x3, 0x3]), (0x4, Flags::INEXACT));
+        assert_eq!(table.word(0x33), 0x81);
+
+        let mut rust = Vec::new();
+        table.write_rust(&mut rust, "MULTIPLICATION_E2M1").unwrap();
+        let rust = String::from_utf8(rust).unwrap();
+        let lines = rust.lines().collect::<Vec<_>>();
+
+        assert_eq!(
+            lines[..4],
+            [
+                "// multiplication e2m1+finite: rounding nearest_even, flush to zero false, denormals are zero false, tininess before_rounding, overflow ieee, nan canonical, ieee rounding false",
+                "// Entry (source1 << 4) | source2 holds (result << 5) | flags",
+                "pub static MULTIPLICATION_E2M1: [u16; 256] = [",
+                "    0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000, 0x0000,",
+            ]
+        );
+        // 1.5 * (0, 0.5, 1, 1.5, 2, 3, 4, 6): 0.75 is tiny, and 9 overflows to 6 (e2m1 has no infinity)
+        assert_eq!(lines[3 + 0x30 / 8], "    0x0000, 0x0043, 0x0060, 0x0081, 0x00a0, 0x00c1, 0x00e0, 0x00e5,");
+        assert_eq!(lines.len(), 3 + 32 + 1);
+        assert_eq!(lines[35], "];");
+
+        let mut readmemh = Vec::new();
+        table.write_readmemh(&mut readmemh).unwrap();
+        let readmemh = String::from_utf8(readmemh).unwrap();
+        let lines = readmemh.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 2 + 256);
+        assert_eq!(lines[2 + 0x33], "081");
+
+        let mut binary = Vec::new();
+        table.write_binary(&mut binary).unwrap();
+
+        assert_eq!(binary.len(), 2 * 256);
+        assert_eq!(binary[2 * 0x33..2 * 0x34], [0x81, 0x00]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested table over 24 source bits, which exceeds the maximum of 16 bits.")]
+    fn too_many_source_bits() {
+        let _ = Table::new(&Op::FusedMultiplyAdd, &Format::ocp_fp8_e4m3(), &Context::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested table with stochastic rounding, whose results depend on random bits as well as sources.")]
+    fn stochastic_rounding() {
+        let context = Context::new().with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 4 });
+        let _ = Table::new(&Op::Addition, &Format::ocp_fp8_e4m3(), &context);
+    }
+}
@@ -0,0 +1,409 @@
+// Reads Berkeley TestFloat test vectors (as written by testfloat_gen) and runs them through the ops. Each line holds the
+// operands, the expected result and the expected exception flags, all in hex, eg. "3F800000 BF800000 00000000 00" for
+// f32_add. Only f16 and f32 functions that correspond to ops are supported, since softy formats are at most 32 bits.
+
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::context::*;
+use crate::flags::*;
+use crate::format::*;
+use crate::ops;
+use crate::rounding::*;
+use crate::value::*;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TestFloatError {
+    UnsupportedFunction(String),
+    UnsupportedRoundingMode(String),
+    UnsupportedTininessMode(String),
+    InvalidLine { line_number: usize },
+}
+
+impl fmt::Display for TestFloatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TestFloatError::UnsupportedFunction(name) => write!(f, "Unsupported TestFloat function \"{}\".", name),
+            TestFloatError::UnsupportedRoundingMode(name) => write!(f, "Unsupported TestFloat rounding mode \"{}\".", name),
+            TestFloatError::UnsupportedTininessMode(name) => write!(f, "Unsupported TestFloat tininess mode \"{}\".", name),
+            TestFloatError::InvalidLine { line_number } => write!(f, "Invalid test vector on line {}.", line_number),
+        }
+    }
+}
+
+impl error::Error for TestFloatError {}
+
+// A TestFloat function (eg. "f32_mulAdd") in terms of ops and formats
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TestFloatFunction {
+    Addition(Format),
+    // Addition with the second operand negated
+    Subtraction(Format),
+    Multiplication(Format),
+    FusedMultiplyAdd(Format),
+    // From the first format to the second
+    Conversion(Format, Format),
+}
+
+impl TestFloatFunction {
+    pub fn num_sources(&self) -> usize {
+        match self {
+            TestFloatFunction::Conversion(..) => 1,
+            TestFloatFunction::FusedMultiplyAdd(_) => 3,
+            _ => 2,
+        }
+    }
+
+    pub fn source_format(&self) -> &Format {
+        match self {
+            TestFloatFunction::Addition(format)
+            | TestFloatFunction::Subtraction(format)
+            | TestFloatFunction::Multiplication(format)
+            | TestFloatFunction::FusedMultiplyAdd(format)
+            | TestFloatFunction::Conversion(format, _) => format,
+        }
+    }
+
+    pub fn result_format(&self) -> &Format {
+        match self {
+            TestFloatFunction::Conversion(_, format) => format,
+            _ => self.source_format(),
+        }
+    }
+
+    fn evaluate(&self, sources: &[u32], context: &mut Context) -> Value {
+        let source = |i: usize| Value::from_bits(sources[i], self.source_format().clone());
+        match self {
+            TestFloatFunction::Addition(_) => ops::addition::addition_with_context(source(0), source(1), context),
+            TestFloatFunction::Subtraction(_) => {
+                // NaNs keep their sign, so that the NaN chosen by the op is the one TestFloat sees
+                let rhs = source(1);
+                let rhs = if rhs.is_nan() { rhs } else { Value::from_comps(!rhs.sign, rhs.exp, rhs.sig, rhs.format) };
+                ops::addition::addition_with_context(source(0), rhs, context)
+            }
+            TestFloatFunction::Multiplication(_) => ops::multiplication::multiplication_with_context(source(0), source(1), context),
+            TestFloatFunction::FusedMultiplyAdd(_) => {
+                ops::fused_multiply_add::fused_multiply_add_with_context(source(0), source(1), source(2), context)
+            }
+            TestFloatFunction::Conversion(_, format) => ops::conversion::conversion_with_context(source(0), format.clone(), context),
+        }
+    }
+}
+
+fn parse_type(name: &str) -> Option<Format> {
+    match name {
+        "f16" => Some(Format::new(5, 10)),
+        "f32" => Some(Format::ieee754_single()),
+        _ => None,
+    }
+}
+
+impl FromStr for TestFloatFunction {
+    type Err = TestFloatError;
+
+    fn from_str(s: &str) -> Result<TestFloatFunction, TestFloatError> {
+        let unsupported = || TestFloatError::UnsupportedFunction(s.to_string());
+
+        let (source_type, operation) = s.split_once('_').ok_or_else(unsupported)?;
+        let format = parse_type(source_type).ok_or_else(unsupported)?;
+        match operation {
+            "add" => Ok(TestFloatFunction::Addition(format)),
+            "sub" => Ok(TestFloatFunction::Subtraction(format)),
+            "mul" => Ok(TestFloatFunction::Multiplication(format)),
+            "mulAdd" => Ok(TestFloatFunction::FusedMultiplyAdd(format)),
+            _ => {
+                let target_type = operation.strip_prefix("to_").ok_or_else(unsupported)?;
+                let target = parse_type(target_type).ok_or_else(unsupported)?;
+                Ok(TestFloatFunction::Conversion(format, target))
+            }
+        }
+    }
+}
+
+// Maps a testfloat_gen rounding mode option (eg. "-rnear_even", or just "near_even") to a rounding mode
+pub fn parse_rounding_mode(option: &str) -> Result<RoundingMode, TestFloatError> {
+    match option.strip_prefix("-r").unwrap_or(option) {
+        "near_even" => Ok(RoundingMode::NearestEven),
+        "near_maxMag" => Ok(RoundingMode::NearestAway),
+        "minMag" => Ok(RoundingMode::TowardZero),
+        "max" => Ok(RoundingMode::TowardPositive),
+        "min" => Ok(RoundingMode::TowardNegative),
+        _ => Err(TestFloatError::UnsupportedRoundingMode(option.to_string())),
+    }
+}
+
+// Maps a testfloat_gen tininess option (eg. "-tininessafter", or just "after") to a tininess mode
+pub fn parse_tininess_mode(option: &str) -> Result<TininessMode, TestFloatError> {
+    match option.strip_prefix("-tininess").unwrap_or(option) {
+        "before" => Ok(TininessMode::BeforeRounding),
+        "after" => Ok(TininessMode::AfterRounding),
+        _ => Err(TestFloatError::UnsupportedTininessMode(option.to_string())),
+    }
+}
+
+// A single test case, with the (1-based) line it was read from
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TestVector {
+    pub(crate) line_number: usize,
+    pub(crate) sources: Vec<u32>,
+    pub(crate) result: u32,
+    pub(crate) flags: Flags,
+}
+
+impl TestVector {
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    pub fn sources(&self) -> &[u32] {
+        &self.sources
+    }
+
+    pub fn result(&self) -> u32 {
+        self.result
+    }
+
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+}
+
+// Parses every (non-blank) line of testfloat_gen's output for function
+pub fn parse_test_vectors(s: &str, function: &TestFloatFunction) -> Result<Vec<TestVector>, TestFloatError> {
+    let parse_hex = |token: &str, num_bits: u32| -> Option<u32> {
+        let bits = u32::from_str_radix(token, 16).ok()?;
+        if (bits as u64) >> num_bits != 0 {
+            return None;
+        }
+        Some(bits)
+    };
+
+    let mut vectors = Vec::new();
+    for (i, line) in s.lines().enumerate() {
+        let line_number = i + 1;
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        if tokens.is_empty() {
+            continue;
+        }
+
+        let num_sources = function.num_sources();
+        let invalid_line = TestFloatError::InvalidLine { line_number };
+        if tokens.len() != num_sources + 2 {
+            return Err(invalid_line);
+        }
+
+        let source_bits = function.source_format().num_storage_bits();
+        let sources = tokens[..num_sources]
+            .iter()
+            .map(|token| parse_hex(token, source_bits))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid_line.clone())?;
+        let result = parse_hex(tokens[num_sources], function.result_format().num_storage_bits()).ok_or_else(|| invalid_line.clone())?;
+        let flags = parse_hex(tokens[num_sources + 1], 8).ok_or(invalid_line)?;
+
+        vectors.push(TestVector {
+            line_number,
+            sources,
+            result,
+            flags: Flags::from_bits(flags),
+        });
+    }
+    Ok(vectors)
+}
+
+// A test vector whose result or flags softy doesn't reproduce
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TestFloatMismatch {
+    pub(crate) function: TestFloatFunction,
+    pub(crate) vector: TestVector,
+    pub(crate) result: u32,
+    pub(crate) flags: Flags,
+}
+
+impl TestFloatMismatch {
+    pub fn vector(&self) -> &TestVector {
+        &self.vector
+    }
+
+    pub fn result(&self) -> u32 {
+        self.result
+    }
+
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+}
+
+impl fmt::Display for TestFloatMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: ", self.vector.line_number)?;
+        for (i, &source) in self.vector.sources.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", Value::from_bits(source, self.function.source_format().clone()).to_fields_string())?;
+        }
+        write!(f, ": expected ")?;
+        write!(f, "{}", Value::from_bits(self.vector.result, self.function.result_format().clone()).to_fields_string())?;
+        write!(f, " {:?}, got ", self.vector.flags)?;
+        write!(f, "{}", Value::from_bits(self.result, self.function.result_format().clone()).to_fields_string())?;
+        write!(f, " {:?}", self.flags)
+    }
+}
+
+// Runs vectors through function with IEEE semantics (IEEE rounding, no DAZ/FTZ), in the rounding and tininess modes the vectors were
+// generated with. As with TestFloat's default, any NaN result matches any expected NaN.
+pub fn run_test_vectors(
+    function: &TestFloatFunction,
+    vectors: &[TestVector],
+    rounding_mode: RoundingMode,
+    tininess_mode: TininessMode,
+) -> Vec<TestFloatMismatch> {
+    let mut context = Context::new()
+        .with_rounding_mode(rounding_mode)
+        .with_tininess_mode(tininess_mode)
+        .with_flush_to_zero(false)
+        .with_denormals_are_zero(false)
+        .with_ieee_rounding(true);
+
+    let mut mismatches = Vec::new();
+    for vector in vectors {
+        context.clear_flags();
+        let res = function.evaluate(&vector.sources, &mut context);
+
+        let expected = Value::from_bits(vector.result, function.result_format().clone());
+        let is_result_match = res.to_bits() == vector.result || (res.is_nan() && expected.is_nan());
+        if !is_result_match || context.flags() != vector.flags {
+            mismatches.push(TestFloatMismatch {
+                function: function.clone(),
+                vector: vector.clone(),
+                result: res.to_bits(),
+                flags: context.flags(),
+            });
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_function() {
+        assert_eq!("f32_add".parse(), Ok(TestFloatFunction::Addition(Format::ieee754_single())));
+        assert_eq!("f16_sub".parse(), Ok(TestFloatFunction::Subtraction(Format::new(5, 10))));
+        assert_eq!("f32_mul".parse(), Ok(TestFloatFunction::Multiplication(Format::ieee754_single())));
+        assert_eq!("f16_mulAdd".parse(), Ok(TestFloatFunction::FusedMultiplyAdd(Format::new(5, 10))));
+        assert_eq!("f32_to_f16".parse(), Ok(TestFloatFunction::Conversion(Format::ieee754_single(), Format::new(5, 10))));
+
+        for &name in &["f64_add", "f32_div", "f32_to_i32", "f32", "add"] {
+            assert_eq!(name.parse::<TestFloatFunction>(), Err(TestFloatError::UnsupportedFunction(name.to_string())));
+        }
+    }
+
+    #[test]
+    fn parse_modes() {
+        assert_eq!(parse_rounding_mode("-rnear_even"), Ok(RoundingMode::NearestEven));
+        assert_eq!(parse_rounding_mode("near_maxMag"), Ok(RoundingMode::NearestAway));
+        assert_eq!(parse_rounding_mode("-rminMag"), Ok(RoundingMode::TowardZero));
+        assert_eq!(parse_rounding_mode("-rmax"), Ok(RoundingMode::TowardPositive));
+        assert_eq!(parse_rounding_mode("-rmin"), Ok(RoundingMode::TowardNegative));
+        assert_eq!(parse_rounding_mode("-rodd"), Err(TestFloatError::UnsupportedRoundingMode("-rodd".to_string())));
+
+        assert_eq!(parse_tininess_mode("-tininessbefore"), Ok(TininessMode::BeforeRounding));
+        assert_eq!(parse_tininess_mode("after"), Ok(TininessMode::AfterRounding));
+        assert!(parse_tininess_mode("-tininessduring").is_err());
+    }
+
+    #[test]
+    fn parse_test_vectors_lines() {
+        let function = TestFloatFunction::Addition(Format::ieee754_single());
+
+        let vectors = parse_test_vectors("3F800000 BF800000 00000000 00\n\n00000001 807FFFFF 807FFFFE 00\r\n", &function).unwrap();
+
+        assert_eq!(vectors.len(), 2);
+        assert_eq!(vectors[0].sources(), &[0x3f800000, 0xbf800000]);
+        assert_eq!(vectors[1].line_number(), 3);
+        assert_eq!(vectors[1].result(), 0x807ffffe);
+        assert!(vectors[1].flags().is_empty());
+
+        for &s in &["3F800000 BF800000 00000000", "3F800000 BF800000 00000000 00 00", "3F80000G BF800000 00000000 00", "1 2 3 100"] {
+            assert_eq!(parse_test_vectors(s, &function), Err(TestFloatError::InvalidLine { line_number: 1 }), "{}", s);
+        }
+        assert_eq!(
+            parse_test_vectors("3C00 3C00 4000 00", &TestFloatFunction::Addition(Format::new(5, 10))).unwrap()[0].result(),
+            0x4000
+        );
+        assert_eq!(
+            parse_test_vectors("13C00 3C00 4000 00", &TestFloatFunction::Addition(Format::new(5, 10))),
+            Err(TestFloatError::InvalidLine { line_number: 1 })
+        );
+    }
+
+    #[test]
+    fn run_test_vectors_modes() {
+        // Vectors as testfloat_gen writes them for each mode
+        let f32_add = TestFloatFunction::Addition(Format::ieee754_single());
+        let vectors = parse_test_vectors(
+            "3F800000 33800000 3F800000 01\n\
+             00000001 807FFFFF 807FFFFE 00\n\
+             3F800000 BF800000 00000000 00\n\
+             7F7FFFFF 7F7FFFFF 7F800000 05\n\
+             7F800000 FF800000 7FC00000 10\n\
+             7F800001 3F800000 7FC00001 10\n",
+            &f32_add,
+        )
+        .unwrap();
+
+        assert!(run_test_vectors(&f32_add, &vectors, RoundingMode::NearestEven, TininessMode::AfterRounding).is_empty());
+
+        let vectors = parse_test_vectors("3F800000 33800000 3F800001 01\n3F800000 BF800000 80000000 00\n7F7FFFFF 7F7FFFFF 7F7FFFFF 05\n", &f32_add).unwrap();
+
+        assert_eq!(run_test_vectors(&f32_add, &vectors, RoundingMode::TowardNegative, TininessMode::AfterRounding).len(), 1);
+        assert!(run_test_vectors(&f32_add, &vectors[..1], RoundingMode::TowardPositive, TininessMode::AfterRounding).is_empty());
+        assert!(run_test_vectors(&f32_add, &vectors[1..], RoundingMode::TowardNegative, TininessMode::AfterRounding).is_empty());
+
+        // (1 + 2^-23) * 2^-126 * (1 - 2^-23) rounds to the smallest normal, which only underflows when tininess is
+        // before rounding
+        let f32_mul = TestFloatFunction::Multiplication(Format::ieee754_single());
+        let before = parse_test_vectors("3F800001 007FFFFF 00800000 03", &f32_mul).unwrap();
+        let after = parse_test_vectors("3F800001 007FFFFF 00800000 01", &f32_mul).unwrap();
+
+        assert!(run_test_vectors(&f32_mul, &before, RoundingMode::NearestEven, TininessMode::BeforeRounding).is_empty());
+        assert!(run_test_vectors(&f32_mul, &after, RoundingMode::NearestEven, TininessMode::AfterRounding).is_empty());
+        assert_eq!(run_test_vectors(&f32_mul, &after, RoundingMode::NearestEven, TininessMode::BeforeRounding).len(), 1);
+
+        let f16_sub = TestFloatFunction::Subtraction(Format::new(5, 10));
+        let vectors = parse_test_vectors("3C00 3C00 0000 00\n0001 8001 0002 00\n3C00 7E00 7E00 00\n", &f16_sub).unwrap();
+
+        assert!(run_test_vectors(&f16_sub, &vectors, RoundingMode::NearestEven, TininessMode::AfterRounding).is_empty());
+
+        let f32_to_f16 = TestFloatFunction::Conversion(Format::ieee754_single(), Format::new(5, 10));
+        let vectors = parse_test_vectors("3F800000 3C00 00\n477FF000 7C00 05\n33000001 0001 03\n", &f32_to_f16).unwrap();
+
+        assert!(run_test_vectors(&f32_to_f16, &vectors, RoundingMode::NearestEven, TininessMode::AfterRounding).is_empty());
+
+        let f16_mul_add = TestFloatFunction::FusedMultiplyAdd(Format::new(5, 10));
+        let vectors = parse_test_vectors("3C00 3C00 BC00 0000 00\n7C00 0000 3C00 7E00 10\n", &f16_mul_add).unwrap();
+
+        assert!(run_test_vectors(&f16_mul_add, &vectors, RoundingMode::NearestEven, TininessMode::AfterRounding).is_empty());
+    }
+
+    #[test]
+    fn mismatch_display() {
+        let f32_add = TestFloatFunction::Addition(Format::ieee754_single());
+        let vectors = parse_test_vectors("3F800000 33800001 3F800001 01", &f32_add).unwrap();
+
+        let mismatches = run_test_vectors(&f32_add, &vectors, RoundingMode::TowardZero, TininessMode::AfterRounding);
+
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(
+            mismatches[0].to_string(),
+            "line 1: 0x3f800000 (sign 0, exp 0x7f, sig 0x000000), 0x33800001 (sign 0, exp 0x67, sig 0x000001): \
+             expected 0x3f800001 (sign 0, exp 0x7f, sig 0x000001) Flags(INEXACT), \
+             got 0x3f800000 (sign 0, exp 0x7f, sig 0x000000) Flags(INEXACT)"
+        );
+    }
+}
@@ -0,0 +1,563 @@
+// Records op invocations as JSON lines, and replays them against the ops. Each line is one flat object holding the op,
+// the format, the source encodings, the context's modes, and the result encoding and flags, eg.
+//
+// {"op":"addition","format":"e8m23","sources":["3f800000","3f800000"],"rounding_mode":"nearest_even",
+//  "flush_to_zero":false,"denormals_are_zero":false,"tininess_mode":"before_rounding","overflow_mode":"ieee",
+//  "nan_mode":"canonical","ieee_rounding":true,"result":"40000000","flags":"00"}
+//
+// (on a single line). Conversions add the target format ("target_format":"e4m3fn"), and stochastic rounding
+// ("rounding_mode":"stochastic8" for 8 random bits) adds the bits drawn ("random_bits":"a5") so that replay is exact.
+// Encodings and flags are hex, as in export; flags use SoftFloat's bit positions.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::context::*;
+use crate::flags::*;
+use crate::format::*;
+use crate::ops::*;
+use crate::rounding::*;
+use crate::value::*;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TraceError {
+    InvalidLine { line_number: usize },
+    InvalidField { line_number: usize, field: &'static str },
+}
+
+impl fmt::Display for TraceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TraceError::InvalidLine { line_number } => write!(f, "Invalid trace record on line {}.", line_number),
+            TraceError::InvalidField { line_number, field } => {
+                write!(f, "Invalid or missing \"{}\" in trace record on line {}.", field, line_number)
+            }
+        }
+    }
+}
+
+impl error::Error for TraceError {}
+
+// One op invocation: what was asked, under which modes, and what came back
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TraceRecord {
+    pub(crate) op: Op,
+    pub(crate) format: Format,
+    pub(crate) sources: Vec<u32>,
+    pub(crate) rounding_mode: RoundingMode,
+    pub(crate) flush_to_zero: bool,
+    pub(crate) denormals_are_zero: bool,
+    pub(crate) tininess_mode: TininessMode,
+    pub(crate) overflow_mode: OverflowMode,
+    pub(crate) nan_mode: NanMode,
+    pub(crate) ieee_rounding: bool,
+    pub(crate) random_bits: Option<u32>,
+    pub(crate) result: u32,
+    pub(crate) flags: Flags,
+}
+
+impl TraceRecord {
+    pub fn op(&self) -> &Op {
+        &self.op
+    }
+
+    pub fn format(&self) -> &Format {
+        &self.format
+    }
+
+    pub fn sources(&self) -> &[u32] {
+        &self.sources
+    }
+
+    pub fn rounding_mode(&self) -> RoundingMode {
+        self.rounding_mode
+    }
+
+    pub fn flush_to_zero(&self) -> bool {
+        self.flush_to_zero
+    }
+
+    pub fn denormals_are_zero(&self) -> bool {
+        self.denormals_are_zero
+    }
+
+    pub fn tininess_mode(&self) -> TininessMode {
+        self.tininess_mode
+    }
+
+    pub fn overflow_mode(&self) -> OverflowMode {
+        self.overflow_mode
+    }
+
+    pub fn nan_mode(&self) -> NanMode {
+        self.nan_mode
+    }
+
+    pub fn ieee_rounding(&self) -> bool {
+        self.ieee_rounding
+    }
+
+    // The bits drawn for stochastic rounding, if any were
+    pub fn random_bits(&self) -> Option<u32> {
+        self.random_bits
+    }
+
+    pub fn result(&self) -> u32 {
+        self.result
+    }
+
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+
+    // A context with the recorded modes (and no flags raised), whose random source repeats the recorded bits (or zeros,
+    // if none were recorded)
+    pub fn context(&self) -> Context {
+        let context = Context::new()
+            .with_rounding_mode(self.rounding_mode)
+            .with_flush_to_zero(self.flush_to_zero)
+            .with_denormals_are_zero(self.denormals_are_zero)
+            .with_tininess_mode(self.tininess_mode)
+            .with_overflow_mode(self.overflow_mode)
+            .with_nan_mode(self.nan_mode)
+            .with_ieee_rounding(self.ieee_rounding);
+        let random_bits = self.random_bits.unwrap_or(0);
+        context.with_random_source(move |_| random_bits)
+    }
+}
+
+impl fmt::Display for TraceRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{{\"op\":\"{}\",\"format\":\"{}\",", self.op.name(), self.format)?;
+        if let Op::Conversion(target) = &self.op {
+            write!(f, "\"target_format\":\"{}\",", target)?;
+        }
+        write!(f, "\"sources\":[")?;
+        for (i, &source) in self.sources.iter().enumerate() {
+            if i != 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "\"{:0width$x}\"", source, width = self.format.num_storage_bits().div_ceil(4) as usize)?;
+        }
+        write!(
+            f,
+            "],\"rounding_mode\":\"{}\",\"flush_to_zero\":{},\"denormals_are_zero\":{},\"tininess_mode\":\"{}\",\"overflow_mode\":\"{}\",\"nan_mode\":\"{}\",\"ieee_rounding\":{},",
+            self.rounding_mode,
+            self.flush_to_zero,
+            self.denormals_are_zero,
+            self.tininess_mode,
+            self.overflow_mode,
+            self.nan_mode,
+            self.ieee_rounding,
+        )?;
+        if let Some(random_bits) = self.random_bits {
+            write!(f, "\"random_bits\":\"{:x}\",", random_bits)?;
+        }
+        let result_format = self.op.result_format(&self.format);
+        write!(
+            f,
+            "\"result\":\"{:0width$x}\",\"flags\":\"{:02x}\"}}",
+            self.result,
+            self.flags.bits(),
+            width = result_format.num_storage_bits().div_ceil(4) as usize
+        )
+    }
+}
+
+// A JSON value, limited to what trace records use
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum JsonValue {
+    String(String),
+    Bool(bool),
+    Strings(Vec<String>),
+}
+
+// Parses a flat JSON object whose values are strings (without escapes), bools or arrays of strings
+fn parse_json_object(s: &str) -> Option<Vec<(String, JsonValue)>> {
+    fn skip_whitespace(s: &str) -> &str {
+        s.trim_start()
+    }
+
+    fn parse_string(s: &str) -> Option<(String, &str)> {
+        let s = skip_whitespace(s).strip_prefix('"')?;
+        let end = s.find('"')?;
+        let string = &s[..end];
+        if string.contains('\\') {
+            return None;
+        }
+        Some((string.to_string(), &s[end + 1..]))
+    }
+
+    let mut s = skip_whitespace(s).strip_prefix('{')?;
+    let mut fields = Vec::new();
+    if let Some(rest) = skip_whitespace(s).strip_prefix('}') {
+        return if skip_whitespace(rest).is_empty() { Some(fields) } else { None };
+    }
+    loop {
+        let (key, rest) = parse_string(s)?;
+        let rest = skip_whitespace(rest).strip_prefix(':')?;
+        let rest = skip_whitespace(rest);
+        let (value, rest) = if let Some(rest) = rest.strip_prefix("true") {
+            (JsonValue::Bool(true), rest)
+        } else if let Some(rest) = rest.strip_prefix("false") {
+            (JsonValue::Bool(false), rest)
+        } else if let Some(mut rest) = rest.strip_prefix('[') {
+            let mut strings = Vec::new();
+            if let Some(after) = skip_whitespace(rest).strip_prefix(']') {
+                rest = after;
+            } else {
+                loop {
+                    let (string, after) = parse_string(rest)?;
+                    strings.push(string);
+                    let after = skip_whitespace(after);
+                    if let Some(after) = after.strip_prefix(',') {
+                        rest = after;
+                    } else {
+                        rest = after.strip_prefix(']')?;
+                        break;
+                    }
+                }
+            }
+            (JsonValue::Strings(strings), rest)
+        } else {
+            let (string, rest) = parse_string(rest)?;
+            (JsonValue::String(string), rest)
+        };
+        fields.push((key, value));
+
+        let rest = skip_whitespace(rest);
+        if let Some(rest) = rest.strip_prefix(',') {
+            s = rest;
+        } else {
+            let rest = rest.strip_prefix('}')?;
+            return if skip_whitespace(rest).is_empty() { Some(fields) } else { None };
+        }
+    }
+}
+
+fn parse_hex(s: &str, num_bits: u32) -> Option<u32> {
+    let bits = u32::from_str_radix(s, 16).ok()?;
+    if (bits as u64) >> num_bits != 0 {
+        return None;
+    }
+    Some(bits)
+}
+
+// Parses a single record; line_number only labels errors
+fn parse_record(s: &str, line_number: usize) -> Result<TraceRecord, TraceError> {
+    let fields = parse_json_object(s).ok_or(TraceError::InvalidLine { line_number })?;
+    let invalid = |field| TraceError::InvalidField { line_number, field };
+    let find = |field: &'static str| fields.iter().find(|(key, _)| key == field).map(|(_, value)| value);
+    let string = |field: &'static str| match find(field) {
+        Some(JsonValue::String(string)) => Ok(string.as_str()),
+        _ => Err(invalid(field)),
+    };
+    let bool = |field: &'static str| match find(field) {
+        Some(&JsonValue::Bool(value)) => Ok(value),
+        _ => Err(invalid(field)),
+    };
+
+    let format = string("format")?.parse::<Format>().map_err(|_| invalid("format"))?;
+    // Conversions keep their target in a field of its own
+    let op = match string("op")? {
+        "conversion" => format!("conversion:{}", string("target_format")?),
+        op if op.contains(':') => return Err(invalid("op")),
+        op => op.to_string(),
+    };
+    let op = op.parse::<Op>().map_err(|error| match error {
+        ParseOpError::UnknownOp(_) => invalid("op"),
+        ParseOpError::InvalidTargetFormat(_) => invalid("target_format"),
+    })?;
+
+    let sources = match find("sources") {
+        Some(JsonValue::Strings(sources)) if sources.len() == op.num_sources() => sources
+            .iter()
+            .map(|source| parse_hex(source, format.num_storage_bits()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| invalid("sources"))?,
+        _ => return Err(invalid("sources")),
+    };
+
+    let rounding_mode = string("rounding_mode")?.parse().map_err(|_| invalid("rounding_mode"))?;
+    let random_bits = match (rounding_mode, find("random_bits")) {
+        (RoundingMode::Stochastic { num_random_bits }, Some(_)) => {
+            Some(parse_hex(string("random_bits")?, num_random_bits).ok_or_else(|| invalid("random_bits"))?)
+        }
+        (_, None) => None,
+        (_, Some(_)) => return Err(invalid("random_bits")),
+    };
+
+    Ok(TraceRecord {
+        sources,
+        rounding_mode,
+        flush_to_zero: bool("flush_to_zero")?,
+        denormals_are_zero: bool("denormals_are_zero")?,
+        tininess_mode: string("tininess_mode")?.parse().map_err(|_| invalid("tininess_mode"))?,
+        overflow_mode: string("overflow_mode")?.parse().map_err(|_| invalid("overflow_mode"))?,
+        nan_mode: string("nan_mode")?.parse().map_err(|_| invalid("nan_mode"))?,
+        ieee_rounding: bool("ieee_rounding")?,
+        random_bits,
+        result: parse_hex(string("result")?, op.result_format(&format).num_storage_bits()).ok_or_else(|| invalid("result"))?,
+        flags: Flags::from_bits(parse_hex(string("flags")?, 8).ok_or_else(|| invalid("flags"))?),
+        op,
+        format,
+    })
+}
+
+impl FromStr for TraceRecord {
+    type Err = TraceError;
+
+    fn from_str(s: &str) -> Result<TraceRecord, TraceError> {
+        parse_record(s, 1)
+    }
+}
+
+// Parses every (non-blank) line of a trace
+pub fn parse_trace(s: &str) -> Result<Vec<TraceRecord>, TraceError> {
+    s.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| parse_record(line, i + 1))
+        .collect()
+}
+
+// Evaluates ops like Op::evaluate, writing a record of each invocation to writer
+pub struct Tracer<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> Tracer<W> {
+    pub fn new(writer: W) -> Tracer<W> {
+        Tracer { writer }
+    }
+
+    // Records the flags this invocation raised (on top of any already raised in context, which are kept)
+    pub fn evaluate(&mut self, op: &Op, sources: &[u32], format: &Format, context: &mut Context) -> io::Result<Value> {
+        let previous_flags = context.flags();
+        context.clear_flags();
+        context.last_random_bits = None;
+
+        let result = op.evaluate(sources, format, context);
+
+        let record = TraceRecord {
+            op: op.clone(),
+            format: format.clone(),
+            sources: sources.to_vec(),
+            rounding_mode: context.rounding_mode(),
+            flush_to_zero: context.flush_to_zero(),
+            denormals_are_zero: context.denormals_are_zero(),
+            tininess_mode: context.tininess_mode(),
+            overflow_mode: context.overflow_mode(),
+            nan_mode: context.nan_mode(),
+            ieee_rounding: context.ieee_rounding(),
+            random_bits: context.last_random_bits,
+            result: result.to_bits(),
+            flags: context.flags(),
+        };
+        context.raise(previous_flags);
+        writeln!(self.writer, "{}", record)?;
+
+        Ok(result)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+// A record whose result or flags the ops don't reproduce
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TraceMismatch {
+    pub(crate) line_number: usize,
+    pub(crate) record: TraceRecord,
+    pub(crate) result: u32,
+    pub(crate) flags: Flags,
+}
+
+impl TraceMismatch {
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    pub fn record(&self) -> &TraceRecord {
+        &self.record
+    }
+
+    pub fn result(&self) -> u32 {
+        self.result
+    }
+
+    pub fn flags(&self) -> Flags {
+        self.flags
+    }
+}
+
+impl fmt::Display for TraceMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let record = &self.record;
+        write!(f, "line {}: {}(", self.line_number, record.op.name())?;
+        for (i, &source) in record.sources.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", Value::from_bits(source, record.format.clone()).to_fields_string())?;
+        }
+        let result_format = record.op.result_format(&record.format);
+        write!(f, ") with {}: expected ", record.rounding_mode)?;
+        write!(f, "{}", Value::from_bits(record.result, result_format.clone()).to_fields_string())?;
+        write!(f, " {:?}, got ", record.flags)?;
+        write!(f, "{}", Value::from_bits(self.result, result_format.clone()).to_fields_string())?;
+        write!(f, " {:?}", self.flags)
+    }
+}
+
+// Re-executes every record of a trace (eg. one captured from an emulator or written by RTL) under its recorded modes,
+// returning those whose result encoding or flags differ
+pub fn replay_trace(s: &str) -> Result<Vec<TraceMismatch>, TraceError> {
+    let mut mismatches = Vec::new();
+    for (i, line) in s.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let line_number = i + 1;
+        let record = parse_record(line, line_number)?;
+        let mut context = record.context();
+        let result = record.op.evaluate(&record.sources, &record.format, &mut context);
+        if result.to_bits() != record.result || context.flags() != record.flags {
+            mismatches.push(TraceMismatch { line_number, record, result: result.to_bits(), flags: context.flags() });
+        }
+    }
+    Ok(mismatches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::verify::*;
+
+    fn nearest_even() -> Context {
+        Context::new()
+            .with_rounding_mode(RoundingMode::NearestEven)
+            .with_flush_to_zero(false)
+            .with_denormals_are_zero(false)
+            .with_ieee_rounding(true)
+    }
+
+    #[test]
+    fn record() {
+        let f = Format::ieee754_single();
+        let mut tracer = Tracer::new(Vec::new());
+        let mut context = nearest_even();
+        context.raise(Flags::INVALID);
+
+        let res = tracer.evaluate(&Op::Addition, &[0x3f800000, 0x33000000], &f, &mut context).unwrap();
+
+        assert_eq!(res.to_bits(), 0x3f800000);
+        assert_eq!(context.flags(), Flags::INVALID | Flags::INEXACT);
+        assert_eq!(
+            String::from_utf8(tracer.into_inner()).unwrap(),
+            concat!(
+                "{\"op\":\"addition\",\"format\":\"e8m23\",\"sources\":[\"3f800000\",\"33000000\"],\"rounding_mode\":\"nearest_even\",",
+                "\"flush_to_zero\":false,\"denormals_are_zero\":false,\"tininess_mode\":\"before_rounding\",\"overflow_mode\":\"ieee\",",
+                "\"nan_mode\":\"canonical\",\"ieee_rounding\":true,\"result\":\"3f800000\",\"flags\":\"01\"}\n"
+            )
+        );
+    }
+
+    #[test]
+    fn record_stochastic_and_conversion() {
+        let f = Format::new(5, 10);
+        let mut tracer = Tracer::new(Vec::new());
+        let mut context = nearest_even().with_rounding_mode(RoundingMode::Stochastic { num_random_bits: 8 }).with_random_source(|_| 0x1a5);
+
+        tracer.evaluate(&Op::Multiplication, &[0x3c01, 0x3c01], &f, &mut context).unwrap();
+        tracer.evaluate(&Op::Conversion(Format::ocp_fp8_e4m3()), &[0x3c00], &f, &mut context).unwrap();
+
+        let trace = String::from_utf8(tracer.into_inner()).unwrap();
+        let records = parse_trace(&trace).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].rounding_mode(), RoundingMode::Stochastic { num_random_bits: 8 });
+        assert_eq!(records[0].random_bits(), Some(0xa5));
+        assert_eq!(records[1].op(), &Op::Conversion(Format::ocp_fp8_e4m3()));
+        assert_eq!(records[1].random_bits(), Some(0xa5)); // Drawn, even though 1.0 is exact
+        assert_eq!(records[1].result(), 0x38);
+        assert!(trace.contains("\"target_format\":\"e4m3fn\""));
+
+        // Records print back to the same line
+        assert_eq!(records.iter().map(|record| format!("{}\n", record)).collect::<String>(), trace);
+    }
+
+    #[test]
+    fn replay() {
+        let f = Format::new(5, 10);
+        let mut tracer = Tracer::new(Vec::new());
+        let mut random = Random::new(42);
+        for &nan_mode in &[NanMode::Canonical, NanMode::X86] {
+            for &rounding_mode in &[RoundingMode::NearestEven, RoundingMode::TowardNegative, RoundingMode::Stochastic { num_random_bits: 4 }] {
+                let mut context = Context::new().with_rounding_mode(rounding_mode).with_nan_mode(nan_mode).with_random_source(|_| 0x9);
+                for _ in 0..100 {
+                    let sources = [random.next_u32() & 0xffff, random.next_u32() & 0xffff, random.next_u32() & 0xffff];
+                    tracer.evaluate(&Op::FusedMultiplyAdd, &sources, &f, &mut context).unwrap();
+                    tracer.evaluate(&Op::Addition, &sources[..2], &f, &mut context).unwrap();
+                }
+            }
+        }
+        let trace = String::from_utf8(tracer.into_inner()).unwrap();
+
+        assert!(replay_trace(&trace).unwrap().is_empty());
+
+        // As if from RTL, with a wrong result on line 2
+        let trace = concat!(
+            "{\"op\":\"addition\",\"format\":\"e5m10\",\"sources\":[\"3c00\",\"3c00\"],\"rounding_mode\":\"nearest_even\",\"flush_to_zero\":false,\"denormals_are_zero\":false,\"tininess_mode\":\"before_rounding\",\"overflow_mode\":\"ieee\",\"nan_mode\":\"canonical\",\"ieee_rounding\":true,\"result\":\"4000\",\"flags\":\"00\"}\n",
+            "{ \"op\": \"addition\", \"format\": \"e5m10\", \"sources\": [\"7bff\", \"7bff\"], \"rounding_mode\": \"toward_zero\", \"flush_to_zero\": true, \"denormals_are_zero\": true, \"tininess_mode\": \"before_rounding\", \"overflow_mode\": \"ieee\", \"nan_mode\": \"canonical\", \"ieee_rounding\": true, \"result\": \"7c00\", \"flags\": \"05\" }\n",
+            "\n",
+        );
+        let mismatches = replay_trace(trace).unwrap();
+
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].line_number(), 2);
+        assert_eq!((mismatches[0].result(), mismatches[0].flags()), (0x7bff, Flags::OVERFLOW | Flags::INEXACT));
+        assert_eq!(
+            mismatches[0].to_string(),
+            "line 2: addition(0x7bff (sign 0, exp 0x1e, sig 0x3ff), 0x7bff (sign 0, exp 0x1e, sig 0x3ff)) with toward_zero: expected 0x7c00 (sign 0, exp 0x1f, sig 0x000) Flags(INEXACT | OVERFLOW), got 0x7bff (sign 0, exp 0x1e, sig 0x3ff) Flags(INEXACT | OVERFLOW)"
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        let line = "{\"op\":\"addition\",\"format\":\"e5m10\",\"sources\":[\"3c00\",\"3c00\"],\"rounding_mode\":\"nearest_even\",\"flush_to_zero\":false,\"denormals_are_zero\":false,\"tininess_mode\":\"before_rounding\",\"overflow_mode\":\"ieee\",\"nan_mode\":\"canonical\",\"ieee_rounding\":true,\"result\":\"4000\",\"flags\":\"00\"}";
+
+        assert!(line.parse::<TraceRecord>().is_ok());
+        assert_eq!(parse_trace(&format!("{}\n{{", line)), Err(TraceError::InvalidLine { line_number: 2 }));
+        assert_eq!(
+            line.replace("\"3c00\",\"3c00\"", "\"3c00\"").parse::<TraceRecord>(),
+            Err(TraceError::InvalidField { line_number: 1, field: "sources" })
+        );
+        assert_eq!(
+            line.replace("\"4000\"", "\"14000\"").parse::<TraceRecord>(),
+            Err(TraceError::InvalidField { line_number: 1, field: "result" })
+        );
+        assert_eq!(
+            line.replace("nearest_even", "stochastic33").parse::<TraceRecord>(),
+            Err(TraceError::InvalidField { line_number: 1, field: "rounding_mode" })
+        );
+        assert_eq!(
+            line.replace(",\"flush_to_zero\":false", "").parse::<TraceRecord>(),
+            Err(TraceError::InvalidField { line_number: 1, field: "flush_to_zero" })
+        );
+        assert_eq!(
+            line.replace("\"flags\":\"00\"", "\"flags\":\"00\",\"random_bits\":\"1\"").parse::<TraceRecord>(),
+            Err(TraceError::InvalidField { line_number: 1, field: "random_bits" })
+        );
+        assert_eq!(
+            TraceError::InvalidField { line_number: 3, field: "format" }.to_string(),
+            "Invalid or missing \"format\" in trace record on line 3."
+        );
+    }
+}
@@ -1,5 +1,26 @@
 use crate::format::*;
 
+// Value classes, ordered as the bits of the RISC-V fclass result
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Class {
+    NegativeInfinity,
+    NegativeNormal,
+    NegativeSubnormal,
+    NegativeZero,
+    PositiveZero,
+    PositiveSubnormal,
+    PositiveNormal,
+    PositiveInfinity,
+    SignalingNan,
+    QuietNan,
+}
+
+impl Class {
+    pub fn fclass_mask(self) -> u32 {
+        1 << (self as u32)
+    }
+}
+
 pub struct Value {
     pub(crate) sign: bool,
     pub(crate) exp: u32,
@@ -19,6 +40,96 @@ impl Value {
         }
     }
 
+    pub fn from_bits(bits: u32, format: Format) -> Value {
+        let sign = ((bits >> (format.num_exp_bits + format.num_sig_bits)) & 1) != 0;
+        let exp = (bits >> format.num_sig_bits) & format.exp_max();
+        let sig = bits & format.sig_mask();
+        Value::from_comps(sign, exp, sig, format)
+    }
+
+    pub fn zero(sign: bool, format: Format) -> Value {
+        Value::from_comps(sign, 0, 0, format)
+    }
+
+    pub fn one(format: Format) -> Value {
+        Value::power_of_two(0, format)
+    }
+
+    // Distance from 1.0 to the next larger value
+    pub fn epsilon(format: Format) -> Value {
+        let exp = -(format.num_sig_bits as i32);
+        Value::power_of_two(exp, format)
+    }
+
+    pub fn max_finite(sign: bool, format: Format) -> Value {
+        let (max_exp, max_sig) = format.max_finite_comps();
+        Value::from_comps(sign, max_exp, max_sig, format)
+    }
+
+    pub fn min_normal(sign: bool, format: Format) -> Value {
+        Value::from_comps(sign, 1, 0, format)
+    }
+
+    pub fn min_subnormal(sign: bool, format: Format) -> Value {
+        if format.num_sig_bits == 0 {
+            panic!("Requested format has no subnormal values.");
+        }
+
+        Value::from_comps(sign, 0, 1, format)
+    }
+
+    // Exactly 2^exp, if format can represent it
+    fn power_of_two(exp: i32, format: Format) -> Value {
+        let biased_exp = exp + format.exp_bias;
+        let is_normal = biased_exp >= 1 && biased_exp <= format.max_finite_comps().0 as i32;
+        let is_subnormal = biased_exp <= 0 && biased_exp > -(format.num_sig_bits as i32);
+        if is_normal {
+            Value::from_comps(false, biased_exp as u32, 0, format)
+        } else if is_subnormal {
+            let sig = 1 << (format.num_sig_bits as i32 - 1 + biased_exp);
+            Value::from_comps(false, 0, sig, format)
+        } else {
+            panic!("Requested format cannot represent 2^{}.", exp);
+        }
+    }
+
+    // Canonical quiet NaN for format (positive, quiet bit only for IEEE formats)
+    pub fn quiet_nan(format: Format) -> Value {
+        match format.special_encoding {
+            SpecialEncoding::Ieee => {
+                if format.num_sig_bits == 0 {
+                    panic!("Requested format has no NaN encoding.");
+                }
+
+                Value::from_comps(false, format.exp_max(), format.sig_quiet_bit(), format)
+            }
+            SpecialEncoding::NanOnly => Value::from_comps(false, format.exp_max(), format.sig_mask(), format),
+            SpecialEncoding::FiniteOnly => panic!("Requested format has no NaN encoding."),
+        }
+    }
+
+    // Positive signaling NaN with the smallest payload; only IEEE formats with a quiet bit and a payload bit have one
+    pub fn signaling_nan(format: Format) -> Value {
+        if format.special_encoding != SpecialEncoding::Ieee || format.num_sig_bits < 2 {
+            panic!("Requested format has no signaling NaN encoding.");
+        }
+
+        Value::from_comps(false, format.exp_max(), 1, format)
+    }
+
+    // Infinity for format, or the closest stand-in (NaN/max value) for formats without one
+    pub fn infinity(sign: bool, format: Format) -> Value {
+        match format.special_encoding {
+            SpecialEncoding::Ieee => Value::from_comps(sign, format.exp_max(), 0, format),
+            SpecialEncoding::NanOnly => Value::from_comps(sign, format.exp_max(), format.sig_mask(), format),
+            SpecialEncoding::FiniteOnly => Value::max_finite(sign, format),
+        }
+    }
+
+    pub fn from_f32(x: f32) -> Value {
+        Value::from_bits(x.to_bits(), Format::ieee754_single())
+    }
+
     pub fn to_bits(&self) -> u32 {
         let sign = if self.sign { 1 } else { 0 } << (self.format.num_exp_bits + self.format.num_sig_bits);
         let exp = self.exp << self.format.num_sig_bits;
@@ -26,12 +137,120 @@ impl Value {
         sign | exp | sig
     }
 
+    pub fn to_f32(&self) -> f32 {
+        assert_eq!(self.format, Format::ieee754_single());
+
+        f32::from_bits(self.to_bits())
+    }
+
+    pub fn format(&self) -> &Format {
+        &self.format
+    }
+
+    // Encoding followed by its fields, each in hex padded to its width, eg. "0x3fc00000 (sign 0, exp 0x7f, sig 0x400000)"
+    pub fn to_fields_string(&self) -> String {
+        let hex_width = |num_bits: u32| 2 + num_bits.div_ceil(4) as usize;
+        format!(
+            "{:#0bits_width$x} (sign {}, exp {:#0exp_width$x}, sig {:#0sig_width$x})",
+            self.to_bits(),
+            self.sign as u32,
+            self.exp,
+            self.sig,
+            bits_width = hex_width(self.format.num_storage_bits()),
+            exp_width = hex_width(self.format.num_exp_bits),
+            sig_width = hex_width(self.format.num_sig_bits),
+        )
+    }
+
+    // Quiets a NaN and moves it into format, keeping the payload's most significant bits
+    pub(crate) fn quieted_nan(&self, format: &Format) -> Value {
+        match format.special_encoding {
+            SpecialEncoding::Ieee => {
+                let sig = if format.num_sig_bits >= self.format.num_sig_bits {
+                    self.sig << (format.num_sig_bits - self.format.num_sig_bits)
+                } else {
+                    self.sig >> (self.format.num_sig_bits - format.num_sig_bits)
+                };
+                Value::from_comps(self.sign, format.exp_max(), sig | format.sig_quiet_bit(), format.clone())
+            }
+            SpecialEncoding::NanOnly => Value::from_comps(self.sign, format.exp_max(), format.sig_mask(), format.clone()),
+            SpecialEncoding::FiniteOnly => panic!("Requested format has no NaN encoding."),
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.exp == 0 && self.sig == 0
+    }
+
+    pub fn is_subnormal(&self) -> bool {
+        self.exp == 0 && self.sig != 0
+    }
+
+    pub fn is_normal(&self) -> bool {
+        self.exp != 0 && self.is_finite()
+    }
+
+    pub fn is_finite(&self) -> bool {
+        !self.is_nan() && !self.is_inf()
+    }
+
+    // NaNs in formats with a single NaN encoding are always quiet
+    pub fn is_signaling_nan(&self) -> bool {
+        self.is_nan() && self.format.special_encoding == SpecialEncoding::Ieee && (self.sig & self.format.sig_quiet_bit()) == 0
+    }
+
+    pub fn is_quiet_nan(&self) -> bool {
+        self.is_nan() && !self.is_signaling_nan()
+    }
+
     pub fn is_nan(&self) -> bool {
-        self.exp == self.format.exp_max() && self.sig != 0
+        match self.format.special_encoding {
+            SpecialEncoding::Ieee => self.exp == self.format.exp_max() && self.sig != 0,
+            SpecialEncoding::NanOnly => self.exp == self.format.exp_max() && self.sig == self.format.sig_mask(),
+            SpecialEncoding::FiniteOnly => false,
+        }
     }
 
     pub fn is_inf(&self) -> bool {
-        self.exp == self.format.exp_max() && self.sig == 0
+        match self.format.special_encoding {
+            SpecialEncoding::Ieee => self.exp == self.format.exp_max() && self.sig == 0,
+            SpecialEncoding::NanOnly | SpecialEncoding::FiniteOnly => false,
+        }
+    }
+
+    pub fn classify(&self) -> Class {
+        if self.is_signaling_nan() {
+            Class::SignalingNan
+        } else if self.is_nan() {
+            Class::QuietNan
+        } else if self.is_inf() {
+            if self.sign { Class::NegativeInfinity } else { Class::PositiveInfinity }
+        } else if self.is_zero() {
+            if self.sign { Class::NegativeZero } else { Class::PositiveZero }
+        } else if self.is_subnormal() {
+            if self.sign { Class::NegativeSubnormal } else { Class::PositiveSubnormal }
+        } else if self.sign {
+            Class::NegativeNormal
+        } else {
+            Class::PositiveNormal
+        }
+    }
+
+    // Result of the RISC-V fclass instruction
+    pub fn fclass(&self) -> u32 {
+        self.classify().fclass_mask()
+    }
+
+    // Decodes a finite value into an integer sig (including hidden bit) and an unbiased exponent,
+    // such that the magnitude is exactly sig * 2^exp
+    pub(crate) fn decode_finite(&self) -> (u32, i32) {
+        let format = &self.format;
+        let min_exp = format.min_normal_exp() - format.num_sig_bits as i32;
+        if self.exp == 0 {
+            (self.sig, min_exp)
+        } else {
+            ((1 << format.num_sig_bits) | self.sig, min_exp + self.exp as i32 - 1)
+        }
     }
 }
 
@@ -41,6 +260,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn is_nan() {
         let f = Format::ieee754_single();
 
@@ -70,6 +290,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::bool_assert_comparison)]
     fn is_inf() {
         let f = Format::ieee754_single();
 
@@ -101,4 +322,230 @@ mod tests {
 
         assert_eq!(x.is_inf(), true);
     }
+
+    #[test]
+    fn is_nan_is_inf_nan_only() {
+        let f = Format::ocp_fp8_e4m3();
+
+        let x = Value::from_comps(false, 15, 6, f.clone()); // 448.0
+
+        assert!(!x.is_nan());
+        assert!(!x.is_inf());
+
+        let x = Value::from_comps(false, 15, 0, f.clone()); // 256.0
+
+        assert!(!x.is_nan());
+        assert!(!x.is_inf());
+
+        let x = Value::from_comps(true, 15, 7, f.clone()); // -NaN
+
+        assert!(x.is_nan());
+        assert!(!x.is_inf());
+    }
+
+    #[test]
+    fn is_nan_is_inf_finite_only() {
+        let f = Format::ocp_fp4_e2m1();
+
+        for bits in 0..16 {
+            let x = Value::from_bits(bits, f.clone());
+
+            assert!(!x.is_nan());
+            assert!(!x.is_inf());
+        }
+    }
+
+    #[test]
+    fn special_values() {
+        let f = Format::ieee754_single();
+
+        assert_eq!(Value::zero(false, f.clone()).to_bits(), 0x00000000);
+        assert_eq!(Value::zero(true, f.clone()).to_bits(), 0x80000000);
+        assert_eq!(Value::one(f.clone()).to_bits(), 0x3f800000);
+        assert_eq!(Value::epsilon(f.clone()).to_bits(), 0x34000000);
+        assert_eq!(Value::max_finite(false, f.clone()).to_bits(), 0x7f7fffff);
+        assert_eq!(Value::min_normal(true, f.clone()).to_bits(), 0x80800000);
+        assert_eq!(Value::min_subnormal(false, f.clone()).to_bits(), 0x00000001);
+        assert_eq!(Value::infinity(true, f.clone()).to_bits(), 0xff800000);
+        assert_eq!(Value::quiet_nan(f.clone()).to_bits(), 0x7fc00000);
+        assert_eq!(Value::signaling_nan(f.clone()).to_bits(), 0x7f800001);
+
+        let f = Format::ocp_fp8_e4m3();
+
+        assert_eq!(Value::one(f.clone()).to_bits(), 0x38);
+        assert_eq!(Value::epsilon(f.clone()).to_bits(), 0x20);
+        assert_eq!(Value::max_finite(false, f.clone()).to_bits(), 0x7e);
+        assert_eq!(Value::quiet_nan(f.clone()).to_bits(), 0x7f);
+
+        let f = Format::ocp_fp4_e2m1();
+
+        assert_eq!(Value::one(f.clone()).to_bits(), 0x2);
+        assert_eq!(Value::epsilon(f.clone()).to_bits(), 0x1); // 0.5 (subnormal)
+        assert_eq!(Value::max_finite(true, f.clone()).to_bits(), 0xf);
+        assert_eq!(Value::infinity(false, f.clone()).to_bits(), 0x7);
+
+        // 1.0 is subnormal with a negative bias
+        let f = Format::new(4, 3).with_exp_bias(-1);
+
+        assert_eq!(Value::one(f.clone()).to_bits(), 0x02);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested format has no signaling NaN encoding.")]
+    fn signaling_nan_nan_only() {
+        // Panic
+        let _ = Value::signaling_nan(Format::ocp_fp8_e4m3());
+    }
+
+    #[test]
+    #[should_panic(expected = "Requested format cannot represent 2^0.")]
+    fn one_out_of_range() {
+        // Panic
+        let _ = Value::one(Format::new(4, 3).with_exp_bias(-8));
+    }
+
+    #[test]
+    fn classify() {
+        let f = Format::ieee754_single();
+
+        for &(bits, class, mask) in &[
+            (0xff800000, Class::NegativeInfinity, 0x001), // -inf
+            (0xbf800000, Class::NegativeNormal, 0x002), // -1.0
+            (0x807fffff, Class::NegativeSubnormal, 0x004), // -max subnormal
+            (0x80000000, Class::NegativeZero, 0x008), // -0.0
+            (0x00000000, Class::PositiveZero, 0x010), // 0.0
+            (0x00000001, Class::PositiveSubnormal, 0x020), // min subnormal
+            (0x7f7fffff, Class::PositiveNormal, 0x040), // max value
+            (0x7f800000, Class::PositiveInfinity, 0x080), // +inf
+            (0xff800001, Class::SignalingNan, 0x100), // -sNaN
+            (0x7fc00000, Class::QuietNan, 0x200), // qNaN
+        ] {
+            let x = Value::from_bits(bits, f.clone());
+
+            assert_eq!(x.classify(), class);
+            assert_eq!(x.fclass(), mask);
+        }
+
+        // Max values in formats without infinities are normal
+        assert_eq!(Value::from_bits(0x7e, Format::ocp_fp8_e4m3()).classify(), Class::PositiveNormal);
+        assert_eq!(Value::from_bits(0xff, Format::ocp_fp8_e4m3()).classify(), Class::QuietNan);
+        assert_eq!(Value::from_bits(0xf, Format::ocp_fp4_e2m1()).classify(), Class::NegativeNormal);
+        assert_eq!(Value::from_bits(0x9, Format::ocp_fp4_e2m1()).classify(), Class::NegativeSubnormal);
+    }
+
+    #[test]
+    fn is_finite_normal_subnormal() {
+        let f = Format::ieee754_single();
+
+        let x = Value::from_bits(0x3f800000, f.clone()); // 1.0
+
+        assert!(x.is_finite());
+        assert!(x.is_normal());
+        assert!(!x.is_subnormal());
+        assert!(!x.is_zero());
+
+        let x = Value::from_bits(0x80000001, f.clone()); // -min subnormal
+
+        assert!(x.is_finite());
+        assert!(!x.is_normal());
+        assert!(x.is_subnormal());
+
+        let x = Value::from_bits(0x80000000, f.clone()); // -0.0
+
+        assert!(x.is_finite());
+        assert!(!x.is_normal());
+        assert!(!x.is_subnormal());
+        assert!(x.is_zero());
+
+        let x = Value::from_bits(0x7f800000, f.clone()); // +inf
+
+        assert!(!x.is_finite());
+        assert!(!x.is_normal());
+
+        let x = Value::from_bits(0x7fc00000, f.clone()); // qNaN
+
+        assert!(!x.is_finite());
+        assert!(x.is_quiet_nan());
+        assert!(!x.is_signaling_nan());
+    }
+
+    #[test]
+    fn is_signaling_nan() {
+        let f = Format::ieee754_single();
+
+        assert!(Value::from_bits(0x7f800001, f.clone()).is_signaling_nan());
+        assert!(Value::from_bits(0xffbfffff, f.clone()).is_signaling_nan());
+        assert!(!Value::from_bits(0x7fc00000, f.clone()).is_signaling_nan());
+        assert!(!Value::from_bits(0x7f800000, f.clone()).is_signaling_nan());
+        assert!(!Value::from_bits(0x3f800000, f.clone()).is_signaling_nan());
+
+        // Formats with a single NaN encoding have no signaling NaNs
+        assert!(!Value::from_bits(0x7f, Format::ocp_fp8_e4m3()).is_signaling_nan());
+    }
+
+    #[test]
+    fn quieted_nan() {
+        let single = Format::ieee754_single();
+        let half = Format::new(5, 10);
+
+        let x = Value::from_bits(0xff812345, single.clone()); // -sNaN
+
+        assert_eq!(x.quieted_nan(&single).to_bits(), 0xffc12345);
+        assert_eq!(x.quieted_nan(&half).to_bits(), 0xfe09);
+        assert_eq!(x.quieted_nan(&Format::ocp_fp8_e4m3()).to_bits(), 0xff);
+
+        let x = Value::from_bits(0x7d3f, half.clone()); // sNaN
+
+        assert_eq!(x.quieted_nan(&single).to_bits(), 0x7fe7e000);
+    }
+
+    #[test]
+    fn from_bits() {
+        let x = Value::from_bits(0xc0260000, Format::ieee754_single()); // -2.59375
+
+        assert!(x.sign);
+        assert_eq!(x.exp, 128);
+        assert_eq!(x.sig, 0x260000);
+        assert_eq!(x.to_bits(), 0xc0260000);
+
+        let x = Value::from_bits(0xb, Format::ocp_fp4_e2m1()); // -1.5
+
+        assert!(x.sign);
+        assert_eq!(x.exp, 1);
+        assert_eq!(x.sig, 1);
+        assert_eq!(x.to_bits(), 0xb);
+    }
+
+    #[test]
+    fn f32_round_trip() {
+        for &x in &[0.0f32, -0.0, 1.0, -2.59375, 1.0e-40, f32::MAX, f32::INFINITY] {
+            assert_eq!(Value::from_f32(x).to_f32().to_bits(), x.to_bits());
+        }
+    }
+
+    #[test]
+    fn to_fields_string() {
+        assert_eq!(Value::from_bits(0x3fc00000, Format::ieee754_single()).to_fields_string(), "0x3fc00000 (sign 0, exp 0x7f, sig 0x400000)");
+        assert_eq!(Value::from_bits(0x81, Format::ocp_fp8_e4m3()).to_fields_string(), "0x81 (sign 1, exp 0x0, sig 0x1)");
+        assert_eq!(Value::from_bits(0x7c01, Format::new(5, 10)).to_fields_string(), "0x7c01 (sign 0, exp 0x1f, sig 0x001)");
+    }
+
+    #[test]
+    fn decode_finite() {
+        let x = Value::from_f32(1.5);
+
+        assert_eq!(x.decode_finite(), (0xc00000, -23));
+
+        let x = Value::from_bits(1, Format::ieee754_single()); // smallest denormal
+
+        assert_eq!(x.decode_finite(), (1, -149));
+
+        let x = Value::from_bits(0x1, Format::ocp_fp4_e2m1()); // 0.5
+
+        assert_eq!(x.decode_finite(), (1, -1));
+
+        let x = Value::from_bits(0x3fc00000, Format::ieee754_single().with_exp_bias(100)); // 1.5 * 2^27
+
+        assert_eq!(x.decode_finite(), (0xc00000, 4));
+    }
 }
@@ -0,0 +1,288 @@
+// Differential verification of the ops against the host's native f32 arithmetic, for Format::ieee754_single(). The host
+// computes with round to nearest even and IEEE subnormals, so its results are adjusted to softy's conventions: denormal
+// inputs are treated as zero (DAZ), tiny results (detected before rounding) are flushed to zero (FTZ), and every NaN
+// result becomes the canonical quiet NaN. Only results are compared, since the host's exception flags aren't portably
+// observable.
+
+use std::fmt;
+
+use crate::context::*;
+use crate::format::*;
+use crate::ops;
+use crate::rounding::*;
+use crate::value::*;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HostOp {
+    Addition,
+    Multiplication,
+    FusedMultiplyAdd,
+}
+
+impl HostOp {
+    fn num_sources(self) -> usize {
+        match self {
+            HostOp::Addition | HostOp::Multiplication => 2,
+            HostOp::FusedMultiplyAdd => 3,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            HostOp::Addition => "addition",
+            HostOp::Multiplication => "multiplication",
+            HostOp::FusedMultiplyAdd => "fused_multiply_add",
+        }
+    }
+
+    // The host's result, adjusted to softy's DAZ/FTZ and canonical NaN conventions
+    fn host_result(self, sources: &[u32]) -> u32 {
+        let sources = sources.iter().map(|&bits| denormal_as_zero(f32::from_bits(bits))).collect::<Vec<_>>();
+
+        // Exact (or, for FMA, exact as an unevaluated sum) result in f64, to detect tininess before rounding
+        let (res, exact, exact_error) = match self {
+            HostOp::Addition => (sources[0] + sources[1], sources[0] as f64 + sources[1] as f64, 0.0),
+            HostOp::Multiplication => (sources[0] * sources[1], sources[0] as f64 * sources[1] as f64, 0.0),
+            HostOp::FusedMultiplyAdd => {
+                let product = sources[0] as f64 * sources[1] as f64;
+                let addend = sources[2] as f64;
+                let (sum, error) = two_sum(product, addend);
+                (sources[0].mul_add(sources[1], sources[2]), sum, error)
+            }
+        };
+
+        if res.is_nan() {
+            return Value::quiet_nan(Format::ieee754_single()).to_bits();
+        }
+        if is_tiny(exact, exact_error) {
+            return if exact < 0.0 { 0x80000000 } else { 0 };
+        }
+        res.to_bits()
+    }
+
+    fn softy_result(self, sources: &[u32]) -> u32 {
+        let mut context = Context::new().with_rounding_mode(RoundingMode::NearestEven).with_ieee_rounding(true);
+        let source = |i: usize| Value::from_bits(sources[i], Format::ieee754_single());
+        let res = match self {
+            HostOp::Addition => ops::addition::addition_with_context(source(0), source(1), &mut context),
+            HostOp::Multiplication => ops::multiplication::multiplication_with_context(source(0), source(1), &mut context),
+            HostOp::FusedMultiplyAdd => {
+                ops::fused_multiply_add::fused_multiply_add_with_context(source(0), source(1), source(2), &mut context)
+            }
+        };
+        res.to_bits()
+    }
+}
+
+fn denormal_as_zero(x: f32) -> f32 {
+    if x.is_subnormal() {
+        if x.is_sign_negative() {
+            -0.0
+        } else {
+            0.0
+        }
+    } else {
+        x
+    }
+}
+
+// Knuth's TwoSum: sum + error == a + b exactly
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_virtual = sum - a;
+    let a_virtual = sum - b_virtual;
+    (sum, (a - a_virtual) + (b - b_virtual))
+}
+
+// Whether the nonzero, finite value + error lies strictly below the smallest normal f32 in magnitude
+fn is_tiny(value: f64, error: f64) -> bool {
+    let min_normal = f32::MIN_POSITIVE as f64;
+    if value == 0.0 || !value.is_finite() {
+        return false;
+    }
+
+    value.abs() < min_normal || (value.abs() == min_normal && error != 0.0 && (error < 0.0) != (value < 0.0))
+}
+
+// An op whose result in softy differs from the host's
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Mismatch {
+    pub(crate) op: HostOp,
+    pub(crate) sources: Vec<u32>,
+    pub(crate) host_result: u32,
+    pub(crate) softy_result: u32,
+}
+
+impl Mismatch {
+    pub fn op(&self) -> HostOp {
+        self.op
+    }
+
+    pub fn sources(&self) -> &[u32] {
+        &self.sources
+    }
+
+    pub fn host_result(&self) -> u32 {
+        self.host_result
+    }
+
+    pub fn softy_result(&self) -> u32 {
+        self.softy_result
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}(", self.op.name())?;
+        for (i, &source) in self.sources.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", Value::from_bits(source, Format::ieee754_single()).to_fields_string())?;
+        }
+        write!(f, "): host ")?;
+        write!(f, "{}", Value::from_bits(self.host_result, Format::ieee754_single()).to_fields_string())?;
+        write!(f, ", softy ")?;
+        write!(f, "{}", Value::from_bits(self.softy_result, Format::ieee754_single()).to_fields_string())
+    }
+}
+
+// Encodings where truncation, rounding, DAZ/FTZ and special value handling tend to go wrong
+const EDGE_CASES: [u32; 22] = [
+    0x00000000, // 0
+    0x00000001, // Smallest subnormal
+    0x007fffff, // Largest subnormal
+    0x00800000, // Smallest normal
+    0x00800001, // Smallest normal + 1 ulp
+    0x00ffffff, // Just below 2 * smallest normal
+    0x01000000, // 2 * smallest normal
+    0x1f800000, // 2^-64
+    0x33800000, // 2^-24, half an ulp of 1
+    0x34000000, // 2^-23, an ulp of 1
+    0x3effffff, // Just below 0.5
+    0x3f000000, // 0.5
+    0x3f7fffff, // Just below 1
+    0x3f800000, // 1
+    0x3f800001, // 1 + 1 ulp
+    0x3fffffff, // Just below 2
+    0x4b800000, // 2^24
+    0x5f800000, // 2^64
+    0x7f7fffff, // Largest finite
+    0x7f800000, // Infinity
+    0x7f800001, // Signaling NaN
+    0x7fc00000, // Quiet NaN
+];
+
+// xorshift64*, for reproducible stimulus
+pub(crate) struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub(crate) fn new(seed: u64) -> Random {
+        // Spread the seed's bits (so that nearby seeds differ), and avoid the zero state xorshift gets stuck in
+        Random { state: seed.wrapping_mul(0x9e3779b97f4a7c15) | 1 }
+    }
+
+    pub(crate) fn next_u32(&mut self) -> u32 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545f4914f6cdd1d) >> 32) as u32
+    }
+}
+
+// Checks op on every combination of (both signs of) the edge cases, then on num_random_cases random operands drawn
+// deterministically from seed; returns every mismatch found
+pub fn verify_f32(op: HostOp, num_random_cases: u64, seed: u64) -> Vec<Mismatch> {
+    let mut mismatches = Vec::new();
+    let mut check = |sources: &[u32]| {
+        let host_result = op.host_result(sources);
+        let softy_result = op.softy_result(sources);
+        if host_result != softy_result {
+            mismatches.push(Mismatch {
+                op,
+                sources: sources.to_vec(),
+                host_result,
+                softy_result,
+            });
+        }
+    };
+
+    let edge_cases = EDGE_CASES.iter().flat_map(|&bits| vec![bits, bits | 0x80000000]).collect::<Vec<_>>();
+    let num_sources = op.num_sources();
+    let num_edge_combinations = edge_cases.len().pow(num_sources as u32);
+    for mut index in 0..num_edge_combinations {
+        let mut sources = Vec::with_capacity(num_sources);
+        for _ in 0..num_sources {
+            sources.push(edge_cases[index % edge_cases.len()]);
+            index /= edge_cases.len();
+        }
+        check(&sources);
+    }
+
+    let mut random = Random::new(seed);
+    for _ in 0..num_random_cases {
+        let mut sources = (0..num_sources).map(|_| random.next_u32()).collect::<Vec<_>>();
+        // Uniformly random bits rarely make results cancel or land near each other, so sometimes pull a source's
+        // exponent close to the first's
+        if num_sources > 1 && random.next_u32() & 1 != 0 {
+            let i = 1 + (random.next_u32() as usize) % (num_sources - 1);
+            let exp = ((sources[0] >> 23) & 0xff) as i32 + (random.next_u32() % 5) as i32 - 2;
+            sources[i] = (sources[i] & !(0xff << 23)) | ((exp.clamp(0, 0xff) as u32) << 23);
+        }
+        check(&sources);
+    }
+
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_f32_ops() {
+        for &op in &[HostOp::Addition, HostOp::Multiplication, HostOp::FusedMultiplyAdd] {
+            let mismatches = verify_f32(op, 100000, 0x5eed);
+
+            assert!(mismatches.is_empty(), "{}", mismatches.iter().map(|m| m.to_string()).collect::<Vec<_>>().join("\n"));
+        }
+    }
+
+    #[test]
+    fn host_result() {
+        // Denormal inputs are zero
+        assert_eq!(HostOp::Addition.host_result(&[0x00000001, 0x00000001]), 0x00000000);
+        assert_eq!(HostOp::Multiplication.host_result(&[0x80000001, 0x3f800000]), 0x80000000);
+
+        // Subnormal results flush to zero
+        assert_eq!(HostOp::Addition.host_result(&[0x00800001, 0x80800000]), 0x00000000);
+        assert_eq!(HostOp::Multiplication.host_result(&[0x80800000, 0x3f000000]), 0x80000000);
+
+        // (1 - 2^-24) * 2^-126 is tiny, even though it rounds to 2^-126
+        assert_eq!(HostOp::Multiplication.host_result(&[0x3f7fffff, 0x00800000]), 0x00000000);
+        assert_eq!(HostOp::FusedMultiplyAdd.host_result(&[0x3f7fffff, 0x00800000, 0x80000000]), 0x00000000);
+        assert_eq!(HostOp::FusedMultiplyAdd.host_result(&[0x3f7fffff, 0x01000000, 0x80800000]), 0x00000000);
+
+        // NaNs are canonical
+        assert_eq!(HostOp::Addition.host_result(&[0xff800001, 0x3f800000]), 0x7fc00000);
+        assert_eq!(HostOp::Multiplication.host_result(&[0x7f800000, 0x00000000]), 0x7fc00000);
+    }
+
+    #[test]
+    fn mismatch_display() {
+        let mismatch = Mismatch {
+            op: HostOp::Addition,
+            sources: vec![0x3f800000, 0x33800001],
+            host_result: 0x3f800001,
+            softy_result: 0x3f800000,
+        };
+
+        assert_eq!(
+            mismatch.to_string(),
+            "addition(0x3f800000 (sign 0, exp 0x7f, sig 0x000000), 0x33800001 (sign 0, exp 0x67, sig 0x000001)): \
+             host 0x3f800001 (sign 0, exp 0x7f, sig 0x000001), softy 0x3f800000 (sign 0, exp 0x7f, sig 0x000000)"
+        );
+    }
+}
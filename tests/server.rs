@@ -0,0 +1,80 @@
+// Drives the softy-server binary as a simulator would, through its stdin/stdout and (on Unix) through a Unix socket
+
+#[cfg(unix)]
+use std::env;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::process::Command;
+use std::process::Stdio;
+use std::thread;
+#[cfg(unix)]
+use std::time::Duration;
+
+const SERVER: &str = env!("CARGO_BIN_EXE_softy-server");
+
+fn requests() -> (String, Vec<String>) {
+    let mut requests = String::new();
+    let mut replies = Vec::new();
+    for i in 0..1000u32 {
+        // (1 + i * 2^-10) + 1 drops the last bit, rounding ties to even
+        let sig = i & 0x3ff;
+        requests.push_str(&format!("addition e5m10 0 0 {:04x} 3c00\n", 0x3c00 | sig));
+        let rounded = (sig >> 1) + (sig & (sig >> 1) & 1);
+        replies.push(format!("{:04x} {:02x}", 0x4000 + rounded, sig & 1));
+    }
+    (requests, replies)
+}
+
+#[test]
+fn stdio() {
+    let mut server = Command::new(SERVER).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    let (requests, expected) = requests();
+
+    let mut stdin = server.stdin.take().unwrap();
+    let writer = thread::spawn(move || stdin.write_all(requests.as_bytes()).unwrap());
+    let replies = BufReader::new(server.stdout.take().unwrap()).lines().map(Result::unwrap).collect::<Vec<_>>();
+    writer.join().unwrap();
+
+    assert!(server.wait().unwrap().success());
+    assert_eq!(replies, expected);
+}
+
+#[cfg(unix)]
+#[test]
+fn socket() {
+    let path = env::temp_dir().join(format!("softy-server-test-{}.sock", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut server = Command::new(SERVER).arg("--socket").arg(&path).spawn().unwrap();
+
+    let mut stream = None;
+    for _ in 0..500 {
+        if let Ok(connected) = UnixStream::connect(&path) {
+            stream = Some(connected);
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    let mut stream = stream.expect("Couldn't connect to softy-server.");
+
+    // A batch, then a single request on the same connection
+    let (requests, expected) = requests();
+    stream.write_all(requests.as_bytes()).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    for expected in &expected {
+        let mut reply = String::new();
+        reader.read_line(&mut reply).unwrap();
+        assert_eq!(reply.trim_end(), expected);
+    }
+    stream.write_all(b"multiplication e8m23 0 0 3fc00000 3fc00000\n").unwrap();
+    let mut reply = String::new();
+    reader.read_line(&mut reply).unwrap();
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(reply, "40100000 00\n");
+}